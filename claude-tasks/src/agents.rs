@@ -0,0 +1,82 @@
+//! Per-agent layout for Claude agent teams.
+//!
+//! When subagents or teammates work against a shared tasklist, tasks name
+//! their owner. With `group_by_agent`, tasks are nested under one sub-header
+//! per agent below the tasklist header, and each sub-header shows what that
+//! agent is working on right now. Unowned tasks stay directly under the
+//! tasklist header.
+//!
+//! The layout is synced by the reconciliation engine
+//! ([`crate::reconcile`]) along with the tasks: owners change as agents
+//! claim tasks and todos can't be moved, so claimed tasks are recreated
+//! under their new agent.
+
+use crate::claude_task::ClaudeTask;
+use crate::commands::agent_header_id;
+use crate::discovery::active_task;
+use std::collections::{BTreeMap, HashMap};
+
+/// Agent sub-headers and task placements currently displayed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AgentLayout {
+    /// Label shown on each agent's sub-header
+    pub labels: BTreeMap<String, String>,
+    /// Agent each synced task is nested under (unowned tasks are absent)
+    pub placements: HashMap<String, String>,
+}
+
+impl AgentLayout {
+    /// Todo IDs of the displayed agent sub-headers.
+    pub fn header_ids(&self, tasklist_id: &str) -> Vec<String> {
+        self.labels
+            .keys()
+            .map(|agent| agent_header_id(tasklist_id, agent))
+            .collect()
+    }
+}
+
+/// Format an agent sub-header: what the agent is working on right now.
+///
+/// - "AGENT {name}: ▶ {active form}" while a task is in progress
+/// - "AGENT {name}: done" once all its tasks are completed
+/// - "AGENT {name}: idle" otherwise
+pub fn agent_label(agent: &str, tasks: &[&ClaudeTask]) -> String {
+    let activity = match active_task(tasks.iter().copied()) {
+        Some(active) => format!("\u{25B6} {}", active),
+        None if tasks.iter().all(|t| t.status == "completed") => "done".to_string(),
+        None => "idle".to_string(),
+    };
+    format!("AGENT {}: {}", agent, activity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_task(id: &str, status: &str, owner: Option<&str>) -> ClaudeTask {
+        ClaudeTask {
+            id: id.to_string(),
+            subject: format!("Task {}", id),
+            description: String::new(),
+            active_form: format!("Doing {}", id),
+            status: status.to_string(),
+            blocks: vec![],
+            blocked_by: vec![],
+            owner: owner.map(String::from),
+        }
+    }
+
+    #[test]
+    fn test_agent_label() {
+        let working = make_task("1", "in_progress", Some("a"));
+        let done = make_task("2", "completed", Some("a"));
+        let pending = make_task("3", "pending", Some("a"));
+
+        assert_eq!(
+            agent_label("a", &[&done, &working]),
+            "AGENT a: \u{25B6} Doing 1"
+        );
+        assert_eq!(agent_label("a", &[&done]), "AGENT a: done");
+        assert_eq!(agent_label("a", &[&done, &pending]), "AGENT a: idle");
+    }
+}
@@ -0,0 +1,421 @@
+//! Archiving of synced tasklists into ordinary totui todos.
+//!
+//! Synced todos are owned by the plugin: they carry `read_only` metadata and
+//! are deleted again when Claude removes the task files. Archiving copies the
+//! current header and tasks into fresh todos without any plugin metadata, then
+//! deletes the synced mirror so the plugin can detach from the tasklist.
+//!
+//! Archived tasklist IDs are recorded in `claude-tasks-archived.txt` so the
+//! same session is not re-synced (and re-archived) after a restart. The
+//! `unarchive` command removes a tasklist from the record; automatic
+//! archives also end by themselves once Claude writes to the tasklist again.
+
+use crate::claude_task::{map_status_to_state, ClaudeTask};
+use crate::commands::{delete_todo_command, header_id, task_todo_id};
+use crate::paths::plugin_paths;
+use abi_stable::std_types::{ROption, RString};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use totui_plugin_interface::{FfiCommand, FfiTodoState};
+use uuid::Uuid;
+
+/// Why a tasklist was archived automatically.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveReason {
+    /// Every task in the tasklist is completed
+    AllComplete,
+    /// The tasklist has not been updated within the staleness threshold
+    Stale,
+}
+
+/// Decide whether a tasklist should be archived automatically.
+///
+/// - `archive_on_complete`: all tasks completed (and at least one task exists)
+/// - `archive_on_stale`: tasklist is stale
+///
+/// Completion takes precedence over staleness when both apply.
+pub fn should_auto_archive(
+    tasks: &[ClaudeTask],
+    is_stale: bool,
+    archive_on_complete: bool,
+    archive_on_stale: bool,
+) -> Option<ArchiveReason> {
+    let all_complete = !tasks.is_empty() && tasks.iter().all(|t| t.status == "completed");
+
+    if archive_on_complete && all_complete {
+        Some(ArchiveReason::AllComplete)
+    } else if archive_on_stale && is_stale && !tasks.is_empty() {
+        Some(ArchiveReason::Stale)
+    } else {
+        None
+    }
+}
+
+/// Create commands that archive a synced tasklist.
+///
+/// Produces:
+/// 1. CreateTodo for an archive header: "ARCHIVED CLAUDE TASKLIST: {name} (N/M done)"
+/// 2. CreateTodo for each task under the archive header (no metadata, so the
+///    todos are ordinary user-owned todos)
+/// 3. DeleteTodo for each synced task todo in `synced_task_ids`
+/// 4. DeleteTodo for each agent sub-header in `agent_header_ids`
+/// 5. DeleteTodo for the synced header
+///
+/// Archive todos use fresh `claude-archive-{uuid}` temp IDs so they never
+/// collide with the predictable IDs used by the sync engine.
+pub fn create_archive_commands(
+    tasks: &[ClaudeTask],
+    tasklist_id: &str,
+    display_name: Option<&str>,
+    synced_task_ids: &[String],
+    agent_header_ids: &[String],
+) -> Vec<FfiCommand> {
+    let name = display_name.unwrap_or(tasklist_id);
+    let completed = tasks.iter().filter(|t| t.status == "completed").count();
+    let title = format!(
+        "ARCHIVED CLAUDE TASKLIST: {} ({}/{} done)",
+        name,
+        completed,
+        tasks.len()
+    );
+    let entries: Vec<(String, FfiTodoState)> = tasks
+        .iter()
+        .map(|t| (t.subject.clone(), map_status_to_state(&t.status)))
+        .collect();
+
+    let mut commands = create_archive_group_commands(&title, &entries);
+
+    // Remove the synced mirror: children first, then the header
+    for task_id in synced_task_ids {
+        commands.push(delete_todo_command(&task_todo_id(tasklist_id, task_id)));
+    }
+    for agent_header_id in agent_header_ids {
+        commands.push(delete_todo_command(agent_header_id));
+    }
+    commands.push(delete_todo_command(&header_id(tasklist_id)));
+
+    commands
+}
+
+/// Create an archive header with one child todo per entry.
+///
+/// Entries are `(content, state)` pairs. No metadata is attached, so the
+/// created todos are ordinary user-owned todos.
+pub fn create_archive_group_commands(
+    title: &str,
+    entries: &[(String, FfiTodoState)],
+) -> Vec<FfiCommand> {
+    let mut commands = Vec::new();
+    let archive_header_id = new_archive_id();
+
+    commands.push(FfiCommand::CreateTodo {
+        content: RString::from(title),
+        parent_id: ROption::RNone,
+        temp_id: ROption::RSome(RString::from(archive_header_id.clone())),
+        state: FfiTodoState::Empty,
+        priority: ROption::RNone,
+        indent_level: 0,
+    });
+
+    for (content, state) in entries {
+        commands.push(FfiCommand::CreateTodo {
+            content: RString::from(content.as_str()),
+            parent_id: ROption::RSome(RString::from(archive_header_id.clone())),
+            temp_id: ROption::RSome(RString::from(new_archive_id())),
+            state: *state,
+            priority: ROption::RNone,
+            indent_level: 1,
+        });
+    }
+
+    commands
+}
+
+/// Generate a fresh temp ID for an archived todo.
+fn new_archive_id() -> String {
+    format!("claude-archive-{}", Uuid::new_v4())
+}
+
+/// Get the archive record path: {cache_dir}/claude-tasks-archived.txt
+fn archive_record_path() -> Option<PathBuf> {
+    plugin_paths()
+        .cache_dir
+        .map(|p| p.join("claude-tasks-archived.txt"))
+}
+
+/// An entry of the archive record.
+///
+/// Lines are `{id}` for a tasklist archived with the `archive` command and
+/// `{id} {ms}` for an automatic archive, where `ms` is the tasklist's last
+/// activity (milliseconds since the epoch) when it was archived.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ArchiveEntry {
+    tasklist_id: String,
+    /// Archived until the tasklist changes after this time (automatic archives)
+    until_activity_after: Option<u128>,
+}
+
+impl ArchiveEntry {
+    fn parse(line: &str) -> Option<Self> {
+        let mut parts = line.split_whitespace();
+        let tasklist_id = parts.next()?.to_string();
+        let until_activity_after = parts.next().and_then(|ms| ms.parse().ok());
+        Some(Self {
+            tasklist_id,
+            until_activity_after,
+        })
+    }
+
+    fn to_line(&self) -> String {
+        match self.until_activity_after {
+            Some(ms) => format!("{} {}", self.tasklist_id, ms),
+            None => self.tasklist_id.clone(),
+        }
+    }
+
+    /// Whether the entry still holds for a tasklist last active at `last_activity`.
+    fn holds(&self, last_activity: SystemTime) -> bool {
+        self.until_activity_after
+            .is_none_or(|ms| epoch_millis(last_activity) <= ms)
+    }
+}
+
+fn epoch_millis(time: SystemTime) -> u128 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0)
+}
+
+fn read_record(path: &Path) -> Vec<ArchiveEntry> {
+    std::fs::read_to_string(path)
+        .map(|content| content.lines().filter_map(ArchiveEntry::parse).collect())
+        .unwrap_or_default()
+}
+
+fn write_record(path: &Path, entries: &[ArchiveEntry]) -> std::io::Result<()> {
+    let content: String = entries.iter().map(|e| e.to_line() + "\n").collect();
+    std::fs::write(path, content)
+}
+
+/// Check whether a tasklist is archived.
+///
+/// `last_activity` is when the tasklist last changed on disk (see
+/// [`crate::discovery::last_activity`]): an automatic archive only holds
+/// until Claude writes to the tasklist again.
+pub fn is_archived(tasklist_id: &str, last_activity: SystemTime) -> bool {
+    archive_record_path().is_some_and(|path| is_archived_in(&path, tasklist_id, last_activity))
+}
+
+fn is_archived_in(path: &Path, tasklist_id: &str, last_activity: SystemTime) -> bool {
+    read_record(path)
+        .iter()
+        .any(|e| e.tasklist_id == tasklist_id && e.holds(last_activity))
+}
+
+/// Record a tasklist as archived.
+///
+/// With `until_activity_after` (automatic archives), the tasklist is synced
+/// again once it changes after that time; otherwise it stays archived until
+/// `unarchive`. Failures are ignored - the worst case is that the tasklist
+/// is synced again after a restart.
+pub fn record_archived(tasklist_id: &str, until_activity_after: Option<SystemTime>) {
+    if let Some(path) = archive_record_path() {
+        let _ = record_archived_in(&path, tasklist_id, until_activity_after);
+    }
+}
+
+fn record_archived_in(
+    path: &Path,
+    tasklist_id: &str,
+    until_activity_after: Option<SystemTime>,
+) -> std::io::Result<()> {
+    let mut entries = read_record(path);
+    entries.retain(|e| e.tasklist_id != tasklist_id);
+    entries.push(ArchiveEntry {
+        tasklist_id: tasklist_id.to_string(),
+        until_activity_after: until_activity_after.map(epoch_millis),
+    });
+    write_record(path, &entries)
+}
+
+/// Remove a tasklist from the archive record, so it can be synced again.
+///
+/// Returns whether the tasklist was recorded.
+pub fn remove_archived(tasklist_id: &str) -> Result<bool, String> {
+    let Some(path) = archive_record_path() else {
+        return Ok(false);
+    };
+    remove_archived_in(&path, tasklist_id).map_err(|e| e.to_string())
+}
+
+fn remove_archived_in(path: &Path, tasklist_id: &str) -> std::io::Result<bool> {
+    let mut entries = read_record(path);
+    let before = entries.len();
+    entries.retain(|e| e.tasklist_id != tasklist_id);
+    if entries.len() == before {
+        return Ok(false);
+    }
+    write_record(path, &entries)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_task(id: &str, subject: &str, status: &str) -> ClaudeTask {
+        ClaudeTask {
+            id: id.to_string(),
+            subject: subject.to_string(),
+            description: String::new(),
+            active_form: String::new(),
+            status: status.to_string(),
+            blocks: vec![],
+            blocked_by: vec![],
+            owner: None,
+        }
+    }
+
+    #[test]
+    fn test_should_auto_archive_all_complete() {
+        let tasks = vec![
+            make_task("1", "A", "completed"),
+            make_task("2", "B", "completed"),
+        ];
+        assert_eq!(
+            should_auto_archive(&tasks, false, true, false),
+            Some(ArchiveReason::AllComplete)
+        );
+        // Disabled trigger
+        assert_eq!(should_auto_archive(&tasks, false, false, false), None);
+    }
+
+    #[test]
+    fn test_should_auto_archive_incomplete() {
+        let tasks = vec![
+            make_task("1", "A", "completed"),
+            make_task("2", "B", "in_progress"),
+        ];
+        assert_eq!(should_auto_archive(&tasks, false, true, false), None);
+    }
+
+    #[test]
+    fn test_should_auto_archive_stale() {
+        let tasks = vec![make_task("1", "A", "pending")];
+        assert_eq!(
+            should_auto_archive(&tasks, true, false, true),
+            Some(ArchiveReason::Stale)
+        );
+        assert_eq!(should_auto_archive(&tasks, false, false, true), None);
+    }
+
+    #[test]
+    fn test_should_auto_archive_empty_tasklist() {
+        assert_eq!(should_auto_archive(&[], true, true, true), None);
+    }
+
+    #[test]
+    fn test_create_archive_commands_header() {
+        let tasks = vec![
+            make_task("1", "Done", "completed"),
+            make_task("2", "Todo", "pending"),
+        ];
+        let cmds = create_archive_commands(&tasks, "list-1", Some("MyProject"), &[], &[]);
+
+        match &cmds[0] {
+            FfiCommand::CreateTodo {
+                content,
+                temp_id,
+                parent_id,
+                indent_level,
+                ..
+            } => {
+                assert_eq!(
+                    content.as_str(),
+                    "ARCHIVED CLAUDE TASKLIST: MyProject (1/2 done)"
+                );
+                assert!(
+                    matches!(temp_id, ROption::RSome(ref s) if s.as_str().starts_with("claude-archive-"))
+                );
+                assert!(matches!(parent_id, ROption::RNone));
+                assert_eq!(*indent_level, 0);
+            }
+            _ => panic!("Expected CreateTodo command"),
+        }
+    }
+
+    #[test]
+    fn test_create_archive_commands_tasks_have_no_metadata() {
+        let tasks = vec![make_task("1", "Done", "completed")];
+        let cmds = create_archive_commands(&tasks, "list-1", None, &[], &[]);
+
+        assert!(!cmds
+            .iter()
+            .any(|c| matches!(c, FfiCommand::SetTodoMetadata { .. })));
+
+        match &cmds[1] {
+            FfiCommand::CreateTodo {
+                content,
+                state,
+                indent_level,
+                ..
+            } => {
+                assert_eq!(content.as_str(), "Done");
+                assert!(matches!(state, FfiTodoState::Checked));
+                assert_eq!(*indent_level, 1);
+            }
+            _ => panic!("Expected CreateTodo command"),
+        }
+    }
+
+    #[test]
+    fn test_create_archive_commands_deletes_synced_mirror() {
+        let tasks = vec![make_task("1", "A", "completed")];
+        let synced = vec!["1".to_string()];
+        let cmds = create_archive_commands(&tasks, "list-1", None, &synced, &[]);
+
+        // header + 1 task + 1 task delete + header delete
+        assert_eq!(cmds.len(), 4);
+        match &cmds[2] {
+            FfiCommand::DeleteTodo { id } => assert_eq!(id.as_str(), "claude-list-1-1"),
+            _ => panic!("Expected DeleteTodo command"),
+        }
+        match &cmds[3] {
+            FfiCommand::DeleteTodo { id } => assert_eq!(id.as_str(), "claude-header-list-1"),
+            _ => panic!("Expected DeleteTodo command"),
+        }
+    }
+
+    #[test]
+    fn test_archive_record() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("archived.txt");
+        let now = SystemTime::now();
+
+        assert!(!is_archived_in(&path, "list-1", now));
+        record_archived_in(&path, "list-1", None).unwrap();
+        record_archived_in(&path, "list-1", None).unwrap();
+        assert!(is_archived_in(&path, "list-1", now));
+        assert!(!is_archived_in(&path, "list-2", now));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "list-1\n");
+
+        assert!(remove_archived_in(&path, "list-1").unwrap());
+        assert!(!remove_archived_in(&path, "list-1").unwrap());
+        assert!(!is_archived_in(&path, "list-1", now));
+    }
+
+    #[test]
+    fn test_auto_archive_ends_with_new_activity() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("archived.txt");
+        let archived_at = UNIX_EPOCH + std::time::Duration::from_secs(1000);
+
+        record_archived_in(&path, "list-1", Some(archived_at)).unwrap();
+        assert!(is_archived_in(&path, "list-1", archived_at));
+        assert!(!is_archived_in(
+            &path,
+            "list-1",
+            archived_at + std::time::Duration::from_secs(1)
+        ));
+    }
+}
@@ -0,0 +1,445 @@
+//! Inspect Claude tasklists without loading the plugin into totui.
+//!
+//! Uses the plugin's own discovery, hierarchy and sync code, so what it
+//! prints is what the plugin sees:
+//!
+//! - `list`: discovered tasklists, most recent first
+//! - `tree`: a tasklist as the plugin would show it, with blocked and cycle
+//!   annotations
+//! - `validate`: task files that fail to parse, dangling `blockedBy`
+//!   references and cycles
+//! - `watch`: changes as the watcher reports them, with the commands each
+//!   change produces
+//! - `plan`: the commands the plugin emits when it first syncs the tasklist
+//!
+//! Tasklists are referenced like in the `switch` command (UUID, alias or
+//! UUID prefix) and default to the most recently active one. Task roots and
+//! aliases come from the plugin config; `--root` replaces the task roots,
+//! e.g. to inspect a tasklist attached to a bug report.
+
+use abi_stable::std_types::ROption;
+use claude_tasks::cache::DiscoveryCache;
+use claude_tasks::claude_task::{map_status_to_state, ClaudeTask};
+use claude_tasks::commands::format_hierarchy_content;
+use claude_tasks::config::{format_tasklist_display, load_config, tasklist_name, PluginConfig};
+use claude_tasks::discovery::{
+    discover_tasklists_in, is_legacy_todo_file, scan_tasklist, task_files, TasklistInfo,
+};
+use claude_tasks::hierarchy::build_hierarchy;
+use claude_tasks::prompt::resolve_tasklist;
+use claude_tasks::reconcile::Snapshot;
+use claude_tasks::staleness::format_duration;
+use claude_tasks::state::SyncEvent;
+use claude_tasks::sync;
+use claude_tasks::watcher::start_watcher;
+use std::collections::HashSet;
+use std::path::Path;
+use std::process::ExitCode;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::SystemTime;
+use totui_plugin_interface::{FfiCommand, FfiTodoState};
+
+const USAGE: &str = "\
+Usage: claude-tasks-inspect [--root DIR]... <command> [tasklist]
+
+Commands:
+  list                 List discovered tasklists
+  tree [tasklist]      Print a tasklist as a tree
+  validate [tasklist]  Check task files for problems
+  watch [tasklist]     Print changes as they happen
+  plan [tasklist]      Print the commands an initial sync emits
+
+A tasklist is a UUID, alias or UUID prefix (default: most recent).
+--root replaces the configured task roots.";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match run(&args) {
+        Ok(code) => code,
+        Err(message) => {
+            eprintln!("{}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: &[String]) -> Result<ExitCode, String> {
+    let mut config = load_config();
+    let mut roots = Vec::new();
+    let mut rest = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--root" => roots.push(args.next().ok_or("--root needs a folder")?.clone()),
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(ExitCode::SUCCESS);
+            }
+            _ => rest.push(arg.as_str()),
+        }
+    }
+    if !roots.is_empty() {
+        config.task_roots = Some(roots);
+    }
+
+    let tasklists = discover_tasklists_in(&config.task_roots(), &mut DiscoveryCache::default());
+    let (command, query) = match rest.as_slice() {
+        [command] => (*command, None),
+        [command, query] => (*command, Some(*query)),
+        _ => return Err(USAGE.to_string()),
+    };
+    if command == "list" {
+        list(&tasklists, &config);
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let tasklist = match query {
+        Some(query) => resolve_tasklist(query, &tasklists, &config)?,
+        None => tasklists.first().ok_or("No tasklists found")?,
+    };
+    let name = tasklist_name(&tasklist.id, &config);
+    match command {
+        "tree" => {
+            let tasks = scan_tasklist(&tasklist.path);
+            for line in render_tree(&tasklist.id, name.as_deref(), &tasks) {
+                println!("{}", line);
+            }
+        }
+        "validate" => {
+            let problems = validate(&tasklist.path);
+            for problem in &problems {
+                println!("{}", problem);
+            }
+            if !problems.is_empty() {
+                return Ok(ExitCode::FAILURE);
+            }
+            println!("{}: no problems found", tasklist.id);
+        }
+        "watch" => watch(tasklist, name.as_deref(), &config)?,
+        "plan" => {
+            let outcome = sync::sync_tasklist(
+                &tasklist.path,
+                &tasklist.id,
+                Snapshot::default(),
+                name.as_deref(),
+                sync::layout(&config, &tasklist.path),
+            );
+            for command in &outcome.commands {
+                println!("{}", describe_command(command));
+            }
+        }
+        _ => return Err(format!("Unknown command '{}'\n\n{}", command, USAGE)),
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Print the discovered tasklists, most recent first.
+fn list(tasklists: &[TasklistInfo], config: &PluginConfig) {
+    if tasklists.is_empty() {
+        println!("No tasklists found");
+    }
+    for tasklist in tasklists {
+        let age = SystemTime::now()
+            .duration_since(tasklist.last_modified)
+            .map(|elapsed| format!("{} ago", format_duration(elapsed)))
+            .unwrap_or_else(|_| "just now".to_string());
+        let summary = tasklist.summary();
+        println!(
+            "{}  {}/{} done  {}",
+            format_tasklist_display(&tasklist.id, config),
+            summary.counts.completed,
+            summary.task_count,
+            age
+        );
+        if let Some(active) = &summary.active_task {
+            println!("    now: {}", active);
+        }
+    }
+}
+
+/// Checkbox of a todo state, as totui shows it.
+fn state_marker(state: FfiTodoState) -> &'static str {
+    match state {
+        FfiTodoState::Empty => "[ ]",
+        FfiTodoState::Checked => "[x]",
+        FfiTodoState::InProgress => "[*]",
+        FfiTodoState::Question => "[?]",
+        FfiTodoState::Exclamation => "[!]",
+        FfiTodoState::Cancelled => "[-]",
+    }
+}
+
+/// Render a tasklist like the todos the plugin creates for it.
+fn render_tree(tasklist_id: &str, name: Option<&str>, tasks: &[ClaudeTask]) -> Vec<String> {
+    let hierarchy = build_hierarchy(tasks);
+    let mut lines = vec![format!("CLAUDE TASKLIST: {}", name.unwrap_or(tasklist_id))];
+    for task in tasks {
+        let mut line = format!(
+            "  {} {}",
+            state_marker(map_status_to_state(&task.status)),
+            format_hierarchy_content(task, &hierarchy)
+        );
+        if let Some(owner) = &task.owner {
+            line.push_str(&format!("  @{}", owner));
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// Find problems in the task files of a tasklist.
+///
+/// Reports files that fail to parse (the plugin skips them), tasks whose ID
+/// doesn't match their file name, unknown statuses, `blockedBy` references
+/// to missing tasks and dependency cycles.
+fn validate(tasklist_path: &Path) -> Vec<String> {
+    let mut problems = Vec::new();
+    let (_, unreadable) = task_files(tasklist_path);
+    for file in &unreadable {
+        let error = match std::fs::read_to_string(file) {
+            Err(e) => e.to_string(),
+            Ok(_) if is_legacy_todo_file(tasklist_path) => "not a TodoWrite list".to_string(),
+            Ok(content) => serde_json::from_str::<ClaudeTask>(&content)
+                .err()
+                .map(|e| e.to_string())
+                .unwrap_or_default(),
+        };
+        problems.push(format!("{}: unreadable: {}", file.display(), error));
+    }
+
+    let tasks = scan_tasklist(tasklist_path);
+    let ids: HashSet<&str> = tasks.iter().map(|task| task.id.as_str()).collect();
+    let hierarchy = build_hierarchy(&tasks);
+    for task in &tasks {
+        let file = tasklist_path.join(format!("{}.json", task.id));
+        if !is_legacy_todo_file(tasklist_path) && !file.exists() {
+            problems.push(format!("task {}: not in {}.json", task.id, task.id));
+        }
+        if !matches!(
+            task.status.as_str(),
+            "pending" | "in_progress" | "completed"
+        ) {
+            problems.push(format!(
+                "task {}: unknown status '{}'",
+                task.id, task.status
+            ));
+        }
+        for blocker in &task.blocked_by {
+            if !ids.contains(blocker.as_str()) {
+                problems.push(format!(
+                    "task {}: blocked by missing task {}",
+                    task.id, blocker
+                ));
+            }
+        }
+        if hierarchy.is_cyclic(&task.id) {
+            problems.push(format!("task {}: part of a dependency cycle", task.id));
+        }
+    }
+    problems
+}
+
+/// Print file changes and the commands they produce until interrupted.
+fn watch(tasklist: &TasklistInfo, name: Option<&str>, config: &PluginConfig) -> Result<(), String> {
+    let (tx, rx) = mpsc::channel();
+    let _handle = start_watcher(
+        tasklist.path.clone(),
+        tx,
+        Arc::new(Mutex::new(None)),
+        config.watch_mode(),
+    )?;
+
+    let layout = sync::layout(config, &tasklist.path);
+    let snapshot = Snapshot::default();
+    let outcome = sync::sync_tasklist(&tasklist.path, &tasklist.id, snapshot, name, layout);
+    let mut known: HashSet<String> = outcome.synced.into_iter().collect();
+    let mut agent_layout = outcome.agent_layout.unwrap_or_default();
+    println!(
+        "Watching {} ({} tasks), Ctrl-C to stop",
+        tasklist.path.display(),
+        known.len()
+    );
+
+    for event in rx {
+        let path = match &event {
+            SyncEvent::FileChanged(path) => {
+                println!("changed {}", path.display());
+                path.clone()
+            }
+            SyncEvent::FileRemoved(path) => {
+                println!("removed {}", path.display());
+                path.clone()
+            }
+            SyncEvent::InitialScan | SyncEvent::ConfigChanged => continue,
+        };
+
+        let snapshot = Snapshot::from_known(&known, &agent_layout, &tasklist.id);
+        let outcome = if is_legacy_todo_file(&tasklist.path) {
+            known.clear();
+            sync::sync_tasklist(&tasklist.path, &tasklist.id, snapshot, name, layout)
+        } else {
+            let paths = [path];
+            sync::sync_task_files(
+                &tasklist.path,
+                &tasklist.id,
+                &paths,
+                &snapshot,
+                name,
+                layout,
+            )
+        };
+        for task_id in &outcome.removed {
+            known.remove(task_id);
+        }
+        known.extend(outcome.synced);
+        if let Some(updated) = outcome.agent_layout {
+            agent_layout = updated;
+        }
+        for command in &outcome.commands {
+            println!("  {}", describe_command(command));
+        }
+    }
+    Ok(())
+}
+
+/// Describe a command on one line, for plans and bug reports.
+fn describe_command(command: &FfiCommand) -> String {
+    match command {
+        FfiCommand::CreateTodo {
+            content,
+            parent_id,
+            temp_id,
+            state,
+            indent_level,
+            ..
+        } => {
+            let id = match temp_id {
+                ROption::RSome(id) => id.as_str(),
+                ROption::RNone => "-",
+            };
+            let parent = match parent_id {
+                ROption::RSome(parent) => format!(" under {}", parent),
+                ROption::RNone => String::new(),
+            };
+            format!(
+                "create {}{} (indent {}): {} {}",
+                id,
+                parent,
+                indent_level,
+                state_marker(*state),
+                content
+            )
+        }
+        FfiCommand::UpdateTodo {
+            id, content, state, ..
+        } => {
+            let mut changes = Vec::new();
+            if let ROption::RSome(state) = state {
+                changes.push(state_marker(*state).to_string());
+            }
+            if let ROption::RSome(content) = content {
+                changes.push(content.to_string());
+            }
+            format!("update {}: {}", id, changes.join(" "))
+        }
+        FfiCommand::DeleteTodo { id } => format!("delete {}", id),
+        FfiCommand::MoveTodo { id, position } => format!("move {} to {}", id, position),
+        FfiCommand::SetTodoMetadata {
+            todo_id,
+            data,
+            merge,
+        } => {
+            let verb = if *merge { "merge" } else { "set" };
+            format!("metadata {} {}: {}", verb, todo_id, data)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claude_tasks::commands::{create_header_command, delete_todo_command};
+
+    fn task(id: &str, subject: &str, status: &str, blocked_by: &[&str]) -> ClaudeTask {
+        ClaudeTask {
+            id: id.to_string(),
+            subject: subject.to_string(),
+            description: String::new(),
+            active_form: String::new(),
+            status: status.to_string(),
+            blocks: vec![],
+            blocked_by: blocked_by.iter().map(|s| s.to_string()).collect(),
+            owner: None,
+        }
+    }
+
+    fn write_task(dir: &Path, file: &str, task: &ClaudeTask) {
+        let json = serde_json::to_string(task).unwrap();
+        std::fs::write(dir.join(file), json).unwrap();
+    }
+
+    #[test]
+    fn test_render_tree() {
+        let mut owned = task("2", "Ship it", "pending", &["1"]);
+        owned.owner = Some("reviewer".to_string());
+        let tasks = vec![task("1", "Write tests", "in_progress", &[]), owned];
+
+        assert_eq!(
+            render_tree("abc", Some("My Project"), &tasks),
+            vec![
+                "CLAUDE TASKLIST: My Project",
+                "  [*] Write tests",
+                "  [ ] \u{1F512} Ship it (blocked by: Write tests)  @reviewer",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_reports_problems() {
+        let dir = tempfile::tempdir().unwrap();
+        write_task(dir.path(), "1.json", &task("1", "Fine", "completed", &[]));
+        write_task(
+            dir.path(),
+            "2.json",
+            &task("2", "Dangling", "pending", &["9"]),
+        );
+        write_task(dir.path(), "3.json", &task("3", "Odd", "blocked", &[]));
+        write_task(dir.path(), "5.json", &task("4", "Misnamed", "pending", &[]));
+        std::fs::write(dir.path().join("6.json"), "{ half written").unwrap();
+
+        let problems = validate(dir.path());
+        assert_eq!(problems.len(), 4, "{:?}", problems);
+        assert!(problems[0].contains("6.json: unreadable"));
+        assert!(problems.contains(&"task 2: blocked by missing task 9".to_string()));
+        assert!(problems.contains(&"task 3: unknown status 'blocked'".to_string()));
+        assert!(problems.contains(&"task 4: not in 4.json".to_string()));
+    }
+
+    #[test]
+    fn test_validate_reports_cycles() {
+        let dir = tempfile::tempdir().unwrap();
+        write_task(dir.path(), "1.json", &task("1", "A", "pending", &["2"]));
+        write_task(dir.path(), "2.json", &task("2", "B", "pending", &["1"]));
+
+        let problems = validate(dir.path());
+        assert_eq!(
+            problems,
+            vec![
+                "task 1: part of a dependency cycle",
+                "task 2: part of a dependency cycle",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_describe_command() {
+        assert_eq!(
+            describe_command(&create_header_command("abc", Some("My Project"))),
+            "create claude-header-abc (indent 0): [ ] CLAUDE TASKLIST: My Project"
+        );
+        assert_eq!(
+            describe_command(&delete_todo_command("claude-abc-1")),
+            "delete claude-abc-1"
+        );
+    }
+}
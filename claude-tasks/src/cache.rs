@@ -0,0 +1,423 @@
+//! Cache for tasklist discovery.
+//!
+//! Parsing every task file of every tasklist each time the picker opens gets
+//! slow with hundreds of old sessions. The cache keeps an entry per tasklist
+//! keyed by a [`Fingerprint`] of file metadata, so only tasklists whose
+//! files changed are looked at again. Task files are only parsed when a
+//! summary is asked for (see [`DiscoveryCache::summary`]).
+//!
+//! The cache lives in memory for the plugin's lifetime and can optionally be
+//! persisted to `{cache_dir}/claude-tasks-discovery.json`.
+
+use crate::discovery::{
+    active_task, find_project, scan_tasklist, session_id, ProjectInfo, StatusCounts,
+};
+use crate::paths::plugin_paths;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+
+/// Number of sample task subjects kept per tasklist.
+const SAMPLE_TASKS: usize = 3;
+
+/// Cheap fingerprint of a tasklist folder, computed from metadata only.
+///
+/// The directory mtime catches added and removed files; the newest file
+/// mtime and total size catch in-place edits. Legacy TodoWrite files are
+/// fingerprinted by their own metadata.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fingerprint {
+    /// Modification time of the tasklist directory
+    pub dir_mtime: SystemTime,
+    /// Number of .json files
+    pub file_count: usize,
+    /// Newest modification time of any .json file
+    pub newest_mtime: SystemTime,
+    /// Total size of all .json files in bytes
+    pub total_len: u64,
+}
+
+impl Fingerprint {
+    /// Compute the fingerprint of a tasklist folder without reading any files.
+    pub fn of(path: &Path) -> Option<Self> {
+        let metadata = std::fs::metadata(path).ok()?;
+        let dir_mtime = metadata.modified().ok()?;
+        if metadata.is_file() {
+            return Some(Fingerprint {
+                dir_mtime,
+                file_count: 1,
+                newest_mtime: dir_mtime,
+                total_len: metadata.len(),
+            });
+        }
+
+        let mut fingerprint = Fingerprint {
+            dir_mtime,
+            file_count: 0,
+            newest_mtime: SystemTime::UNIX_EPOCH,
+            total_len: 0,
+        };
+
+        for entry in std::fs::read_dir(path).ok()?.flatten() {
+            let is_json = entry.path().extension().is_some_and(|e| e == "json");
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if !is_json || !metadata.is_file() {
+                continue;
+            }
+
+            fingerprint.file_count += 1;
+            fingerprint.total_len += metadata.len();
+            if let Ok(mtime) = metadata.modified() {
+                fingerprint.newest_mtime = fingerprint.newest_mtime.max(mtime);
+            }
+        }
+
+        Some(fingerprint)
+    }
+
+    /// Time of the last change: the newest task file or, for removals, the
+    /// folder itself.
+    pub fn last_activity(&self) -> SystemTime {
+        self.dir_mtime.max(self.newest_mtime)
+    }
+}
+
+/// Parsed summary of a tasklist, as shown in the picker.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TasklistSummary {
+    /// Number of valid task files
+    pub task_count: usize,
+    /// Task counts by status
+    pub counts: StatusCounts,
+    /// Active form (or subject) of the first in-progress task
+    pub active_task: Option<String>,
+    /// First task subjects (ordered by numeric task id ascending)
+    pub sample_tasks: Vec<String>,
+}
+
+impl TasklistSummary {
+    /// Parse all task files of a tasklist.
+    pub fn scan(path: &Path) -> Self {
+        let tasks = scan_tasklist(path);
+        Self {
+            task_count: tasks.len(),
+            counts: StatusCounts::from_tasks(&tasks),
+            active_task: active_task(&tasks),
+            sample_tasks: tasks
+                .iter()
+                .take(SAMPLE_TASKS)
+                .map(|t| t.subject.clone())
+                .collect(),
+        }
+    }
+}
+
+/// Look up the Claude project of a tasklist from the transcripts next to
+/// its task root.
+fn lookup_project(id: &str, path: &Path) -> Option<ProjectInfo> {
+    path.parent()
+        .and_then(|root| find_project(root, session_id(id)))
+}
+
+/// Cached data of a tasklist and the fingerprint it was computed for.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    fingerprint: Fingerprint,
+    /// Project looked up for this fingerprint; None is a cached miss
+    project: Option<ProjectInfo>,
+    /// Parsed summary, filled in on first use
+    summary: Option<TasklistSummary>,
+}
+
+/// Tasklist summaries keyed by tasklist ID.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct DiscoveryCache {
+    entries: HashMap<String, CacheEntry>,
+    /// Whether entries changed since the last save
+    #[serde(skip)]
+    dirty: bool,
+    /// Whether the on-disk cache has been merged in
+    #[serde(skip)]
+    disk_loaded: bool,
+}
+
+impl DiscoveryCache {
+    /// Bring the entry for a tasklist up to date.
+    ///
+    /// Returns true if the fingerprint changed (the project is looked up
+    /// again and the summary dropped), false if the cached entry was still
+    /// valid or the folder couldn't be read. A project that wasn't found is
+    /// cached as well, so the transcripts are only searched again once the
+    /// tasklist changes.
+    pub fn refresh(&mut self, id: &str, path: &Path) -> bool {
+        let Some(fingerprint) = Fingerprint::of(path) else {
+            return false;
+        };
+
+        if self
+            .entries
+            .get(id)
+            .is_some_and(|e| e.fingerprint == fingerprint)
+        {
+            return false;
+        }
+
+        self.entries.insert(
+            id.to_string(),
+            CacheEntry {
+                fingerprint,
+                project: lookup_project(id, path),
+                summary: None,
+            },
+        );
+        self.dirty = true;
+        true
+    }
+
+    /// Get the cached fingerprint of a tasklist.
+    pub fn fingerprint(&self, id: &str) -> Option<Fingerprint> {
+        self.entries.get(id).map(|e| e.fingerprint)
+    }
+
+    /// Get the cached project of a tasklist.
+    pub fn project(&self, id: &str) -> Option<&ProjectInfo> {
+        self.entries.get(id).and_then(|e| e.project.as_ref())
+    }
+
+    /// Get the summary of a tasklist, parsing its task files if the cache
+    /// has none for the current fingerprint.
+    ///
+    /// Returns None if the tasklist couldn't be read.
+    pub fn summary(&mut self, id: &str, path: &Path) -> Option<&TasklistSummary> {
+        self.refresh(id, path);
+        let entry = self.entries.get_mut(id)?;
+        if entry.summary.is_none() {
+            entry.summary = Some(TasklistSummary::scan(path));
+            self.dirty = true;
+        }
+        entry.summary.as_ref()
+    }
+
+    /// Drop entries for tasklists that no longer exist.
+    pub fn retain(&mut self, live_ids: &HashSet<String>) {
+        let before = self.entries.len();
+        self.entries.retain(|id, _| live_ids.contains(id));
+        if self.entries.len() != before {
+            self.dirty = true;
+        }
+    }
+
+    /// Load a cache file. Missing or unreadable files give an empty cache.
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Save the cache if it changed. Failures are ignored.
+    pub fn save(&mut self, path: &Path) {
+        if !self.dirty {
+            return;
+        }
+        if let Ok(content) = serde_json::to_string(self) {
+            if std::fs::write(path, content).is_ok() {
+                self.dirty = false;
+            }
+        }
+    }
+}
+
+/// Get the on-disk cache path: {cache_dir}/claude-tasks-discovery.json
+fn disk_cache_path() -> Option<PathBuf> {
+    plugin_paths()
+        .cache_dir
+        .map(|p| p.join("claude-tasks-discovery.json"))
+}
+
+/// Run `f` with the process-wide discovery cache.
+///
+/// With `on_disk`, the on-disk cache is merged in on first use and the
+/// cache is written back afterwards if it changed.
+pub fn with_cache<R>(on_disk: bool, f: impl FnOnce(&mut DiscoveryCache) -> R) -> R {
+    static CACHE: OnceLock<Mutex<DiscoveryCache>> = OnceLock::new();
+    let mut cache = CACHE
+        .get_or_init(|| Mutex::new(DiscoveryCache::default()))
+        .lock()
+        .unwrap();
+
+    let disk_path = disk_cache_path().filter(|_| on_disk);
+
+    if let Some(path) = &disk_path {
+        if !cache.disk_loaded {
+            let loaded = DiscoveryCache::load(path);
+            for (id, entry) in loaded.entries {
+                cache.entries.entry(id).or_insert(entry);
+            }
+            cache.disk_loaded = true;
+        }
+    }
+
+    let result = f(&mut cache);
+
+    if let Some(path) = &disk_path {
+        cache.save(path);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    fn write_task(dir: &Path, id: &str, status: &str) {
+        let json = format!(
+            r#"{{"id":"{}","subject":"Task {}","description":"","activeForm":"","status":"{}"}}"#,
+            id, id, status
+        );
+        fs::write(dir.join(format!("{}.json", id)), json).unwrap();
+    }
+
+    #[test]
+    fn test_refresh_scans_only_on_change() {
+        let temp_dir = TempDir::new().unwrap();
+        write_task(temp_dir.path(), "1", "pending");
+
+        let mut cache = DiscoveryCache::default();
+        assert!(cache.refresh("list", temp_dir.path()));
+        assert!(!cache.refresh("list", temp_dir.path()));
+        // Task files are only parsed once a summary is asked for
+        assert!(cache.entries["list"].summary.is_none());
+        assert_eq!(
+            cache.summary("list", temp_dir.path()).unwrap().task_count,
+            1
+        );
+
+        // A new file changes the fingerprint
+        write_task(temp_dir.path(), "2", "completed");
+        assert!(cache.refresh("list", temp_dir.path()));
+        assert!(cache.entries["list"].summary.is_none());
+
+        let summary = cache.summary("list", temp_dir.path()).unwrap();
+        assert_eq!(summary.task_count, 2);
+        assert_eq!(summary.counts.completed, 1);
+        assert_eq!(summary.sample_tasks, vec!["Task 1", "Task 2"]);
+    }
+
+    #[test]
+    fn test_refresh_detects_in_place_edit() {
+        let temp_dir = TempDir::new().unwrap();
+        write_task(temp_dir.path(), "1", "pending");
+
+        let mut cache = DiscoveryCache::default();
+        cache.refresh("list", temp_dir.path());
+
+        write_task(temp_dir.path(), "1", "in_progress");
+        // Ensure the mtime differs even on coarse-grained filesystems
+        let file = fs::File::options()
+            .write(true)
+            .open(temp_dir.path().join("1.json"))
+            .unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(5))
+            .unwrap();
+
+        assert!(cache.refresh("list", temp_dir.path()));
+        let summary = cache.summary("list", temp_dir.path()).unwrap();
+        assert_eq!(summary.counts.in_progress, 1);
+    }
+
+    #[test]
+    fn test_refresh_legacy_todo_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("abc-agent-abc.json");
+        fs::write(&file, r#"[{"content":"Todo","status":"pending"}]"#).unwrap();
+
+        let mut cache = DiscoveryCache::default();
+        assert!(cache.refresh("abc-agent-abc", &file));
+        assert!(!cache.refresh("abc-agent-abc", &file));
+        assert_eq!(
+            cache.summary("abc-agent-abc", &file).unwrap().sample_tasks,
+            vec!["Todo"]
+        );
+
+        // Rewriting the list changes the size and therefore the fingerprint
+        fs::write(&file, r#"[{"content":"Todo","status":"completed"}]"#).unwrap();
+        assert!(cache.refresh("abc-agent-abc", &file));
+        let summary = cache.summary("abc-agent-abc", &file).unwrap();
+        assert_eq!(summary.counts.completed, 1);
+    }
+
+    #[test]
+    fn test_refresh_finds_late_project() {
+        let temp_dir = TempDir::new().unwrap();
+        let tasklist = temp_dir.path().join("tasks").join("abc-123");
+        fs::create_dir_all(&tasklist).unwrap();
+        write_task(&tasklist, "1", "pending");
+
+        let mut cache = DiscoveryCache::default();
+        assert!(cache.refresh("abc-123", &tasklist));
+        assert!(cache.project("abc-123").is_none());
+
+        // The transcript is written after the first task
+        let transcripts = temp_dir.path().join("projects").join("-work-repo");
+        fs::create_dir_all(&transcripts).unwrap();
+        fs::write(
+            transcripts.join("abc-123.jsonl"),
+            r#"{"cwd":"/work/repo","gitBranch":"main"}"#,
+        )
+        .unwrap();
+
+        // The miss is cached until the tasklist changes
+        assert!(!cache.refresh("abc-123", &tasklist));
+        assert!(cache.project("abc-123").is_none());
+
+        write_task(&tasklist, "2", "pending");
+        assert!(cache.refresh("abc-123", &tasklist));
+        let project = cache.project("abc-123").unwrap();
+        assert_eq!(project.cwd, PathBuf::from("/work/repo"));
+    }
+
+    #[test]
+    fn test_retain_drops_vanished() {
+        let temp_dir = TempDir::new().unwrap();
+        write_task(temp_dir.path(), "1", "pending");
+
+        let mut cache = DiscoveryCache::default();
+        cache.refresh("list", temp_dir.path());
+        cache.retain(&HashSet::new());
+        assert!(cache.fingerprint("list").is_none());
+    }
+
+    #[test]
+    fn test_save_and_load_roundtrip() {
+        let temp_dir = TempDir::new().unwrap();
+        let list_dir = temp_dir.path().join("list");
+        fs::create_dir(&list_dir).unwrap();
+        write_task(&list_dir, "1", "pending");
+        let cache_file = temp_dir.path().join("cache.json");
+
+        let mut cache = DiscoveryCache::default();
+        cache.summary("list", &list_dir);
+        cache.save(&cache_file);
+
+        let mut loaded = DiscoveryCache::load(&cache_file);
+        // Fingerprint still matches, so no rescan is needed
+        assert!(!loaded.refresh("list", &list_dir));
+        assert!(loaded.entries["list"].summary.is_some());
+        assert_eq!(loaded.summary("list", &list_dir).unwrap().task_count, 1);
+    }
+
+    #[test]
+    fn test_load_missing_file() {
+        let cache = DiscoveryCache::load(Path::new("/nonexistent/cache.json"));
+        assert!(cache.fingerprint("list").is_none());
+    }
+}
@@ -1,13 +1,18 @@
 //! Claude Code task data structures and parsing.
 //!
 //! Claude Code stores tasks in `~/.claude/tasks/{uuid}/*.json` with this schema.
+//!
+//! Older Claude Code versions (and some modes) persist TodoWrite lists instead:
+//! one JSON array per agent in `~/.claude/todos/{session}-agent-{agent}.json`.
+//! Those items have no IDs, so [`parse_legacy_todos`] converts them to
+//! [`ClaudeTask`]s with IDs derived from array position and content.
 
 use serde::{Deserialize, Serialize};
 use totui_plugin_interface::FfiTodoState;
 
 /// A task from Claude Code's task list.
 ///
-/// Maps directly to the JSON schema: `{id, subject, description, activeForm, status, blocks[], blockedBy[]}`
+/// Maps directly to the JSON schema: `{id, subject, description, activeForm, status, blocks[], blockedBy[], owner?}`
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ClaudeTask {
     /// Numeric string ID (e.g., "1", "2")
@@ -27,6 +32,72 @@ pub struct ClaudeTask {
     /// Task IDs blocking this task (upstream dependencies)
     #[serde(rename = "blockedBy", default)]
     pub blocked_by: Vec<String>,
+    /// Agent that owns the task (subagents and agent teammates); older
+    /// versions call it `agent`
+    #[serde(default, alias = "agent", skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+}
+
+/// An item of a legacy TodoWrite list.
+///
+/// Maps to the JSON schema: `{content, status, activeForm}`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LegacyTodo {
+    /// Todo text
+    pub content: String,
+    /// Status: "pending", "in_progress", "completed"
+    pub status: String,
+    /// Current activity description (spinner text)
+    #[serde(rename = "activeForm", default)]
+    pub active_form: String,
+}
+
+impl LegacyTodo {
+    /// Convert to a task with an ID derived from its position in the list.
+    pub fn into_task(self, position: usize) -> ClaudeTask {
+        ClaudeTask {
+            id: legacy_task_id(position, &self.content),
+            subject: self.content,
+            description: String::new(),
+            active_form: self.active_form,
+            status: self.status,
+            blocks: Vec::new(),
+            blocked_by: Vec::new(),
+            owner: None,
+        }
+    }
+}
+
+/// Build a stable task ID for a legacy todo: "{position}-{content hash}".
+///
+/// The 1-based position keeps IDs short and ordered; the hash keeps a
+/// reworded todo from silently taking over the previous todo's ID.
+pub fn legacy_task_id(position: usize, content: &str) -> String {
+    format!("{}-{:08x}", position + 1, stable_hash(content))
+}
+
+/// Hash text for use in todo IDs.
+///
+/// Uses FNV-1a so IDs are stable across Rust versions (they end up in todo
+/// IDs stored by the host).
+pub fn stable_hash(text: &str) -> u32 {
+    text.bytes().fold(0x811c_9dc5_u32, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    })
+}
+
+/// Parse a legacy TodoWrite file into tasks, in list order.
+///
+/// Returns None if the content is not a JSON array of todos.
+pub fn parse_legacy_todos(json: &str) -> Option<Vec<ClaudeTask>> {
+    let todos: Vec<LegacyTodo> = serde_json::from_str(json).ok()?;
+    Some(
+        todos
+            .into_iter()
+            .enumerate()
+            .map(|(position, todo)| todo.into_task(position))
+            .collect(),
+    )
 }
 
 /// Map Claude task status to totui todo state.
@@ -103,6 +174,57 @@ mod tests {
         assert_eq!(task.id, "1");
         assert!(task.blocks.is_empty());
         assert!(task.blocked_by.is_empty());
+        assert!(task.owner.is_none());
+    }
+
+    #[test]
+    fn test_parse_claude_task_owner() {
+        let json = r#"{"id":"1","subject":"Owned","description":"","activeForm":"","status":"pending","owner":"researcher"}"#;
+        let task: ClaudeTask = serde_json::from_str(json).unwrap();
+        assert_eq!(task.owner.as_deref(), Some("researcher"));
+
+        let json = r#"{"id":"1","subject":"Owned","description":"","activeForm":"","status":"pending","agent":"tester"}"#;
+        let task: ClaudeTask = serde_json::from_str(json).unwrap();
+        assert_eq!(task.owner.as_deref(), Some("tester"));
+    }
+
+    #[test]
+    fn test_parse_legacy_todos() {
+        let json = r#"[
+            {"content": "Write parser", "status": "completed", "activeForm": "Writing parser"},
+            {"content": "Add tests", "status": "in_progress", "activeForm": "Adding tests", "id": "2", "priority": "high"},
+            {"content": "Ship it", "status": "pending"}
+        ]"#;
+
+        let tasks = parse_legacy_todos(json).unwrap();
+        assert_eq!(tasks.len(), 3);
+        assert_eq!(tasks[0].subject, "Write parser");
+        assert_eq!(tasks[1].active_form, "Adding tests");
+        assert_eq!(tasks[1].status, "in_progress");
+        assert!(tasks[2].active_form.is_empty());
+        assert!(tasks[0].id.starts_with("1-"));
+        assert!(tasks[2].id.starts_with("3-"));
+
+        assert_eq!(parse_legacy_todos("[]").unwrap().len(), 0);
+        assert!(parse_legacy_todos(r#"{"id": "1"}"#).is_none());
+    }
+
+    #[test]
+    fn test_legacy_task_id_stable() {
+        // Same position and content always give the same ID
+        assert_eq!(legacy_task_id(0, "Add tests"), "1-bfc2b431");
+        assert_eq!(
+            legacy_task_id(0, "Add tests"),
+            legacy_task_id(0, "Add tests")
+        );
+        assert_ne!(
+            legacy_task_id(0, "Add tests"),
+            legacy_task_id(1, "Add tests")
+        );
+        assert_ne!(
+            legacy_task_id(0, "Add tests"),
+            legacy_task_id(0, "Add docs")
+        );
     }
 
     #[test]
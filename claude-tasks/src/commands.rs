@@ -3,7 +3,9 @@
 //! Helper functions to generate FfiCommand instances for creating, updating,
 //! and deleting todos from Claude tasks.
 
-use crate::claude_task::{map_status_to_state, ClaudeTask};
+use crate::claude_task::{map_status_to_state, stable_hash, ClaudeTask};
+use crate::metadata::TodoMetadata;
+use crate::staleness::HeaderStatus;
 use abi_stable::std_types::{ROption, RString};
 use totui_plugin_interface::{FfiCommand, FfiTodoState};
 
@@ -26,6 +28,14 @@ pub fn create_header_command(tasklist_id: &str, display_name: Option<&str>) -> F
     }
 }
 
+/// Create a metadata command for a tasklist header.
+///
+/// Tags the header with `source` and `tasklist_id` so headers can be found
+/// via `query_todos_by_metadata` even after all task todos are gone.
+pub fn header_metadata_command(tasklist_id: &str) -> FfiCommand {
+    TodoMetadata::header(tasklist_id).set_command(&header_id(tasklist_id))
+}
+
 /// Create commands for a new todo from a Claude task.
 ///
 /// Returns two commands:
@@ -54,14 +64,8 @@ pub fn create_todo_commands(
         indent_level: 1,
     };
 
-    // Build metadata JSON
-    let metadata = build_metadata_json(tasklist_id, &task.id, &task.blocked_by);
-
-    let metadata_cmd = FfiCommand::SetTodoMetadata {
-        todo_id: RString::from(temp_id),
-        data: RString::from(metadata),
-        merge: false,
-    };
+    let metadata_cmd =
+        TodoMetadata::task(tasklist_id, &task.id, &task.blocked_by).set_command(&temp_id);
 
     vec![create_cmd, metadata_cmd]
 }
@@ -71,27 +75,46 @@ pub fn create_todo_commands(
 /// Uses hierarchy to determine:
 /// - annotation: appended to content in grey if blocked
 ///
-/// All tasks use header as parent (flat list) at indent_level 1.
+/// Tasks are not nested by dependency (flat list): the parent is the
+/// tasklist header at indent_level 1, or an agent sub-header at
+/// indent_level 2 with `group_by_agent`.
 ///
 /// Returns two commands:
-/// 1. CreateTodo - creates the todo item at root level
+/// 1. CreateTodo - creates the todo item under `parent_id`
 /// 2. SetTodoMetadata - sets correlation metadata
 pub fn create_todo_commands_with_hierarchy(
     task: &ClaudeTask,
     tasklist_id: &str,
-    header_id: &str,
+    parent_id: &str,
+    indent_level: u32,
     hierarchy: &crate::hierarchy::TaskHierarchy,
 ) -> Vec<FfiCommand> {
     let temp_id = format!("claude-{}-{}", tasklist_id, task.id);
+    let content = format_hierarchy_content(task, hierarchy);
 
-    // Always use header as parent (flat list)
-    let parent_id = ROption::RSome(RString::from(header_id));
+    let create_cmd = FfiCommand::CreateTodo {
+        content: RString::from(content),
+        parent_id: ROption::RSome(RString::from(parent_id)),
+        temp_id: ROption::RSome(RString::from(temp_id.clone())),
+        state: map_status_to_state(&task.status),
+        priority: ROption::RNone,
+        indent_level,
+    };
+
+    let metadata_cmd =
+        TodoMetadata::task(tasklist_id, &task.id, &task.blocked_by).set_command(&temp_id);
 
-    // Always indent level 1 (flat list under header)
-    let indent_level = 1;
+    vec![create_cmd, metadata_cmd]
+}
 
-    // Format content with annotation if needed
-    let content = if let Some(annotation) = hierarchy.get_annotation(&task.id) {
+/// Format task content with its hierarchy annotation, if any.
+///
+/// This is the content a todo gets on create; updates only set the subject.
+pub fn format_hierarchy_content(
+    task: &ClaudeTask,
+    hierarchy: &crate::hierarchy::TaskHierarchy,
+) -> String {
+    if let Some(annotation) = hierarchy.get_annotation(&task.id) {
         // Annotation for blocked tasks or cycles
         if hierarchy.is_cyclic(&task.id) {
             format!("{} {}", annotation, task.subject)
@@ -102,27 +125,7 @@ pub fn create_todo_commands_with_hierarchy(
     } else {
         // Normal content - just subject
         task.subject.clone()
-    };
-
-    let create_cmd = FfiCommand::CreateTodo {
-        content: RString::from(content),
-        parent_id,
-        temp_id: ROption::RSome(RString::from(temp_id.clone())),
-        state: map_status_to_state(&task.status),
-        priority: ROption::RNone,
-        indent_level,
-    };
-
-    // Build metadata JSON
-    let metadata = build_metadata_json(tasklist_id, &task.id, &task.blocked_by);
-
-    let metadata_cmd = FfiCommand::SetTodoMetadata {
-        todo_id: RString::from(temp_id),
-        data: RString::from(metadata),
-        merge: false,
-    };
-
-    vec![create_cmd, metadata_cmd]
+    }
 }
 
 /// Create an update command for an existing todo.
@@ -148,6 +151,12 @@ pub fn delete_todo_command(todo_id: &str) -> FfiCommand {
     }
 }
 
+/// Delete a block of todos rendered with a header first (status report,
+/// prune preview): children first, then the header.
+pub fn clear_todos_commands(ids: &[String]) -> Vec<FfiCommand> {
+    ids.iter().rev().map(|id| delete_todo_command(id)).collect()
+}
+
 /// Format task content.
 ///
 /// Returns just the subject - blocked annotation is handled separately
@@ -156,31 +165,12 @@ fn format_task_content(task: &ClaudeTask) -> String {
     task.subject.clone()
 }
 
-/// Build metadata JSON string for a task.
-///
-/// Metadata includes:
-/// - source: "claude-tasks" (for querying all synced todos)
-/// - tasklist_id: UUID of the tasklist
-/// - task_id: ID of the task within the tasklist
-/// - read_only: true (tasks are managed by Claude)
-/// - blocked_by: array of blocking task IDs (if any)
-fn build_metadata_json(tasklist_id: &str, task_id: &str, blocked_by: &[String]) -> String {
-    let blocked_by_json = if blocked_by.is_empty() {
-        "[]".to_string()
-    } else {
-        let items: Vec<String> = blocked_by.iter().map(|id| format!("\"{}\"", id)).collect();
-        format!("[{}]", items.join(","))
-    };
-
-    format!(
-        r#"{{"source":"claude-tasks","tasklist_id":"{}","task_id":"{}","read_only":true,"blocked_by":{}}}"#,
-        tasklist_id, task_id, blocked_by_json
-    )
-}
+/// Prefix of tasklist header todo IDs
+pub const HEADER_ID_PREFIX: &str = "claude-header-";
 
 /// Get the header todo ID for a tasklist.
 pub fn header_id(tasklist_id: &str) -> String {
-    format!("claude-header-{}", tasklist_id)
+    format!("{}{}", HEADER_ID_PREFIX, tasklist_id)
 }
 
 /// Get the todo temp_id for a task.
@@ -188,24 +178,93 @@ pub fn task_todo_id(tasklist_id: &str, task_id: &str) -> String {
     format!("claude-{}-{}", tasklist_id, task_id)
 }
 
+/// Get the todo ID of an agent sub-header.
+///
+/// The agent name is slugged (lowercase, non-alphanumerics as '-') so
+/// names with spaces still give a predictable ID. Names the slug changes
+/// get a hash of the name appended, so "Code Reviewer" and "code-reviewer"
+/// don't share a sub-header.
+pub fn agent_header_id(tasklist_id: &str, agent: &str) -> String {
+    let slug: String = agent
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    if slug == agent {
+        format!("claude-agent-{}-{}", tasklist_id, slug)
+    } else {
+        format!(
+            "claude-agent-{}-{}-{:08x}",
+            tasklist_id,
+            slug,
+            stable_hash(agent)
+        )
+    }
+}
+
+/// Create commands for an agent sub-header under the tasklist header.
+///
+/// Returns two commands:
+/// 1. CreateTodo - "{label}" at indent_level 1, temp_id from [`agent_header_id`]
+/// 2. SetTodoMetadata - tags it as `type: "agent"` with the agent name
+pub fn create_agent_header_commands(
+    tasklist_id: &str,
+    agent: &str,
+    label: &str,
+) -> Vec<FfiCommand> {
+    let agent_id = agent_header_id(tasklist_id, agent);
+
+    vec![
+        FfiCommand::CreateTodo {
+            content: RString::from(label),
+            parent_id: ROption::RSome(RString::from(header_id(tasklist_id))),
+            temp_id: ROption::RSome(RString::from(agent_id.clone())),
+            state: FfiTodoState::Empty,
+            priority: ROption::RNone,
+            indent_level: 1,
+        },
+        TodoMetadata::agent(tasklist_id, agent).set_command(&agent_id),
+    ]
+}
+
+/// Create an update command for an agent sub-header's label.
+pub fn update_agent_header_command(tasklist_id: &str, agent: &str, label: &str) -> FfiCommand {
+    FfiCommand::UpdateTodo {
+        id: RString::from(agent_header_id(tasklist_id, agent)),
+        content: ROption::RSome(RString::from(label)),
+        state: ROption::RNone,
+        priority: ROption::RNone,
+        due_date: ROption::RNone,
+        description: ROption::RNone,
+    }
+}
+
 /// Create an update command for the header todo with optional staleness indicator.
 ///
-/// Format: "CLAUDE TASKLIST: {name}" or "CLAUDE TASKLIST: {name} \u{23F0} STALE (Xm)"
+/// Format: "CLAUDE TASKLIST: {name}" or "CLAUDE TASKLIST: {name} {status label}",
+/// e.g. "CLAUDE TASKLIST: {name} \u{23F0} STALE (Xm)". The header state is
+/// Exclamation while stale or abandoned, Empty otherwise.
 pub fn update_header_command(
     tasklist_id: &str,
     display_name: Option<&str>,
-    staleness: Option<&str>,
+    status: Option<&HeaderStatus>,
 ) -> FfiCommand {
     let name = display_name.unwrap_or(tasklist_id);
-    let content = match staleness {
-        Some(duration) => format!("CLAUDE TASKLIST: {} \u{23F0} STALE ({})", name, duration),
+    let content = match status {
+        Some(status) => format!("CLAUDE TASKLIST: {} {}", name, status.label()),
         None => format!("CLAUDE TASKLIST: {}", name),
     };
+    let state = status.map_or(FfiTodoState::Empty, HeaderStatus::todo_state);
 
     FfiCommand::UpdateTodo {
         id: RString::from(header_id(tasklist_id)),
         content: ROption::RSome(RString::from(content)),
-        state: ROption::RNone,
+        state: ROption::RSome(state),
         priority: ROption::RNone,
         due_date: ROption::RNone,
         description: ROption::RNone,
@@ -215,6 +274,7 @@ pub fn update_header_command(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::staleness::StalenessLevel;
 
     fn make_test_task(id: &str, subject: &str, status: &str) -> ClaudeTask {
         ClaudeTask {
@@ -225,6 +285,7 @@ mod tests {
             status: status.to_string(),
             blocks: vec![],
             blocked_by: vec![],
+            owner: None,
         }
     }
 
@@ -237,6 +298,7 @@ mod tests {
             status: "pending".to_string(),
             blocks: vec![],
             blocked_by: blocked_by.into_iter().map(String::from).collect(),
+            owner: None,
         }
     }
 
@@ -275,6 +337,21 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_header_metadata_command() {
+        let cmd = header_metadata_command("abc-123");
+
+        match cmd {
+            FfiCommand::SetTodoMetadata { todo_id, data, .. } => {
+                assert_eq!(todo_id.as_str(), "claude-header-abc-123");
+                assert!(data.as_str().contains("\"source\":\"claude-tasks\""));
+                assert!(data.as_str().contains("\"tasklist_id\":\"abc-123\""));
+                assert!(data.as_str().contains("\"type\":\"header\""));
+            }
+            _ => panic!("Expected SetTodoMetadata command"),
+        }
+    }
+
     #[test]
     fn test_create_todo_commands_pending() {
         let task = make_test_task("1", "Test task", "pending");
@@ -415,6 +492,15 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_clear_todos_commands() {
+        let ids = vec!["claude-status".to_string(), "claude-status-0".to_string()];
+        let cmds = clear_todos_commands(&ids);
+        // Children are deleted before the header
+        assert!(matches!(&cmds[0], FfiCommand::DeleteTodo { id } if id == "claude-status-0"));
+        assert!(matches!(&cmds[1], FfiCommand::DeleteTodo { id } if id == "claude-status"));
+    }
+
     #[test]
     fn test_header_id() {
         assert_eq!(header_id("abc"), "claude-header-abc");
@@ -438,6 +524,31 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_agent_header_id_slug() {
+        assert_eq!(
+            agent_header_id("tl", "researcher"),
+            "claude-agent-tl-researcher"
+        );
+        assert_eq!(
+            agent_header_id("tl", "code-reviewer"),
+            "claude-agent-tl-code-reviewer"
+        );
+
+        // Names that only match once slugged still get their own ID
+        let spaced = agent_header_id("tl", "Code Reviewer");
+        assert!(spaced.starts_with("claude-agent-tl-code-reviewer-"));
+        assert_ne!(spaced, agent_header_id("tl", "code-reviewer"));
+        assert_ne!(spaced, agent_header_id("tl", "Code-Reviewer"));
+    }
+
+    fn stale(duration: &str) -> HeaderStatus {
+        HeaderStatus::Quiet {
+            level: StalenessLevel::Stale,
+            duration: duration.to_string(),
+        }
+    }
+
     #[test]
     fn test_update_header_command_no_staleness() {
         let cmd = update_header_command("abc-123", Some("MyProject"), None);
@@ -454,9 +565,11 @@ mod tests {
 
     #[test]
     fn test_update_header_command_with_staleness() {
-        let cmd = update_header_command("abc-123", Some("MyProject"), Some("23m"));
+        let status = stale("23m");
+        let cmd = update_header_command("abc-123", Some("MyProject"), Some(&status));
         match cmd {
-            FfiCommand::UpdateTodo { content, .. } => {
+            FfiCommand::UpdateTodo { content, state, .. } => {
+                assert!(matches!(state, ROption::RSome(FfiTodoState::Exclamation)));
                 let content_str = match content {
                     ROption::RSome(s) => s.as_str().to_string(),
                     _ => panic!("Expected content"),
@@ -471,7 +584,8 @@ mod tests {
 
     #[test]
     fn test_update_header_command_no_alias() {
-        let cmd = update_header_command("abc-123", None, Some("1h5m"));
+        let status = stale("1h5m");
+        let cmd = update_header_command("abc-123", None, Some(&status));
         match cmd {
             FfiCommand::UpdateTodo { content, .. } => {
                 let content_str = match content {
@@ -1,14 +1,85 @@
 //! Plugin configuration loading and alias resolution.
 //!
-//! Configuration is loaded from two locations:
+//! Configuration is merged from, lowest precedence first:
+//! - Built-in defaults
+//! - Host: the plugin settings edited in totui (see [`crate::schema`])
 //! - Global: ~/.config/totui/claude-tasks.toml
-//! - Local: .totui/aliases.toml (overrides global)
+//! - Local: claude-tasks.toml, then aliases.toml in the project's `.totui/`
+//!   folder, found by walking up from the current directory to the git root
+//!
+//! A `.totui/` folder comes with any cloned repository, so local files may
+//! only set aliases and display settings (see [`merge_local_overrides`]); the other
+//! keys are ignored with a warning.
+//!
+//! A setting edited in totui that a file also sets is overridden by the file.
+//! [`ConfigLoad::sources`] records which file each setting came from, so the
+//! schema can point at it and the log warns about it.
+//!
+//! Alias changes made from inside totui are written back with `toml_edit`,
+//! which preserves comments and formatting of the existing file.
 
-use crate::discovery::{discover_tasklists, TasklistInfo};
+use crate::discovery::{discover_tasklists, find_project, project_root, session_id, TasklistInfo};
+use crate::errors::PluginError;
+use crate::log::{Level, LogFormat, LogSettings};
+use crate::paths::plugin_paths;
+use crate::staleness::{StalenessLevel, StalenessThresholds};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
 use std::time::SystemTime;
+use toml_edit::DocumentMut;
+
+/// Project-local config folder.
+const LOCAL_CONFIG_DIR: &str = ".totui";
+/// Full config file in the project-local config folder.
+const LOCAL_CONFIG_FILE: &str = "claude-tasks.toml";
+/// Alias file in the project-local config folder (written by `alias --local`).
+const LOCAL_ALIASES_FILE: &str = "aliases.toml";
+
+/// Maximum characters of a task subject shown in a picker option.
+const OPTION_SUBJECT_LEN: usize = 40;
+
+/// What to do with todos from tasklists that no longer exist.
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PruneAction {
+    /// Delete the orphaned todos
+    #[default]
+    Delete,
+    /// Convert the orphaned todos into ordinary (archived) todos
+    Archive,
+}
+
+/// Whether to prune orphaned todos when the plugin is first invoked.
+///
+/// Finding orphans needs host access, which the plugin only gets when it is
+/// invoked, so this runs on the first invocation rather than at startup.
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PruneOnFirstInvocation {
+    /// Don't prune on the first invocation
+    #[default]
+    Off,
+    /// Show a dry-run preview of what would be pruned
+    Preview,
+    /// Prune using the configured prune action
+    Apply,
+}
+
+/// How tasklist folders are watched for changes.
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WatchMode {
+    /// Native file system events (inotify, FSEvents, ...), falling back to
+    /// polling if the folder can't be watched natively
+    #[default]
+    Native,
+    /// Poll for changes; use for bind mounts and network file systems that
+    /// don't deliver native events
+    Poll,
+}
 
 /// Plugin configuration.
 #[derive(Debug, Default, Deserialize, Serialize, Clone)]
@@ -16,9 +87,52 @@ pub struct PluginConfig {
     /// UUID -> friendly name mappings
     #[serde(default)]
     pub aliases: HashMap<String, String>,
+    /// Minutes without updates until the tasklist is idle (default: 5)
+    #[serde(default)]
+    pub idle_threshold_minutes: Option<u64>,
     /// Staleness threshold in minutes (default: 15)
     #[serde(default)]
     pub staleness_threshold_minutes: Option<u64>,
+    /// Minutes without updates until the tasklist is abandoned (default: 1440)
+    #[serde(default)]
+    pub abandoned_threshold_minutes: Option<u64>,
+    /// Archive the tasklist automatically once every task is completed (default: false)
+    #[serde(default)]
+    pub archive_on_complete: Option<bool>,
+    /// Archive the tasklist automatically once it goes stale (default: false)
+    #[serde(default)]
+    pub archive_on_stale: Option<bool>,
+    /// What prune does with orphaned todos (default: delete)
+    #[serde(default)]
+    pub prune_action: Option<PruneAction>,
+    /// Prune orphaned todos on the first invocation (default: off); formerly
+    /// `prune_on_startup`
+    #[serde(default, alias = "prune_on_startup")]
+    pub prune_on_first_invocation: Option<PruneOnFirstInvocation>,
+    /// Only list tasklists of the current project in the picker (default: false)
+    #[serde(default)]
+    pub current_project_only: Option<bool>,
+    /// Persist the discovery cache to disk between runs (default: false)
+    #[serde(default)]
+    pub discovery_disk_cache: Option<bool>,
+    /// Folders containing Claude tasklists (default: $CLAUDE_CONFIG_DIR/tasks or ~/.claude/tasks)
+    #[serde(default)]
+    pub task_roots: Option<Vec<String>>,
+    /// How tasklist folders are watched (default: native)
+    #[serde(default)]
+    pub watch_mode: Option<WatchMode>,
+    /// Nest tasks under one sub-header per owning agent (default: false)
+    #[serde(default)]
+    pub group_by_agent: Option<bool>,
+    /// Most verbose level written to the plugin log (default: info)
+    #[serde(default)]
+    pub log_level: Option<Level>,
+    /// Format of the plugin log (default: text)
+    #[serde(default)]
+    pub log_format: Option<LogFormat>,
+    /// Plugin log file (default: {cache_dir}/claude-tasks-plugin.log)
+    #[serde(default)]
+    pub log_file: Option<String>,
 }
 
 impl PluginConfig {
@@ -27,99 +141,775 @@ impl PluginConfig {
         self.staleness_threshold_minutes.unwrap_or(15)
     }
 
+    /// Get the thresholds of all staleness levels
+    pub fn staleness_thresholds(&self) -> StalenessThresholds {
+        let defaults = StalenessThresholds::default();
+        StalenessThresholds {
+            idle_minutes: self.idle_threshold_minutes.unwrap_or(defaults.idle_minutes),
+            stale_minutes: self.staleness_threshold(),
+            abandoned_minutes: self
+                .abandoned_threshold_minutes
+                .unwrap_or(defaults.abandoned_minutes),
+        }
+    }
+
+    /// Whether to archive automatically when all tasks are completed
+    pub fn archive_on_complete(&self) -> bool {
+        self.archive_on_complete.unwrap_or(false)
+    }
+
+    /// Whether to archive automatically when the tasklist goes stale
+    pub fn archive_on_stale(&self) -> bool {
+        self.archive_on_stale.unwrap_or(false)
+    }
+
+    /// Get the prune action, defaulting to delete
+    pub fn prune_action(&self) -> PruneAction {
+        self.prune_action.unwrap_or_default()
+    }
+
+    /// Get the first-invocation prune mode, defaulting to off
+    pub fn prune_on_first_invocation(&self) -> PruneOnFirstInvocation {
+        self.prune_on_first_invocation.unwrap_or_default()
+    }
+
+    /// Whether the picker lists only tasklists of the current project
+    pub fn current_project_only(&self) -> bool {
+        self.current_project_only.unwrap_or(false)
+    }
+
+    /// Whether the discovery cache is persisted to disk
+    pub fn discovery_disk_cache(&self) -> bool {
+        self.discovery_disk_cache.unwrap_or(false)
+    }
+
+    /// Get the task roots to discover tasklists in.
+    ///
+    /// Configured roots have a leading `~` expanded; without configuration
+    /// the default root is used (see [`default_task_root`]).
+    pub fn task_roots(&self) -> Vec<PathBuf> {
+        match &self.task_roots {
+            Some(roots) => roots.iter().map(|r| expand_home(r)).collect(),
+            None => default_task_root().into_iter().collect(),
+        }
+    }
+
+    /// Get the watch mode, defaulting to native
+    pub fn watch_mode(&self) -> WatchMode {
+        self.watch_mode.unwrap_or_default()
+    }
+
+    /// Whether to nest tasks under per-agent sub-headers
+    pub fn group_by_agent(&self) -> bool {
+        self.group_by_agent.unwrap_or(false)
+    }
+
+    /// Get the plugin log settings; the log file has a leading `~` expanded
+    pub fn log_settings(&self) -> LogSettings {
+        LogSettings {
+            level: self.log_level,
+            format: self.log_format,
+            file: self.log_file.as_deref().map(expand_home),
+        }
+    }
+
     /// Get alias for a tasklist UUID, if configured
     pub fn get_alias(&self, uuid: &str) -> Option<&str> {
         self.aliases.get(uuid).map(|s| s.as_str())
     }
 }
 
-/// Load configuration from global and local paths.
+/// Top-level keys `PluginConfig` understands; anything else is reported as unknown.
+pub const CONFIG_KEYS: &[&str] = &[
+    "aliases",
+    "idle_threshold_minutes",
+    "staleness_threshold_minutes",
+    "abandoned_threshold_minutes",
+    "archive_on_complete",
+    "archive_on_stale",
+    "prune_action",
+    "prune_on_first_invocation",
+    "current_project_only",
+    "discovery_disk_cache",
+    "task_roots",
+    "watch_mode",
+    "group_by_agent",
+    "log_level",
+    "log_format",
+    "log_file",
+];
+
+/// Keys a project-local config may set (see [`merge_local_overrides`]).
+const LOCAL_KEYS: &[&str] = &[
+    "aliases",
+    "idle_threshold_minutes",
+    "staleness_threshold_minutes",
+    "abandoned_threshold_minutes",
+    "current_project_only",
+    "group_by_agent",
+];
+
+/// Old names of keys, still accepted.
+const RENAMED_KEYS: &[&str] = &["prune_on_startup"];
+
+/// Whether `key` is a top-level config key (under its current or old name).
+pub fn is_config_key(key: &str) -> bool {
+    CONFIG_KEYS.contains(&key) || RENAMED_KEYS.contains(&key)
+}
+
+/// Merged configuration plus what was wrong with the config files.
+#[derive(Debug, Default)]
+pub struct ConfigLoad {
+    /// The merged configuration
+    pub config: PluginConfig,
+    /// Files that failed to parse; their last valid contents were used instead
+    pub errors: Vec<PluginError>,
+    /// Unknown keys found in the config files, one message per key
+    pub warnings: Vec<String>,
+    /// Config files that were read, in merge order
+    pub files: Vec<PathBuf>,
+    /// File each setting was last taken from; settings missing here come
+    /// from totui or the defaults. Aliases are merged entry by entry and
+    /// aren't listed.
+    pub sources: HashMap<&'static str, PathBuf>,
+}
+
+/// Load configuration from the host settings and the global and local paths.
+///
+/// Host config: plugin settings from totui (see [`set_host_config`])
+/// Global config: ~/.config/totui/claude-tasks.toml (overrides host)
+/// Local config: .totui/claude-tasks.toml and .totui/aliases.toml in the
+/// project (override global, see [`local_config_dir`])
 ///
-/// Global config: ~/.config/totui/claude-tasks.toml
-/// Local config: .totui/aliases.toml (overrides global)
+/// Invalid files are skipped in favour of their last valid contents; use
+/// [`load_config_report`] to find out about them.
 pub fn load_config() -> PluginConfig {
-    let mut config = PluginConfig::default();
+    load_config_report().config
+}
+
+/// Load configuration, reporting parse errors and unknown keys.
+///
+/// A file that fails to parse is replaced by the last contents that parsed
+/// during this session (nothing, if it never did), so one typo doesn't
+/// throw away every setting.
+pub fn load_config_report() -> ConfigLoad {
+    let host = host_config().lock().unwrap().clone();
+    let mut report = ConfigLoad {
+        config: host.clone(),
+        ..Default::default()
+    };
 
-    // Load global config
+    // Merge global config (overrides host)
     if let Some(global_path) = global_config_path() {
-        if global_path.exists() {
-            if let Ok(content) = std::fs::read_to_string(&global_path) {
-                if let Ok(global) = toml::from_str::<PluginConfig>(&content) {
-                    config = global;
-                }
-            }
+        if let Some(global) = read_config_file(&global_path, &mut report) {
+            record_sources(&mut report, &global_path, set_keys(&global));
+            merge_overrides(&mut report.config, global);
+        }
+    }
+
+    // Merge local configs (override global, aliases.toml last)
+    for local_path in local_config_paths() {
+        if let Some(local) = read_config_file(&local_path, &mut report) {
+            let keys = set_keys(&local);
+            let ignored = merge_local_overrides(&mut report.config, local);
+            let taken = keys.into_iter().filter(|key| !ignored.contains(key));
+            record_sources(&mut report, &local_path, taken.collect());
+            report.warnings.extend(ignored.iter().map(|key| {
+                let path = local_path.display();
+                format!("{}: `{}` is ignored in a project config", path, key)
+            }));
+        }
+    }
+
+    // Settings edited in totui lose against the files
+    let host_keys = set_keys(&host);
+    for key in CONFIG_KEYS.iter().filter(|key| host_keys.contains(key)) {
+        if let Some(path) = report.sources.get(key) {
+            report.warnings.push(format!(
+                "`{}` from the plugin settings is overridden by {}",
+                key,
+                path.display()
+            ));
+        }
+    }
+
+    report
+}
+
+/// Record `path` as the source of `keys`; aliases are merged, not overridden.
+fn record_sources(report: &mut ConfigLoad, path: &Path, keys: Vec<&'static str>) {
+    for key in keys.into_iter().filter(|key| *key != "aliases") {
+        report.sources.insert(key, path.to_path_buf());
+    }
+}
+
+/// Keys set in `config`, in [`CONFIG_KEYS`] order.
+fn set_keys(config: &PluginConfig) -> Vec<&'static str> {
+    let set: [bool; CONFIG_KEYS.len()] = [
+        !config.aliases.is_empty(),
+        config.idle_threshold_minutes.is_some(),
+        config.staleness_threshold_minutes.is_some(),
+        config.abandoned_threshold_minutes.is_some(),
+        config.archive_on_complete.is_some(),
+        config.archive_on_stale.is_some(),
+        config.prune_action.is_some(),
+        config.prune_on_first_invocation.is_some(),
+        config.current_project_only.is_some(),
+        config.discovery_disk_cache.is_some(),
+        config.task_roots.is_some(),
+        config.watch_mode.is_some(),
+        config.group_by_agent.is_some(),
+        config.log_level.is_some(),
+        config.log_format.is_some(),
+        config.log_file.is_some(),
+    ];
+    CONFIG_KEYS
+        .iter()
+        .zip(set)
+        .filter_map(|(key, set)| set.then_some(*key))
+        .collect()
+}
+
+/// Merge a higher-precedence config into `config`; settings present in
+/// `local` win.
+pub fn merge_overrides(config: &mut PluginConfig, local: PluginConfig) {
+    // Merge aliases - local overrides global
+    config.aliases.extend(local.aliases);
+    // Override staleness thresholds if specified
+    if local.idle_threshold_minutes.is_some() {
+        config.idle_threshold_minutes = local.idle_threshold_minutes;
+    }
+    if local.staleness_threshold_minutes.is_some() {
+        config.staleness_threshold_minutes = local.staleness_threshold_minutes;
+    }
+    if local.abandoned_threshold_minutes.is_some() {
+        config.abandoned_threshold_minutes = local.abandoned_threshold_minutes;
+    }
+    // Override archive triggers if specified
+    if local.archive_on_complete.is_some() {
+        config.archive_on_complete = local.archive_on_complete;
+    }
+    if local.archive_on_stale.is_some() {
+        config.archive_on_stale = local.archive_on_stale;
+    }
+    // Override prune settings if specified
+    if local.prune_action.is_some() {
+        config.prune_action = local.prune_action;
+    }
+    if local.prune_on_first_invocation.is_some() {
+        config.prune_on_first_invocation = local.prune_on_first_invocation;
+    }
+    // Override picker filter if specified
+    if local.current_project_only.is_some() {
+        config.current_project_only = local.current_project_only;
+    }
+    // Override discovery cache setting if specified
+    if local.discovery_disk_cache.is_some() {
+        config.discovery_disk_cache = local.discovery_disk_cache;
+    }
+    // Override task roots and watch mode if specified
+    if local.task_roots.is_some() {
+        config.task_roots = local.task_roots;
+    }
+    if local.watch_mode.is_some() {
+        config.watch_mode = local.watch_mode;
+    }
+    // Override agent layout if specified
+    if local.group_by_agent.is_some() {
+        config.group_by_agent = local.group_by_agent;
+    }
+    // Override log settings if specified
+    if local.log_level.is_some() {
+        config.log_level = local.log_level;
+    }
+    if local.log_format.is_some() {
+        config.log_format = local.log_format;
+    }
+    if local.log_file.is_some() {
+        config.log_file = local.log_file;
+    }
+}
+
+/// Merge a project-local config into `config`.
+///
+/// Only aliases and display settings (staleness thresholds,
+/// `current_project_only`, `group_by_agent`) are taken. Task roots,
+/// logging, pruning and archiving stay with the host and global config, so
+/// opening a repository can't redirect discovery or logging, or delete the
+/// user's todos. Returns the keys that were set but ignored.
+pub fn merge_local_overrides(config: &mut PluginConfig, local: PluginConfig) -> Vec<&'static str> {
+    let ignored: Vec<&'static str> = set_keys(&local)
+        .into_iter()
+        .filter(|key| !LOCAL_KEYS.contains(key))
+        .collect();
+
+    merge_overrides(
+        config,
+        PluginConfig {
+            aliases: local.aliases,
+            idle_threshold_minutes: local.idle_threshold_minutes,
+            staleness_threshold_minutes: local.staleness_threshold_minutes,
+            abandoned_threshold_minutes: local.abandoned_threshold_minutes,
+            current_project_only: local.current_project_only,
+            group_by_agent: local.group_by_agent,
+            ..Default::default()
+        },
+    );
+    ignored
+}
+
+/// Read one config file, recording problems in `report`.
+///
+/// Returns None if the file doesn't exist or can't be read. If it doesn't
+/// parse, its last valid contents are returned.
+fn read_config_file(path: &Path, report: &mut ConfigLoad) -> Option<PluginConfig> {
+    let content = std::fs::read_to_string(path).ok()?;
+    report.files.push(path.to_path_buf());
+    let mut last_good = last_good_configs().lock().unwrap();
+
+    match parse_config(&content) {
+        Ok((config, unknown_keys)) => {
+            report.warnings.extend(
+                unknown_keys
+                    .iter()
+                    .map(|key| format!("{}: unknown key `{}`", path.display(), key)),
+            );
+            last_good.insert(path.to_path_buf(), config.clone());
+            Some(config)
+        }
+        Err(message) => {
+            report.errors.push(PluginError::ConfigParseError(format!(
+                "{} {}",
+                path.display(),
+                message
+            )));
+            last_good.get(path).cloned()
+        }
+    }
+}
+
+/// Parse a config file, returning the config and its unknown top-level keys.
+///
+/// Errors are formatted as "line {n}: {message}".
+fn parse_config(content: &str) -> Result<(PluginConfig, Vec<String>), String> {
+    let config = toml::from_str::<PluginConfig>(content)
+        .map_err(|e| describe_toml_error(content, e.span(), e.message()))?;
+
+    let unknown_keys = content
+        .parse::<toml::Table>()
+        .map(|table| {
+            table
+                .keys()
+                .filter(|key| !is_config_key(key))
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok((config, unknown_keys))
+}
+
+/// Format a TOML error as "line {n}: {message}".
+fn describe_toml_error(content: &str, span: Option<Range<usize>>, message: &str) -> String {
+    let line = span
+        .map(|span| content[..span.start].matches('\n').count() + 1)
+        .unwrap_or(1);
+    format!("line {}: {}", line, message)
+}
+
+/// Store the plugin settings received from the host.
+///
+/// They are merged below the config files by every later [`load_config`].
+pub fn set_host_config(config: PluginConfig) {
+    *host_config().lock().unwrap() = config;
+}
+
+/// Plugin settings received from the host (empty until the host sends them).
+fn host_config() -> &'static Mutex<PluginConfig> {
+    static HOST_CONFIG: OnceLock<Mutex<PluginConfig>> = OnceLock::new();
+    HOST_CONFIG.get_or_init(|| Mutex::new(PluginConfig::default()))
+}
+
+/// Last successfully parsed contents of each config file.
+fn last_good_configs() -> &'static Mutex<HashMap<PathBuf, PluginConfig>> {
+    static LAST_GOOD: OnceLock<Mutex<HashMap<PathBuf, PluginConfig>>> = OnceLock::new();
+    LAST_GOOD.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Get the default task root: `$CLAUDE_CONFIG_DIR/tasks` if the variable is
+/// set, otherwise `~/.claude/tasks`.
+pub fn default_task_root() -> Option<PathBuf> {
+    match std::env::var_os("CLAUDE_CONFIG_DIR") {
+        Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir).join("tasks")),
+        _ => dirs::home_dir().map(|h| h.join(".claude").join("tasks")),
+    }
+}
+
+/// Expand a leading `~` to the home directory.
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix('~'), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest.trim_start_matches(['/', '\\'])),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Format a task root for display, abbreviating the home directory to `~`.
+pub fn display_root(root: &Path) -> String {
+    match dirs::home_dir().and_then(|h| root.strip_prefix(h).ok().map(|r| r.to_path_buf())) {
+        Some(rest) => format!("~/{}", rest.display()),
+        None => root.display().to_string(),
+    }
+}
+
+// ============================================================================
+// Alias persistence
+// ============================================================================
+
+/// Which config file an alias change is written to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigScope {
+    /// ~/.config/totui/claude-tasks.toml
+    Global,
+    /// .totui/aliases.toml in the project (see [`local_config_dir`])
+    Local,
+}
+
+impl ConfigScope {
+    /// Path of the config file for this scope.
+    pub fn path(self) -> Option<PathBuf> {
+        match self {
+            ConfigScope::Global => global_config_path(),
+            ConfigScope::Local => Some(local_config_path()),
+        }
+    }
+
+    /// Default scope for new aliases: local if a local config exists, else global.
+    pub fn default_for_alias() -> Self {
+        if local_config_paths().iter().any(|p| p.exists()) {
+            ConfigScope::Local
+        } else {
+            ConfigScope::Global
         }
     }
+}
+
+/// Persist an alias to the config file for `scope`.
+///
+/// Returns the path that was written.
+pub fn save_alias(uuid: &str, name: &str, scope: ConfigScope) -> Result<PathBuf, PluginError> {
+    let path = scope
+        .path()
+        .ok_or_else(|| PluginError::ConfigWriteError("no config directory".to_string()))?;
+    save_alias_to_path(&path, uuid, name)?;
+    Ok(path)
+}
 
-    // Merge local config (overrides global)
-    let local_path = local_config_path();
-    if local_path.exists() {
-        if let Ok(content) = std::fs::read_to_string(&local_path) {
-            if let Ok(local) = toml::from_str::<PluginConfig>(&content) {
-                // Merge aliases - local overrides global
-                config.aliases.extend(local.aliases);
-                // Override staleness if specified
-                if local.staleness_threshold_minutes.is_some() {
-                    config.staleness_threshold_minutes = local.staleness_threshold_minutes;
-                }
-            }
+/// Remove an alias from both the global and local config files.
+///
+/// Returns the paths that contained the alias.
+pub fn remove_alias(uuid: &str) -> Result<Vec<PathBuf>, PluginError> {
+    let mut changed = Vec::new();
+    for path in global_config_path().into_iter().chain(local_config_paths()) {
+        if path.exists() && remove_alias_from_path(&path, uuid)? {
+            changed.push(path);
         }
     }
+    Ok(changed)
+}
+
+/// Set `aliases.{uuid} = name` in the TOML file at `path`, creating it if needed.
+pub fn save_alias_to_path(path: &Path, uuid: &str, name: &str) -> Result<(), PluginError> {
+    let mut doc = read_document(path)?;
+    set_alias_in_document(&mut doc, uuid, name)?;
+    write_document(path, &doc)
+}
 
-    config
+/// Remove `aliases.{uuid}` from the TOML file at `path`.
+///
+/// Returns whether the alias was present.
+pub fn remove_alias_from_path(path: &Path, uuid: &str) -> Result<bool, PluginError> {
+    let mut doc = read_document(path)?;
+    let removed = remove_alias_from_document(&mut doc, uuid);
+    if removed {
+        write_document(path, &doc)?;
+    }
+    Ok(removed)
+}
+
+/// Set an alias in a TOML document, adding an `[aliases]` table if missing.
+pub fn set_alias_in_document(
+    doc: &mut DocumentMut,
+    uuid: &str,
+    name: &str,
+) -> Result<(), PluginError> {
+    let aliases = doc
+        .entry("aliases")
+        .or_insert(toml_edit::table())
+        .as_table_like_mut()
+        .ok_or_else(|| PluginError::ConfigParseError("aliases is not a table".to_string()))?;
+    aliases.insert(uuid, toml_edit::value(name));
+    Ok(())
+}
+
+/// Remove an alias from a TOML document. Returns whether it was present.
+pub fn remove_alias_from_document(doc: &mut DocumentMut, uuid: &str) -> bool {
+    doc.get_mut("aliases")
+        .and_then(|a| a.as_table_like_mut())
+        .and_then(|a| a.remove(uuid))
+        .is_some()
+}
+
+/// Read a TOML document, treating a missing file as empty.
+fn read_document(path: &Path) -> Result<DocumentMut, PluginError> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => {
+            let message = format!("{}: {}", path.display(), e);
+            return Err(PluginError::ConfigWriteError(message));
+        }
+    };
+    content.parse::<DocumentMut>().map_err(|e| {
+        let message = describe_toml_error(&content, e.span(), e.message());
+        PluginError::ConfigParseError(format!("{} {}", path.display(), message))
+    })
+}
+
+/// Write a TOML document, creating parent directories as needed.
+fn write_document(path: &Path, doc: &DocumentMut) -> Result<(), PluginError> {
+    let write = || -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, doc.to_string())
+    };
+    write().map_err(|e| PluginError::ConfigWriteError(format!("{}: {}", path.display(), e)))
 }
 
 /// Get global config path: ~/.config/totui/claude-tasks.toml
 fn global_config_path() -> Option<PathBuf> {
-    dirs::config_dir().map(|p| p.join("totui").join("claude-tasks.toml"))
+    plugin_paths()
+        .config_dir
+        .map(|p| p.join("claude-tasks.toml"))
 }
 
-/// Get local config path: .totui/aliases.toml
+/// Get local config path: aliases.toml in the project's `.totui/` folder
 fn local_config_path() -> PathBuf {
-    PathBuf::from(".totui").join("aliases.toml")
+    current_local_config_dir().join(LOCAL_ALIASES_FILE)
 }
 
-/// Format tasklist display with alias if available.
+/// Get the local config paths, in merge order: claude-tasks.toml, then aliases.toml
+fn local_config_paths() -> Vec<PathBuf> {
+    let dir = current_local_config_dir();
+    vec![dir.join(LOCAL_CONFIG_FILE), dir.join(LOCAL_ALIASES_FILE)]
+}
+
+/// Get the `.totui/` folder for the current directory (see [`local_config_dir`]).
+fn current_local_config_dir() -> PathBuf {
+    match plugin_paths().working_dir {
+        Some(dir) => local_config_dir(&dir),
+        None => PathBuf::from(LOCAL_CONFIG_DIR),
+    }
+}
+
+/// Find the project-local config folder for `start`.
 ///
-/// Returns "Alias (a1b2c3...)" if alias exists, otherwise just UUID.
-pub fn format_tasklist_display(uuid: &str, config: &PluginConfig) -> String {
-    match config.get_alias(uuid) {
-        Some(alias) => {
-            let short_uuid = &uuid[..8.min(uuid.len())];
-            format!("{} ({}...)", alias, short_uuid)
+/// Walks up from `start` to the nearest folder containing `.totui/`,
+/// stopping at the git root. Without one, the `.totui/` folder of the
+/// project root (see [`project_root`]) is returned, where aliases saved
+/// with `--local` create it.
+pub fn local_config_dir(start: &Path) -> PathBuf {
+    for dir in start.ancestors() {
+        let candidate = dir.join(LOCAL_CONFIG_DIR);
+        if candidate.is_dir() {
+            return candidate;
         }
+        if dir.join(".git").exists() {
+            break;
+        }
+    }
+    project_root(start).join(LOCAL_CONFIG_DIR)
+}
+
+/// Config files `load_config` reads, in merge order (global, then local).
+///
+/// Local paths are absolute so file system events can be matched against
+/// them.
+pub fn config_paths() -> Vec<PathBuf> {
+    global_config_path()
+        .into_iter()
+        .chain(local_config_paths())
+        .collect()
+}
+
+/// Get the name for a tasklist: manual alias first, then the name derived
+/// from the Claude project the session belongs to.
+pub fn tasklist_name(uuid: &str, config: &PluginConfig) -> Option<String> {
+    config.get_alias(uuid).map(|s| s.to_string()).or_else(|| {
+        config
+            .task_roots()
+            .iter()
+            .find_map(|root| find_project(root, session_id(uuid)))
+            .map(|p| p.name())
+    })
+}
+
+/// Format tasklist display with alias or project name if available.
+///
+/// Returns "Name (a1b2c3...)" if a name exists, otherwise just UUID.
+pub fn format_tasklist_display(uuid: &str, config: &PluginConfig) -> String {
+    match tasklist_name(uuid, config) {
+        Some(name) => format_named(&name, uuid),
         None => uuid.to_string(),
     }
 }
 
+/// Format "Name (a1b2c3...)" with a shortened UUID.
+fn format_named(name: &str, uuid: &str) -> String {
+    let short_uuid = &uuid[..8.min(uuid.len())];
+    format!("{} ({}...)", name, short_uuid)
+}
+
 /// Generate Select options for tasklist picker.
 ///
-/// Format: "Alias (uuid...) - N tasks, updated X ago" or "uuid - N tasks, updated X ago"
-/// Returns (display_string, uuid) pairs.
+/// Each option is formatted by [`format_tasklist_option`].
+/// Returns (display_string, uuid) pairs, grouped by project (see
+/// [`group_tasklist_options`]) relative to the current working directory.
+/// Archived tasklists are left out; `unarchive` brings them back.
 pub fn generate_tasklist_options(config: &PluginConfig) -> Vec<(String, String)> {
-    let tasklists = discover_tasklists();
+    let mut tasklists = discover_tasklists(config);
+    tasklists.retain(|t| !crate::archive::is_archived(&t.id, t.last_modified));
+    let current_root = plugin_paths().working_dir.map(|d| project_root(&d));
 
-    tasklists
+    group_tasklist_options(tasklists, config, current_root.as_deref())
+}
+
+/// Group picker options by project.
+///
+/// - Tasklists of the current project come first, marked with "★"
+/// - Remaining tasklists follow grouped by project, with tasklists of
+///   unknown projects last
+/// - With `current_project_only`, other projects are hidden unless the
+///   current project has no tasklists
+///
+/// Every option selects a tasklist: groups are only conveyed by ordering
+/// (each option already names its project). Without any project info the
+/// list stays flat (most recent first).
+pub fn group_tasklist_options(
+    tasklists: Vec<TasklistInfo>,
+    config: &PluginConfig,
+    current_root: Option<&Path>,
+) -> Vec<(String, String)> {
+    // Only label task roots when tasklists come from more than one
+    let show_root = tasklists.iter().any(|t| t.root != tasklists[0].root);
+    let option = |t: &TasklistInfo| (format_tasklist_option(t, config, show_root), t.id.clone());
+
+    if tasklists.iter().all(|t| t.project.is_none()) {
+        return tasklists.iter().map(option).collect();
+    }
+
+    // Group by project root, keeping most-recent-first order within and across groups
+    let mut current = Vec::new();
+    let mut groups: Vec<(Option<PathBuf>, Vec<&TasklistInfo>)> = Vec::new();
+    for t in &tasklists {
+        let root = t.project.as_ref().map(|p| p.root());
+        if root.is_some() && root.as_deref() == current_root {
+            current.push(t);
+        } else if let Some(group) = groups.iter_mut().find(|(r, _)| *r == root) {
+            group.1.push(t);
+        } else {
+            groups.push((root, vec![t]));
+        }
+    }
+    groups.sort_by_key(|(root, _)| root.is_none());
+
+    let mut options: Vec<(String, String)> = current
         .into_iter()
         .map(|t| {
-            let display = format_tasklist_option(&t, config);
-            (display, t.id)
+            let (label, value) = option(t);
+            (format!("\u{2605} {}", label), value)
         })
-        .collect()
+        .collect();
+    if !options.is_empty() && config.current_project_only() {
+        return options;
+    }
+
+    for (_, group) in groups {
+        options.extend(group.into_iter().map(option));
+    }
+
+    options
 }
 
 /// Format a single tasklist for display in Select options.
-fn format_tasklist_option(tasklist: &TasklistInfo, config: &PluginConfig) -> String {
-    let name_part = match config.get_alias(&tasklist.id) {
-        Some(alias) => {
-            let short_uuid = &tasklist.id[..8.min(tasklist.id.len())];
-            format!("{} ({}...)", alias, short_uuid)
-        }
+///
+/// Format: "{name} - N tasks (A done, B in progress, C pending), {age}{marker}{root} - {detail}"
+/// - marker: stale or abandoned, for incomplete tasklists that went quiet
+/// - root: " @ {task root}", only with `show_root`
+/// - detail: the in-progress task, or the first sample subjects
+///
+/// Manual aliases take precedence over the derived project name.
+fn format_tasklist_option(
+    tasklist: &TasklistInfo,
+    config: &PluginConfig,
+    show_root: bool,
+) -> String {
+    let name = config
+        .get_alias(&tasklist.id)
+        .map(|s| s.to_string())
+        .or_else(|| tasklist.project.as_ref().map(|p| p.name()));
+    let name_part = match name {
+        Some(name) => format_named(&name, &tasklist.id),
         None => tasklist.id.clone(),
     };
 
+    let summary = tasklist.summary();
+    let counts = summary.counts;
     let age = format_age(tasklist.last_modified);
-    format!("{} - {} tasks, {}", name_part, tasklist.task_count, age)
+    let mut option = format!(
+        "{} - {} tasks ({} done, {} in progress, {} pending), {}",
+        name_part, summary.task_count, counts.completed, counts.in_progress, counts.pending, age
+    );
+
+    if !counts.is_complete() {
+        let idle = tasklist.last_modified.elapsed().unwrap_or_default();
+        match config.staleness_thresholds().level(idle) {
+            StalenessLevel::Abandoned => option.push_str(" \u{1F4A4} ABANDONED"),
+            StalenessLevel::Stale => option.push_str(" \u{23F0} STALE"),
+            StalenessLevel::Active | StalenessLevel::Idle => {}
+        }
+    }
+
+    if show_root {
+        option.push_str(" @ ");
+        option.push_str(&display_root(&tasklist.root));
+    }
+
+    let detail = match &summary.active_task {
+        Some(active) => format!("\u{25B6} {}", truncate(active, OPTION_SUBJECT_LEN)),
+        None => summary
+            .sample_tasks
+            .iter()
+            .map(|s| truncate(s, OPTION_SUBJECT_LEN))
+            .collect::<Vec<_>>()
+            .join(", "),
+    };
+    if !detail.is_empty() {
+        option.push_str(" - ");
+        option.push_str(&detail);
+    }
+
+    option
+}
+
+/// Truncate a subject to `max` characters, adding an ellipsis if cut.
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        text.to_string()
+    } else {
+        let cut: String = text.chars().take(max.saturating_sub(1)).collect();
+        format!("{}\u{2026}", cut)
+    }
 }
 
 /// Format time since last modified.
@@ -141,6 +931,8 @@ fn format_age(time: SystemTime) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cache::TasklistSummary;
+    use crate::discovery::{ProjectInfo, StatusCounts};
 
     #[test]
     fn test_plugin_config_default() {
@@ -183,7 +975,12 @@ mod tests {
 
     #[test]
     fn test_format_tasklist_display_no_alias() {
-        let config = PluginConfig::default();
+        // An empty task root, so no transcript of the user's can name it
+        let dir = tempfile::tempdir().unwrap();
+        let config = PluginConfig {
+            task_roots: Some(vec![dir.path().join("tasks").display().to_string()]),
+            ..Default::default()
+        };
         let display = format_tasklist_display("abc-123", &config);
         assert_eq!(display, "abc-123");
     }
@@ -204,6 +1001,208 @@ mod tests {
         assert_eq!(config.get_alias("def-456"), Some("Project B"));
     }
 
+    #[test]
+    fn test_archive_triggers_default_off() {
+        let config = PluginConfig::default();
+        assert!(!config.archive_on_complete());
+        assert!(!config.archive_on_stale());
+    }
+
+    #[test]
+    fn test_parse_archive_triggers() {
+        let toml_str = r#"
+            archive_on_complete = true
+            archive_on_stale = false
+        "#;
+
+        let config: PluginConfig = toml::from_str(toml_str).unwrap();
+        assert!(config.archive_on_complete());
+        assert!(!config.archive_on_stale());
+    }
+
+    #[test]
+    fn test_parse_prune_settings() {
+        let toml_str = r#"
+            prune_action = "archive"
+            prune_on_first_invocation = "preview"
+        "#;
+
+        let config: PluginConfig = toml::from_str(toml_str).unwrap();
+        assert_eq!(config.prune_action(), PruneAction::Archive);
+        assert_eq!(
+            config.prune_on_first_invocation(),
+            PruneOnFirstInvocation::Preview
+        );
+
+        // The old name is still accepted
+        let (config, unknown) = parse_config(r#"prune_on_startup = "apply""#).unwrap();
+        assert_eq!(
+            config.prune_on_first_invocation(),
+            PruneOnFirstInvocation::Apply
+        );
+        assert!(unknown.is_empty());
+
+        let defaults = PluginConfig::default();
+        assert_eq!(defaults.prune_action(), PruneAction::Delete);
+        assert_eq!(
+            defaults.prune_on_first_invocation(),
+            PruneOnFirstInvocation::Off
+        );
+    }
+
+    #[test]
+    fn test_set_alias_preserves_comments() {
+        let mut doc: DocumentMut = r#"# My settings
+staleness_threshold_minutes = 20 # minutes
+
+[aliases]
+# the backend
+"abc-123" = "Backend"
+"#
+        .parse()
+        .unwrap();
+
+        set_alias_in_document(&mut doc, "def-456", "Frontend").unwrap();
+        let out = doc.to_string();
+
+        assert!(out.contains("# My settings"));
+        assert!(out.contains("= 20 # minutes"));
+        assert!(out.contains("# the backend"));
+
+        let config: PluginConfig = toml::from_str(&out).unwrap();
+        assert_eq!(config.get_alias("abc-123"), Some("Backend"));
+        assert_eq!(config.get_alias("def-456"), Some("Frontend"));
+    }
+
+    #[test]
+    fn test_set_alias_creates_table() {
+        let mut doc = DocumentMut::new();
+        set_alias_in_document(&mut doc, "abc-123", "Backend").unwrap();
+
+        let config: PluginConfig = toml::from_str(&doc.to_string()).unwrap();
+        assert_eq!(config.get_alias("abc-123"), Some("Backend"));
+    }
+
+    #[test]
+    fn test_remove_alias_from_document() {
+        let mut doc: DocumentMut = "[aliases]\n\"abc-123\" = \"Backend\"\n".parse().unwrap();
+
+        assert!(remove_alias_from_document(&mut doc, "abc-123"));
+        assert!(!remove_alias_from_document(&mut doc, "abc-123"));
+        assert!(!remove_alias_from_document(
+            &mut DocumentMut::new(),
+            "abc-123"
+        ));
+    }
+
+    #[test]
+    fn test_save_and_remove_alias_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("aliases.toml");
+
+        save_alias_to_path(&path, "abc-123", "Backend").unwrap();
+        let config: PluginConfig =
+            toml::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(config.get_alias("abc-123"), Some("Backend"));
+
+        assert!(remove_alias_from_path(&path, "abc-123").unwrap());
+        let config: PluginConfig =
+            toml::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert!(config.aliases.is_empty());
+    }
+
+    #[test]
+    fn test_save_alias_invalid_toml() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("aliases.toml");
+        std::fs::write(&path, "not = [valid").unwrap();
+
+        let err = save_alias_to_path(&path, "abc-123", "Backend").unwrap_err();
+        assert!(matches!(err, PluginError::ConfigParseError(_)));
+        assert!(err.to_string().contains("line 1:"), "{}", err);
+        // File left untouched
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "not = [valid");
+    }
+
+    #[test]
+    fn test_parse_current_project_only() {
+        let config: PluginConfig = toml::from_str("current_project_only = true").unwrap();
+        assert!(config.current_project_only());
+        assert!(!PluginConfig::default().current_project_only());
+    }
+
+    #[test]
+    fn test_parse_discovery_disk_cache() {
+        let config: PluginConfig = toml::from_str("discovery_disk_cache = true").unwrap();
+        assert!(config.discovery_disk_cache());
+        assert!(!PluginConfig::default().discovery_disk_cache());
+    }
+
+    #[test]
+    fn test_parse_group_by_agent() {
+        let config: PluginConfig = toml::from_str("group_by_agent = true").unwrap();
+        assert!(config.group_by_agent());
+        assert!(!PluginConfig::default().group_by_agent());
+    }
+
+    #[test]
+    fn test_parse_log_settings() {
+        let config: PluginConfig = toml::from_str(
+            r#"
+log_level = "debug"
+log_format = "json"
+log_file = "/var/log/claude-tasks.log"
+"#,
+        )
+        .unwrap();
+        let settings = config.log_settings();
+        assert_eq!(settings.level, Some(Level::Debug));
+        assert_eq!(settings.format, Some(LogFormat::Json));
+        assert_eq!(
+            settings.file,
+            Some(PathBuf::from("/var/log/claude-tasks.log"))
+        );
+        assert_eq!(
+            PluginConfig::default().log_settings(),
+            LogSettings::default()
+        );
+        assert!(toml::from_str::<PluginConfig>("log_level = \"loud\"").is_err());
+    }
+
+    #[test]
+    fn test_task_roots() {
+        let config: PluginConfig = toml::from_str(
+            r#"
+            task_roots = ["~/.claude/tasks", "/mnt/devcontainer/.claude/tasks"]
+            watch_mode = "poll"
+        "#,
+        )
+        .unwrap();
+
+        let roots = config.task_roots();
+        assert_eq!(roots.len(), 2);
+        if let Some(home) = dirs::home_dir() {
+            assert_eq!(roots[0], home.join(".claude/tasks"));
+        }
+        assert_eq!(roots[1], PathBuf::from("/mnt/devcontainer/.claude/tasks"));
+        assert_eq!(config.watch_mode(), WatchMode::Poll);
+
+        // Default is a single root
+        assert_eq!(
+            PluginConfig::default().task_roots(),
+            default_task_root().into_iter().collect::<Vec<_>>()
+        );
+        assert_eq!(PluginConfig::default().watch_mode(), WatchMode::Native);
+    }
+
+    #[test]
+    fn test_display_root() {
+        assert_eq!(display_root(Path::new("/mnt/tasks")), "/mnt/tasks");
+        if let Some(home) = dirs::home_dir() {
+            assert_eq!(display_root(&home.join(".claude/tasks")), "~/.claude/tasks");
+        }
+    }
+
     #[test]
     fn test_parse_empty_toml() {
         let config: PluginConfig = toml::from_str("").unwrap();
@@ -249,15 +1248,12 @@ mod tests {
             .aliases
             .insert("abc-123-def-456".to_string(), "MyProject".to_string());
 
-        let tasklist = TasklistInfo {
-            id: "abc-123-def-456".to_string(),
-            path: PathBuf::from("/test"),
+        let tasklist = make_tasklist("abc-123-def-456", None).with_summary(TasklistSummary {
             task_count: 5,
-            last_modified: SystemTime::now(),
-            sample_tasks: vec![],
-        };
+            ..Default::default()
+        });
 
-        let display = format_tasklist_option(&tasklist, &config);
+        let display = format_tasklist_option(&tasklist, &config, false);
         assert!(display.starts_with("MyProject (abc-123-...)"));
         assert!(display.contains("5 tasks"));
         assert!(display.contains("just now"));
@@ -267,17 +1263,301 @@ mod tests {
     fn test_format_tasklist_option_no_alias() {
         let config = PluginConfig::default();
 
-        let tasklist = TasklistInfo {
-            id: "abc-123-def-456".to_string(),
-            path: PathBuf::from("/test"),
+        let tasklist = make_tasklist("abc-123-def-456", None).with_summary(TasklistSummary {
             task_count: 3,
-            last_modified: SystemTime::now(),
-            sample_tasks: vec![],
-        };
+            ..Default::default()
+        });
 
-        let display = format_tasklist_option(&tasklist, &config);
+        let display = format_tasklist_option(&tasklist, &config, false);
         assert!(display.starts_with("abc-123-def-456"));
         assert!(display.contains("3 tasks"));
         assert!(display.contains("just now"));
     }
+
+    #[test]
+    fn test_format_tasklist_option_project_name() {
+        let mut tasklist = make_tasklist("abc-123-def-456", None);
+        tasklist.project = Some(ProjectInfo {
+            cwd: PathBuf::from("/nonexistent/my-repo"),
+            git_branch: Some("main".to_string()),
+        });
+
+        let display = format_tasklist_option(&tasklist, &PluginConfig::default(), false);
+        assert!(display.starts_with("my-repo [main] (abc-123-...)"));
+
+        // Manual alias takes precedence
+        let mut config = PluginConfig::default();
+        config
+            .aliases
+            .insert("abc-123-def-456".to_string(), "MyProject".to_string());
+        let display = format_tasklist_option(&tasklist, &config, false);
+        assert!(display.starts_with("MyProject (abc-123-...)"));
+    }
+
+    fn make_tasklist(id: &str, cwd: Option<&str>) -> TasklistInfo {
+        let project = cwd.map(|c| ProjectInfo {
+            cwd: PathBuf::from(c),
+            git_branch: None,
+        });
+        TasklistInfo::new(
+            id.to_string(),
+            PathBuf::from("/test").join(id),
+            PathBuf::from("/test"),
+            SystemTime::now(),
+            project,
+        )
+        .with_summary(TasklistSummary {
+            task_count: 1,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_group_tasklist_options_flat_without_projects() {
+        let tasklists = vec![make_tasklist("a", None), make_tasklist("b", None)];
+        let options = group_tasklist_options(tasklists, &PluginConfig::default(), None);
+
+        let values: Vec<&str> = options.iter().map(|(_, v)| v.as_str()).collect();
+        assert_eq!(values, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_group_tasklist_options_shows_root_when_multiple() {
+        let single = vec![make_tasklist("a", None), make_tasklist("b", None)];
+        let options = group_tasklist_options(single.clone(), &PluginConfig::default(), None);
+        assert!(options.iter().all(|(label, _)| !label.contains(" @ ")));
+
+        let mut multi = single;
+        multi[1].root = PathBuf::from("/mnt/other/tasks");
+        let options = group_tasklist_options(multi, &PluginConfig::default(), None);
+        assert!(options[0].0.contains(" @ /test"));
+        assert!(options[1].0.contains(" @ /mnt/other/tasks"));
+    }
+
+    #[test]
+    fn test_group_tasklist_options_current_first() {
+        let tasklists = vec![
+            make_tasklist("other-1", Some("/nonexistent/api")),
+            make_tasklist("mine-1", Some("/nonexistent/web")),
+            make_tasklist("unknown-1", None),
+            make_tasklist("other-2", Some("/nonexistent/api")),
+        ];
+        let options = group_tasklist_options(
+            tasklists,
+            &PluginConfig::default(),
+            Some(Path::new("/nonexistent/web")),
+        );
+
+        let values: Vec<&str> = options.iter().map(|(_, v)| v.as_str()).collect();
+        assert_eq!(values, vec!["mine-1", "other-1", "other-2", "unknown-1"]);
+        assert!(options[0].0.starts_with("\u{2605} web (mine-1...)"));
+        assert!(options[1].0.starts_with("api (other-1...)"));
+    }
+
+    #[test]
+    fn test_group_tasklist_options_current_only() {
+        let tasklists = vec![
+            make_tasklist("other-1", Some("/nonexistent/api")),
+            make_tasklist("mine-1", Some("/nonexistent/web")),
+        ];
+        let config = PluginConfig {
+            current_project_only: Some(true),
+            ..Default::default()
+        };
+
+        let options = group_tasklist_options(
+            tasklists.clone(),
+            &config,
+            Some(Path::new("/nonexistent/web")),
+        );
+        let values: Vec<&str> = options.iter().map(|(_, v)| v.as_str()).collect();
+        assert_eq!(values, vec!["mine-1"]);
+
+        // Falls back to all projects when the current one has no tasklists
+        let options = group_tasklist_options(tasklists, &config, Some(Path::new("/elsewhere")));
+        assert_eq!(options.len(), 2);
+    }
+
+    #[test]
+    fn test_format_tasklist_option_progress_and_active_task() {
+        let tasklist = make_tasklist("abc-123-def-456", None).with_summary(TasklistSummary {
+            task_count: 4,
+            counts: StatusCounts {
+                completed: 2,
+                in_progress: 1,
+                pending: 1,
+            },
+            active_task: Some("Writing the parser".to_string()),
+            sample_tasks: vec!["First".to_string()],
+        });
+
+        let display = format_tasklist_option(&tasklist, &PluginConfig::default(), false);
+        assert!(display.contains("4 tasks (2 done, 1 in progress, 1 pending)"));
+        assert!(display.ends_with("- \u{25B6} Writing the parser"));
+        assert!(!display.contains("STALE"));
+    }
+
+    #[test]
+    fn test_format_tasklist_option_samples_and_markers() {
+        use std::time::Duration;
+
+        let summary = TasklistSummary {
+            task_count: 1,
+            counts: StatusCounts {
+                pending: 1,
+                ..Default::default()
+            },
+            sample_tasks: vec!["First".to_string(), "Second".to_string()],
+            ..Default::default()
+        };
+        let mut tasklist = make_tasklist("abc-123-def-456", None).with_summary(summary.clone());
+        tasklist.last_modified = SystemTime::now() - Duration::from_secs(30 * 60);
+
+        let display = format_tasklist_option(&tasklist, &PluginConfig::default(), false);
+        assert!(display.contains("\u{23F0} STALE"));
+        assert!(display.ends_with("- First, Second"));
+
+        tasklist.last_modified = SystemTime::now() - Duration::from_secs(2 * 86400);
+        let display = format_tasklist_option(&tasklist, &PluginConfig::default(), false);
+        assert!(display.contains("ABANDONED"));
+
+        // Completed tasklists are never marked
+        tasklist = tasklist.with_summary(TasklistSummary {
+            counts: StatusCounts {
+                completed: 1,
+                ..Default::default()
+            },
+            ..summary
+        });
+        let display = format_tasklist_option(&tasklist, &PluginConfig::default(), false);
+        assert!(!display.contains("ABANDONED"));
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("short", 10), "short");
+        assert_eq!(truncate("exactly ten", 11), "exactly ten");
+        assert_eq!(truncate("a longer subject", 8), "a longe\u{2026}");
+    }
+
+    #[test]
+    fn test_config_paths_local_is_absolute() {
+        let paths = config_paths();
+        let local = paths.last().unwrap();
+        assert!(local.is_absolute());
+        assert!(local.ends_with(".totui/aliases.toml"));
+    }
+
+    #[test]
+    fn test_parse_config_reports_unknown_keys() {
+        let content = r#"
+group_by_agent = true
+staleness_threshold = 300
+
+[aliases]
+"abc" = "Project"
+"#;
+        let (config, unknown) = parse_config(content).unwrap();
+        assert!(config.group_by_agent());
+        assert_eq!(config.get_alias("abc"), Some("Project"));
+        assert_eq!(unknown, vec!["staleness_threshold"]);
+    }
+
+    #[test]
+    fn test_parse_config_error_has_line() {
+        let content = "group_by_agent = true\nwatch_mode = \"sometimes\"\n";
+        let err = parse_config(content).unwrap_err();
+        assert!(err.starts_with("line 2: "), "{}", err);
+    }
+
+    #[test]
+    fn test_read_config_file_keeps_last_good() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("claude-tasks.toml");
+
+        std::fs::write(&path, "[aliases]\n\"abc\" = \"Project\"\n").unwrap();
+        let mut report = ConfigLoad::default();
+        let config = read_config_file(&path, &mut report).unwrap();
+        assert_eq!(config.get_alias("abc"), Some("Project"));
+        assert!(report.errors.is_empty());
+
+        // A typo: the previous contents stay in use and the error is reported
+        std::fs::write(&path, "[aliases\n\"abc\" = \"Renamed\"\n").unwrap();
+        let config = read_config_file(&path, &mut report).unwrap();
+        assert_eq!(config.get_alias("abc"), Some("Project"));
+        assert_eq!(report.errors.len(), 1);
+        let message = report.errors[0].to_string();
+        assert!(message.starts_with("Invalid config: "));
+        assert!(message.contains("line 1"), "{}", message);
+    }
+
+    #[test]
+    fn test_local_config_only_sets_display_settings() {
+        let global: PluginConfig = toml::from_str(
+            r#"
+task_roots = ["~/.claude/tasks"]
+prune_on_first_invocation = "off"
+"#,
+        )
+        .unwrap();
+        let local: PluginConfig = toml::from_str(
+            r#"
+group_by_agent = true
+staleness_threshold_minutes = 30
+log_file = "/home/user/.bashrc"
+task_roots = ["/"]
+prune_on_first_invocation = "apply"
+prune_action = "delete"
+
+[aliases]
+"abc" = "Project"
+"#,
+        )
+        .unwrap();
+
+        let mut config = global.clone();
+        let ignored = merge_local_overrides(&mut config, local);
+
+        assert!(config.group_by_agent());
+        assert_eq!(config.staleness_threshold(), 30);
+        assert_eq!(config.get_alias("abc"), Some("Project"));
+        assert_eq!(config.log_file, None);
+        assert_eq!(config.task_roots, global.task_roots);
+        assert_eq!(
+            config.prune_on_first_invocation(),
+            PruneOnFirstInvocation::Off
+        );
+        assert_eq!(config.prune_action, None);
+        assert_eq!(
+            ignored,
+            vec![
+                "prune_action",
+                "prune_on_first_invocation",
+                "task_roots",
+                "log_file"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_local_config_dir_walks_up_to_git_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path().join("repo");
+        let nested = repo.join("crates").join("core");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::create_dir(repo.join(".git")).unwrap();
+
+        // No .totui up to the git root: the project root's folder
+        std::fs::create_dir(dir.path().join(".totui")).unwrap();
+        assert_eq!(local_config_dir(&nested), repo.join(".totui"));
+
+        // The nearest .totui wins
+        std::fs::create_dir(repo.join(".totui")).unwrap();
+        assert_eq!(local_config_dir(&nested), repo.join(".totui"));
+        std::fs::create_dir(repo.join("crates").join(".totui")).unwrap();
+        assert_eq!(
+            local_config_dir(&nested),
+            repo.join("crates").join(".totui")
+        );
+    }
 }
@@ -1,89 +1,453 @@
 //! Tasklist discovery and metadata collection.
 //!
-//! Discovers Claude Code tasklist folders in `~/.claude/tasks/` and provides
-//! metadata about each tasklist including task count, last modified time,
-//! and sample task subjects.
-
-use crate::claude_task::ClaudeTask;
+//! Discovers Claude Code tasklist folders in the configured task roots
+//! (default `~/.claude/tasks/`) and provides metadata about each tasklist
+//! including task count, last modified time, and sample task subjects.
+//! Counts and samples are parsed on first use (see [`TasklistInfo::summary`]).
+//!
+//! Legacy TodoWrite files (`todos/<session>-agent-<agent>.json` next to the
+//! task root) are discovered as tasklists too; see [`scan_tasklist`].
+//!
+//! Tasklist UUIDs are Claude session IDs. Session transcripts live next to
+//! the task root in `projects/<encoded-cwd>/<session>.jsonl` and record the
+//! working directory and git branch, which are used to derive a readable name.
+
+use crate::cache::{with_cache, DiscoveryCache, Fingerprint, TasklistSummary};
+use crate::claude_task::{parse_legacy_todos, ClaudeTask};
+use crate::config::PluginConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::BufRead;
 use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 use std::time::SystemTime;
 
-/// Information about a discovered Claude tasklist folder.
+/// Number of transcript lines read when looking for the cwd and git branch.
+const MAX_TRANSCRIPT_LINES: usize = 50;
+
+/// Folder next to each task root holding legacy TodoWrite files.
+const LEGACY_TODOS_DIR: &str = "todos";
+
+/// Separator between session and agent ID in legacy TodoWrite file names.
+const LEGACY_AGENT_SEPARATOR: &str = "-agent-";
+
+/// Information about a discovered Claude tasklist.
 #[derive(Debug, Clone)]
 pub struct TasklistInfo {
-    /// UUID folder name (e.g., "d45035ac-8878-4400-9304-c43d1e9afcbe")
+    /// UUID folder name (e.g., "d45035ac-8878-4400-9304-c43d1e9afcbe"),
+    /// or the file stem of a legacy TodoWrite file
     pub id: String,
-    /// Full path to tasklist folder
+    /// Full path to tasklist folder (or legacy TodoWrite file)
     pub path: PathBuf,
-    /// Number of .json task files
-    pub task_count: usize,
-    /// Last modified time of the directory
+    /// Folder the tasklist was found in (e.g., ~/.claude/tasks or ~/.claude/todos)
+    pub root: PathBuf,
+    /// Time of the newest task file activity (see [`last_activity`])
     pub last_modified: SystemTime,
-    /// First 3 task subjects (ordered by numeric task id ascending)
-    pub sample_tasks: Vec<String>,
+    /// Claude project the session belongs to, if its transcript was found
+    pub project: Option<ProjectInfo>,
+    /// Task counts and sample subjects, parsed on first use
+    summary: OnceLock<TasklistSummary>,
 }
 
-/// Discover all tasklist folders in `~/.claude/tasks/`.
-///
-/// Returns an empty Vec if the tasks directory doesn't exist (not an error).
-/// Empty tasklists (with no tasks) are excluded.
-/// Tasklists are sorted by most recently modified first.
-pub fn discover_tasklists() -> Vec<TasklistInfo> {
-    let Some(home) = dirs::home_dir() else {
-        return vec![];
-    };
-
-    let tasks_dir = home.join(".claude/tasks");
+impl TasklistInfo {
+    /// Create a tasklist entry whose summary is parsed on first use.
+    pub fn new(
+        id: String,
+        path: PathBuf,
+        root: PathBuf,
+        last_modified: SystemTime,
+        project: Option<ProjectInfo>,
+    ) -> Self {
+        Self {
+            id,
+            path,
+            root,
+            last_modified,
+            project,
+            summary: OnceLock::new(),
+        }
+    }
 
-    if !tasks_dir.exists() {
-        return vec![];
+    /// Set the summary up front instead of parsing the task files.
+    pub fn with_summary(mut self, summary: TasklistSummary) -> Self {
+        self.summary = OnceLock::from(summary);
+        self
     }
 
-    let Ok(entries) = std::fs::read_dir(&tasks_dir) else {
-        return vec![];
-    };
+    /// Task counts and sample subjects of the tasklist.
+    ///
+    /// Parsed through the discovery cache on first use, so tasklists that
+    /// are only resolved by ID or name never have their task files read.
+    /// An unreadable tasklist gives an empty summary.
+    pub fn summary(&self) -> &TasklistSummary {
+        self.summary.get_or_init(|| {
+            with_cache(false, |cache| cache.summary(&self.id, &self.path).cloned())
+                .unwrap_or_default()
+        })
+    }
+}
 
-    let mut tasklists = Vec::new();
+/// Task counts by status.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatusCounts {
+    /// Tasks with status "completed"
+    pub completed: usize,
+    /// Tasks with status "in_progress"
+    pub in_progress: usize,
+    /// Tasks with any other status (normally "pending")
+    pub pending: usize,
+}
 
-    for entry in entries.flatten() {
-        let path = entry.path();
-        if !path.is_dir() {
-            continue;
+impl StatusCounts {
+    /// Count tasks by status.
+    pub fn from_tasks(tasks: &[ClaudeTask]) -> Self {
+        let mut counts = Self::default();
+        for task in tasks {
+            match task.status.as_str() {
+                "completed" => counts.completed += 1,
+                "in_progress" => counts.in_progress += 1,
+                _ => counts.pending += 1,
+            }
         }
+        counts
+    }
+
+    /// Whether every task is completed (and there is at least one task).
+    pub fn is_complete(&self) -> bool {
+        self.completed > 0 && self.in_progress == 0 && self.pending == 0
+    }
+}
+
+/// Get the active form (or subject) of the first in-progress task.
+pub fn active_task<'a>(tasks: impl IntoIterator<Item = &'a ClaudeTask>) -> Option<String> {
+    tasks
+        .into_iter()
+        .find(|t| t.status == "in_progress")
+        .map(|t| {
+            if t.active_form.is_empty() {
+                t.subject.clone()
+            } else {
+                t.active_form.clone()
+            }
+        })
+}
+
+/// Claude project a tasklist's session was started in.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProjectInfo {
+    /// Working directory of the Claude session
+    pub cwd: PathBuf,
+    /// Git branch the session was on, if any
+    pub git_branch: Option<String>,
+}
+
+impl ProjectInfo {
+    /// Project root: the enclosing git repository, or the cwd outside git.
+    pub fn root(&self) -> PathBuf {
+        project_root(&self.cwd)
+    }
 
-        let id = path
+    /// Readable name: "repo [branch]" or just "repo".
+    ///
+    /// The repo name is the git root's folder name, falling back to the
+    /// cwd's folder name outside a git repository.
+    pub fn name(&self) -> String {
+        let root = self.root();
+        let repo = root
             .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("unknown")
-            .to_string();
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| root.display().to_string());
 
-        let tasks = scan_tasks_directory(&path);
-        let task_count = tasks.len();
+        match &self.git_branch {
+            Some(branch) => format!("{} [{}]", repo, branch),
+            None => repo,
+        }
+    }
+}
 
-        let last_modified = entry
-            .metadata()
-            .and_then(|m| m.modified())
-            .unwrap_or(SystemTime::UNIX_EPOCH);
+/// Find the project root for a directory: the nearest ancestor containing
+/// `.git`, or the directory itself.
+pub fn project_root(dir: &Path) -> PathBuf {
+    dir.ancestors()
+        .find(|d| d.join(".git").exists())
+        .unwrap_or(dir)
+        .to_path_buf()
+}
 
-        let sample_tasks: Vec<String> = tasks.iter().take(3).map(|t| t.subject.clone()).collect();
+/// Fields of interest in a session transcript line.
+#[derive(Deserialize)]
+struct TranscriptEntry {
+    cwd: Option<String>,
+    #[serde(rename = "gitBranch")]
+    git_branch: Option<String>,
+}
 
-        tasklists.push(TasklistInfo {
-            id,
-            path,
-            task_count,
-            last_modified,
-            sample_tasks,
-        });
+/// Discover all tasklist folders in the configured task roots.
+///
+/// Missing roots are skipped (not an error).
+/// Empty tasklists (with no task files) are excluded.
+/// Tasklists are sorted by most recently modified first.
+///
+/// Fingerprints and projects come from the discovery cache (see
+/// [`crate::cache`]), so only tasklists whose files changed are looked at
+/// again. Task files are parsed lazily by [`TasklistInfo::summary`].
+pub fn discover_tasklists(config: &PluginConfig) -> Vec<TasklistInfo> {
+    let roots = config.task_roots();
+
+    with_cache(config.discovery_disk_cache(), |cache| {
+        discover_tasklists_in(&roots, cache)
+    })
+}
+
+/// Discover tasklist folders in `roots` using `cache` for summaries.
+///
+/// Legacy TodoWrite files in the `todos` folder next to each root are
+/// discovered too (see [`tasklist_paths`]).
+///
+/// If the same tasklist ID appears in several roots (e.g., a bind-mounted
+/// home), the first root wins.
+pub fn discover_tasklists_in(roots: &[PathBuf], cache: &mut DiscoveryCache) -> Vec<TasklistInfo> {
+    let mut tasklists = Vec::new();
+    let mut seen = HashSet::new();
+
+    for (folder, path) in roots.iter().flat_map(|root| tasklist_paths(root)) {
+        let Some(id) = tasklist_id_from_path(&path).map(String::from) else {
+            continue;
+        };
+
+        if !seen.insert(id.clone()) {
+            continue;
+        }
+
+        cache.refresh(&id, &path);
+        let Some(fingerprint) = cache.fingerprint(&id) else {
+            continue;
+        };
+        if fingerprint.file_count == 0 {
+            continue;
+        }
+
+        let project = cache.project(&id).cloned();
+        let mut tasklist =
+            TasklistInfo::new(id, path, folder, fingerprint.last_activity(), project);
+
+        // Claude writes an empty legacy list for every session; a single
+        // file is cheap to parse, so those are filtered by content
+        if is_legacy_todo_file(&tasklist.path) {
+            let summary = cache
+                .summary(&tasklist.id, &tasklist.path)
+                .cloned()
+                .unwrap_or_default();
+            if summary.task_count == 0 {
+                continue;
+            }
+            tasklist = tasklist.with_summary(summary);
+        }
+
+        tasklists.push(tasklist);
     }
 
-    // Filter out empty tasklists
-    tasklists.retain(|t| t.task_count > 0);
+    cache.retain(&seen);
 
     // Sort by most recently modified first
     tasklists.sort_by(|a, b| b.last_modified.cmp(&a.last_modified));
     tasklists
 }
 
+/// Time a tasklist last changed on disk.
+///
+/// This is the newest task file modification time (or the folder's, when a
+/// task was removed since), so it survives restarts of totui. Returns None
+/// if the tasklist doesn't exist.
+pub fn last_activity(path: &Path) -> Option<SystemTime> {
+    Fingerprint::of(path).map(|fingerprint| fingerprint.last_activity())
+}
+
+/// List the tasklists of a task root as (folder, path) pairs.
+///
+/// Covers tasklist folders in the root itself and legacy TodoWrite files
+/// in the `todos` folder next to it (e.g., `~/.claude/todos` for
+/// `~/.claude/tasks`). Missing folders give no entries.
+fn tasklist_paths(root: &Path) -> Vec<(PathBuf, PathBuf)> {
+    let mut paths: Vec<(PathBuf, PathBuf)> = std::fs::read_dir(root)
+        .into_iter()
+        .flat_map(|entries| entries.flatten())
+        .map(|e| e.path())
+        .filter(|p| p.is_dir())
+        .map(|p| (root.to_path_buf(), p))
+        .collect();
+
+    if let Some(todos_dir) = root.parent().map(|p| p.join(LEGACY_TODOS_DIR)) {
+        paths.extend(
+            std::fs::read_dir(&todos_dir)
+                .into_iter()
+                .flat_map(|entries| entries.flatten())
+                .map(|e| e.path())
+                .filter(|p| is_legacy_todo_file(p) && p.is_file())
+                .map(|p| (todos_dir.clone(), p)),
+        );
+    }
+
+    paths
+}
+
+/// List the IDs of all tasklists in the configured task roots.
+///
+/// Unlike [`discover_tasklists`], empty tasklists are included and task
+/// files are not parsed. Used to tell live tasklists from vanished ones.
+pub fn discover_tasklist_ids(config: &PluginConfig) -> Vec<String> {
+    config
+        .task_roots()
+        .iter()
+        .flat_map(|root| tasklist_paths(root))
+        .filter_map(|(_, p)| tasklist_id_from_path(&p).map(String::from))
+        .collect()
+}
+
+/// Whether a tasklist path is a legacy TodoWrite file rather than a folder.
+pub fn is_legacy_todo_file(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e == "json")
+}
+
+/// Get the tasklist ID of a tasklist path.
+///
+/// Tasklist folders are named after their session UUID; legacy TodoWrite
+/// files are identified by their file stem (`{session}-agent-{agent}`).
+pub fn tasklist_id_from_path(path: &Path) -> Option<&str> {
+    if is_legacy_todo_file(path) {
+        path.file_stem()?.to_str()
+    } else {
+        path.file_name()?.to_str()
+    }
+}
+
+/// Get the Claude session ID of a tasklist.
+///
+/// Tasklist folder IDs are session IDs; legacy TodoWrite IDs carry the
+/// agent ID after the session ID.
+pub fn session_id(tasklist_id: &str) -> &str {
+    tasklist_id
+        .split_once(LEGACY_AGENT_SEPARATOR)
+        .map_or(tasklist_id, |(session, _)| session)
+}
+
+/// Find the Claude project a session belongs to.
+///
+/// Transcripts are looked up in the `projects` folder next to the task root
+/// (e.g., `~/.claude/projects` for `~/.claude/tasks`).
+pub fn find_project(tasks_root: &Path, session_id: &str) -> Option<ProjectInfo> {
+    let projects_dir = tasks_root.parent()?.join("projects");
+    find_project_in(&projects_dir, session_id)
+}
+
+/// Find the Claude project of a tasklist in any of the task `roots`.
+pub fn find_tasklist_project(roots: &[PathBuf], tasklist_id: &str) -> Option<ProjectInfo> {
+    let session = session_id(tasklist_id);
+    roots.iter().find_map(|root| find_project(root, session))
+}
+
+/// Find `<projects_dir>/*/<session_id>.jsonl` and read its project info.
+pub fn find_project_in(projects_dir: &Path, session_id: &str) -> Option<ProjectInfo> {
+    let file_name = format!("{}.jsonl", session_id);
+
+    std::fs::read_dir(projects_dir)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path().join(&file_name))
+        .find(|path| path.is_file())
+        .and_then(|path| read_transcript_project(&path))
+}
+
+/// Read the cwd and git branch from the first lines of a session transcript.
+///
+/// Returns None if no line records a cwd.
+fn read_transcript_project(path: &Path) -> Option<ProjectInfo> {
+    let file = std::fs::File::open(path).ok()?;
+    let mut cwd = None;
+    let mut git_branch = None;
+
+    for line in std::io::BufReader::new(file)
+        .lines()
+        .take(MAX_TRANSCRIPT_LINES)
+        .map_while(Result::ok)
+    {
+        let Ok(entry) = serde_json::from_str::<TranscriptEntry>(&line) else {
+            continue;
+        };
+        if cwd.is_none() {
+            cwd = entry.cwd.filter(|c| !c.is_empty());
+        }
+        if git_branch.is_none() {
+            // Detached HEAD isn't a useful name
+            git_branch = entry.git_branch.filter(|b| !b.is_empty() && b != "HEAD");
+        }
+        if cwd.is_some() && git_branch.is_some() {
+            break;
+        }
+    }
+
+    Some(ProjectInfo {
+        cwd: PathBuf::from(cwd?),
+        git_branch,
+    })
+}
+
+/// Scan a tasklist and return all valid ClaudeTask entries.
+///
+/// Dispatches on the source format: tasklist folders are read with
+/// [`scan_tasks_directory`]; legacy TodoWrite files are parsed in list order
+/// (unreadable or malformed files give no tasks).
+pub fn scan_tasklist(path: &Path) -> Vec<ClaudeTask> {
+    if is_legacy_todo_file(path) {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| parse_legacy_todos(&content))
+            .unwrap_or_default()
+    } else {
+        scan_tasks_directory(path)
+    }
+}
+
+/// List the task files of a tasklist and those of them that fail to parse.
+///
+/// A legacy TodoWrite file is a single task file. Returns (all, unreadable).
+pub fn task_files(path: &Path) -> (Vec<PathBuf>, Vec<PathBuf>) {
+    if is_legacy_todo_file(path) {
+        let readable = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| parse_legacy_todos(&content))
+            .is_some();
+        let unreadable = if readable {
+            vec![]
+        } else {
+            vec![path.to_path_buf()]
+        };
+        return (vec![path.to_path_buf()], unreadable);
+    }
+
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return (vec![], vec![]);
+    };
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|p| p.extension().is_some_and(|e| e == "json"))
+        .collect();
+    files.sort();
+
+    let unreadable = files
+        .iter()
+        .filter(|file| {
+            std::fs::read_to_string(file)
+                .ok()
+                .and_then(|content| serde_json::from_str::<ClaudeTask>(&content).ok())
+                .is_none()
+        })
+        .cloned()
+        .collect();
+    (files, unreadable)
+}
+
 /// Scan a tasklist directory and return all valid ClaudeTask entries.
 ///
 /// Reads all .json files in the directory, parses each as ClaudeTask,
@@ -135,6 +499,58 @@ mod tests {
     use std::io::Write;
     use tempfile::TempDir;
 
+    fn make_task(id: &str, status: &str, active_form: &str) -> ClaudeTask {
+        ClaudeTask {
+            id: id.to_string(),
+            subject: format!("Task {}", id),
+            description: String::new(),
+            active_form: active_form.to_string(),
+            status: status.to_string(),
+            blocks: vec![],
+            blocked_by: vec![],
+            owner: None,
+        }
+    }
+
+    #[test]
+    fn test_status_counts() {
+        let tasks = vec![
+            make_task("1", "completed", ""),
+            make_task("2", "in_progress", ""),
+            make_task("3", "pending", ""),
+            make_task("4", "pending", ""),
+        ];
+        let counts = StatusCounts::from_tasks(&tasks);
+        assert_eq!(
+            counts,
+            StatusCounts {
+                completed: 1,
+                in_progress: 1,
+                pending: 2,
+            }
+        );
+        assert!(!counts.is_complete());
+
+        let done = StatusCounts::from_tasks(&[make_task("1", "completed", "")]);
+        assert!(done.is_complete());
+        assert!(!StatusCounts::default().is_complete());
+    }
+
+    #[test]
+    fn test_active_task() {
+        let tasks = vec![
+            make_task("1", "completed", "Done already"),
+            make_task("2", "in_progress", "Writing parser"),
+        ];
+        assert_eq!(active_task(&tasks).as_deref(), Some("Writing parser"));
+
+        // Falls back to the subject without an active form
+        let tasks = vec![make_task("3", "in_progress", "")];
+        assert_eq!(active_task(&tasks).as_deref(), Some("Task 3"));
+
+        assert_eq!(active_task(&[make_task("4", "pending", "")]), None);
+    }
+
     fn create_test_task(dir: &Path, id: &str, subject: &str) {
         let task_json = format!(
             r#"{{
@@ -201,6 +617,18 @@ mod tests {
         assert_eq!(tasks[0].id, "1");
     }
 
+    #[test]
+    fn test_task_files_lists_unreadable() {
+        let temp_dir = TempDir::new().unwrap();
+        create_test_task(temp_dir.path(), "1", "First task");
+        fs::write(temp_dir.path().join("2.json"), "{ not json").unwrap();
+        fs::write(temp_dir.path().join("notes.txt"), "ignored").unwrap();
+
+        let (files, unreadable) = task_files(temp_dir.path());
+        assert_eq!(files.len(), 2);
+        assert_eq!(unreadable, vec![temp_dir.path().join("2.json")]);
+    }
+
     #[test]
     fn test_scan_tasks_directory_skips_non_json() {
         let temp_dir = TempDir::new().unwrap();
@@ -215,6 +643,195 @@ mod tests {
         assert_eq!(tasks.len(), 1);
     }
 
+    fn create_transcript(projects_dir: &Path, session_id: &str, lines: &[&str]) {
+        let dir = projects_dir.join("-home-user-my-repo");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(format!("{}.jsonl", session_id)), lines.join("\n")).unwrap();
+    }
+
+    #[test]
+    fn test_find_project_in() {
+        let temp_dir = TempDir::new().unwrap();
+        create_transcript(
+            temp_dir.path(),
+            "abc-123",
+            &[
+                r#"{"type":"summary","summary":"Work"}"#,
+                r#"{"type":"user","cwd":"/home/user/my-repo","gitBranch":"feature-x"}"#,
+            ],
+        );
+
+        let project = find_project_in(temp_dir.path(), "abc-123").unwrap();
+        assert_eq!(project.cwd, PathBuf::from("/home/user/my-repo"));
+        assert_eq!(project.git_branch.as_deref(), Some("feature-x"));
+        assert_eq!(project.name(), "my-repo [feature-x]");
+
+        assert!(find_project_in(temp_dir.path(), "other-session").is_none());
+    }
+
+    #[test]
+    fn test_find_project_ignores_detached_head() {
+        let temp_dir = TempDir::new().unwrap();
+        create_transcript(
+            temp_dir.path(),
+            "abc-123",
+            &[r#"{"cwd":"/home/user/my-repo","gitBranch":"HEAD"}"#],
+        );
+
+        let project = find_project_in(temp_dir.path(), "abc-123").unwrap();
+        assert_eq!(project.git_branch, None);
+    }
+
+    #[test]
+    fn test_find_project_without_cwd() {
+        let temp_dir = TempDir::new().unwrap();
+        create_transcript(temp_dir.path(), "abc-123", &["not json", r#"{"type":"x"}"#]);
+        assert!(find_project_in(temp_dir.path(), "abc-123").is_none());
+    }
+
+    #[test]
+    fn test_project_name_uses_git_root() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = temp_dir.path().join("my-repo");
+        fs::create_dir_all(repo.join(".git")).unwrap();
+        fs::create_dir_all(repo.join("crates/core")).unwrap();
+
+        let project = ProjectInfo {
+            cwd: repo.join("crates/core"),
+            git_branch: None,
+        };
+        assert_eq!(project.name(), "my-repo");
+    }
+
+    #[test]
+    fn test_discover_tasklists_in() {
+        let tasks_dir = TempDir::new().unwrap();
+        let full = tasks_dir.path().join("full");
+        fs::create_dir(&full).unwrap();
+        create_test_task(&full, "1", "First task");
+        fs::create_dir(tasks_dir.path().join("empty")).unwrap();
+
+        let roots = vec![tasks_dir.path().to_path_buf()];
+        let mut cache = DiscoveryCache::default();
+        let tasklists = discover_tasklists_in(&roots, &mut cache);
+
+        // Empty tasklists are excluded
+        assert_eq!(tasklists.len(), 1);
+        assert_eq!(tasklists[0].id, "full");
+        assert_eq!(tasklists[0].root, tasks_dir.path());
+        assert_eq!(tasklists[0].summary().sample_tasks, vec!["First task"]);
+
+        // Second discovery is served from the cache
+        assert!(!cache.refresh("full", &full));
+        assert_eq!(discover_tasklists_in(&roots, &mut cache).len(), 1);
+    }
+
+    #[test]
+    fn test_last_activity_is_newest_file() {
+        let temp_dir = TempDir::new().unwrap();
+        create_test_task(temp_dir.path(), "1", "First task");
+        create_test_task(temp_dir.path(), "2", "Second task");
+
+        let day = std::time::Duration::from_secs(24 * 60 * 60);
+        let two_days_ago = SystemTime::now() - 2 * day;
+        let set_mtime = |path: &Path, time| {
+            fs::File::open(path).unwrap().set_modified(time).unwrap();
+        };
+        set_mtime(&temp_dir.path().join("1.json"), two_days_ago);
+        set_mtime(&temp_dir.path().join("2.json"), two_days_ago + day);
+        set_mtime(temp_dir.path(), two_days_ago);
+
+        assert_eq!(last_activity(temp_dir.path()), Some(two_days_ago + day));
+        assert_eq!(last_activity(&temp_dir.path().join("missing")), None);
+    }
+
+    #[test]
+    fn test_discover_legacy_todo_files() {
+        let claude_dir = TempDir::new().unwrap();
+        let tasks_root = claude_dir.path().join("tasks");
+        let todos_dir = claude_dir.path().join("todos");
+        fs::create_dir_all(&tasks_root).unwrap();
+        fs::create_dir_all(&todos_dir).unwrap();
+        fs::write(
+            todos_dir.join("abc-123-agent-abc-123.json"),
+            r#"[{"content":"Legacy todo","status":"in_progress","activeForm":"Working"}]"#,
+        )
+        .unwrap();
+        // Claude writes empty lists for every session; they are skipped
+        fs::write(todos_dir.join("def-456-agent-def-456.json"), "[]").unwrap();
+        create_transcript(
+            &claude_dir.path().join("projects"),
+            "abc-123",
+            &[r#"{"cwd":"/home/user/my-repo"}"#],
+        );
+
+        let tasklists = discover_tasklists_in(&[tasks_root], &mut DiscoveryCache::default());
+
+        assert_eq!(tasklists.len(), 1);
+        let legacy = &tasklists[0];
+        assert_eq!(legacy.id, "abc-123-agent-abc-123");
+        assert_eq!(legacy.root, todos_dir);
+        assert_eq!(legacy.summary().sample_tasks, vec!["Legacy todo"]);
+        assert_eq!(legacy.summary().active_task.as_deref(), Some("Working"));
+        assert_eq!(
+            legacy.project.as_ref().map(|p| p.name()).as_deref(),
+            Some("my-repo")
+        );
+        assert_eq!(scan_tasklist(&legacy.path).len(), 1);
+    }
+
+    #[test]
+    fn test_tasklist_and_session_ids() {
+        let folder = Path::new("/home/user/.claude/tasks/abc-123");
+        let legacy = Path::new("/home/user/.claude/todos/abc-123-agent-def-456.json");
+
+        assert_eq!(tasklist_id_from_path(folder), Some("abc-123"));
+        assert_eq!(tasklist_id_from_path(legacy), Some("abc-123-agent-def-456"));
+        assert!(!is_legacy_todo_file(folder));
+        assert!(is_legacy_todo_file(legacy));
+
+        assert_eq!(session_id("abc-123"), "abc-123");
+        assert_eq!(session_id("abc-123-agent-def-456"), "abc-123");
+    }
+
+    #[test]
+    fn test_discover_tasklists_in_multiple_roots() {
+        let first = TempDir::new().unwrap();
+        let second = TempDir::new().unwrap();
+        for (root, id) in [(&first, "a"), (&second, "b"), (&second, "a")] {
+            let dir = root.path().join(id);
+            fs::create_dir_all(&dir).unwrap();
+            create_test_task(&dir, "1", id);
+        }
+
+        let roots = vec![
+            first.path().to_path_buf(),
+            PathBuf::from("/nonexistent/root"),
+            second.path().to_path_buf(),
+        ];
+        let mut tasklists = discover_tasklists_in(&roots, &mut DiscoveryCache::default());
+        tasklists.sort_by(|x, y| x.id.cmp(&y.id));
+
+        // Duplicate IDs resolve to the first root; missing roots are skipped
+        assert_eq!(tasklists.len(), 2);
+        assert_eq!(tasklists[0].root, first.path());
+        assert_eq!(tasklists[1].root, second.path());
+        assert_eq!(tasklists[1].path, second.path().join("b"));
+    }
+
+    #[test]
+    fn test_find_project_next_to_root() {
+        let claude_dir = TempDir::new().unwrap();
+        create_transcript(
+            &claude_dir.path().join("projects"),
+            "abc-123",
+            &[r#"{"cwd":"/home/user/my-repo"}"#],
+        );
+
+        let project = find_project(&claude_dir.path().join("tasks"), "abc-123").unwrap();
+        assert_eq!(project.cwd, PathBuf::from("/home/user/my-repo"));
+    }
+
     #[test]
     fn test_scan_nonexistent_directory() {
         let tasks = scan_tasks_directory(Path::new("/nonexistent/path/that/does/not/exist"));
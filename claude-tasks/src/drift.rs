@@ -0,0 +1,190 @@
+//! Drift detection and repair between a tasklist folder and totui.
+//!
+//! The local sync path trusts `known_tasks` and the predictable todo IDs, so
+//! a missed file event or a todo deleted in totui leaves the two out of sync
+//! for good. The consistency check compares the tasks on disk against the
+//! synced todos found via `query_todos_by_metadata`, and the reconciliation
+//! engine ([`crate::reconcile`]) builds the commands that bring totui back
+//! in line with the folder.
+//!
+//! The check needs host access, so it runs from `execute_with_host`: at most
+//! every [`DRIFT_CHECK_INTERVAL`], or right away with the `repair` command.
+
+use crate::claude_task::ClaudeTask;
+use crate::metadata::TodoMetadata;
+use crate::reconcile::{reconcile, Layout, Plan, Scope, Snapshot};
+use std::time::Duration;
+use totui_plugin_interface::FfiTodoItem;
+
+/// Minimum time between two periodic consistency checks.
+pub const DRIFT_CHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Compare the tasks on disk against the synced todos of a tasklist.
+///
+/// `todos` are `(todo, metadata)` pairs of this tasklist (see
+/// [`crate::sync::synced_todos`]). The returned plan repairs the drift: a
+/// missing header is recreated first, missing tasks are created under it,
+/// outdated todos updated and orphaned todos deleted. With
+/// [`Layout::ByAgent`], missing agent sub-headers are recreated as well and
+/// tasks are repaired under their owner's sub-header.
+pub fn find_drift(
+    tasks: &[ClaudeTask],
+    todos: &[(FfiTodoItem, TodoMetadata)],
+    tasklist_id: &str,
+    layout: Layout,
+) -> Plan {
+    let snapshot = Snapshot::from_todos(todos, tasklist_id);
+    reconcile(tasklist_id, tasks, &snapshot, &Scope::All, layout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::{agent_header_id, header_id, task_todo_id};
+    use abi_stable::std_types::{ROption, RString};
+    use totui_plugin_interface::{FfiCommand, FfiTodoState};
+
+    fn task(id: &str, subject: &str, status: &str) -> ClaudeTask {
+        ClaudeTask {
+            id: id.to_string(),
+            subject: subject.to_string(),
+            description: String::new(),
+            active_form: String::new(),
+            status: status.to_string(),
+            blocks: vec![],
+            blocked_by: vec![],
+            owner: None,
+        }
+    }
+
+    fn todo(id: &str, content: &str, state: FfiTodoState) -> FfiTodoItem {
+        FfiTodoItem {
+            id: RString::from(id),
+            content: RString::from(content),
+            state,
+            priority: ROption::RNone,
+            due_date: ROption::RNone,
+            description: ROption::RNone,
+            parent_id: ROption::RNone,
+            indent_level: 1,
+            created_at: 0,
+            modified_at: 0,
+            completed_at: ROption::RNone,
+            position: 0,
+        }
+    }
+
+    fn synced(
+        tasklist_id: &str,
+        task_id: &str,
+        content: &str,
+        state: FfiTodoState,
+    ) -> (FfiTodoItem, TodoMetadata) {
+        (
+            todo(&task_todo_id(tasklist_id, task_id), content, state),
+            TodoMetadata::task(tasklist_id, task_id, &[]),
+        )
+    }
+
+    fn header(tasklist_id: &str) -> (FfiTodoItem, TodoMetadata) {
+        (
+            todo(
+                &header_id(tasklist_id),
+                "CLAUDE TASKLIST: x",
+                FfiTodoState::Empty,
+            ),
+            TodoMetadata::header(tasklist_id),
+        )
+    }
+
+    #[test]
+    fn test_find_drift_in_sync() {
+        let mut blocked = task("1", "Write tests", "completed");
+        blocked.blocked_by = vec!["2".to_string()];
+        let tasks = vec![blocked, task("2", "Fix bug", "completed")];
+        let todos = vec![
+            header("abc"),
+            synced(
+                "abc",
+                "1",
+                "\u{1F512} Write tests (blocked by: Fix bug)",
+                FfiTodoState::Checked,
+            ),
+            synced("abc", "2", "Fix bug", FfiTodoState::Checked),
+        ];
+
+        let drift = find_drift(&tasks, &todos, "abc", Layout::Flat);
+        assert!(drift.is_empty());
+        assert_eq!(drift.summary(), "in sync");
+        assert!(drift.commands("abc", None).is_empty());
+    }
+
+    #[test]
+    fn test_find_drift_and_repair() {
+        let tasks = vec![
+            task("1", "Write tests", "in_progress"),
+            task("2", "Fix bug", "pending"),
+        ];
+        let todos = vec![
+            synced("abc", "1", "Write tests", FfiTodoState::Empty),
+            synced("abc", "3", "Removed task", FfiTodoState::Empty),
+        ];
+
+        let drift = find_drift(&tasks, &todos, "abc", Layout::Flat);
+        assert!(drift.header_missing);
+        assert_eq!(drift.missing.len(), 1);
+        assert_eq!(drift.missing[0].0.id, "2");
+        assert_eq!(drift.outdated[0].1, task_todo_id("abc", "1"));
+        assert_eq!(
+            drift.orphaned,
+            vec![("3".to_string(), task_todo_id("abc", "3"))]
+        );
+        assert_eq!(
+            drift.summary(),
+            "header missing, 1 missing, 1 out of date, 1 orphaned"
+        );
+
+        let commands = drift.commands("abc", Some("My Project"));
+        // header + metadata, create + metadata, update, delete
+        assert_eq!(commands.len(), 6);
+        assert!(matches!(
+            &commands[0],
+            FfiCommand::CreateTodo { content, .. } if content.as_str() == "CLAUDE TASKLIST: My Project"
+        ));
+        assert!(matches!(
+            &commands[4],
+            FfiCommand::UpdateTodo { id, .. } if id.as_str() == task_todo_id("abc", "1")
+        ));
+        assert!(matches!(
+            &commands[5],
+            FfiCommand::DeleteTodo { id } if id.as_str() == task_todo_id("abc", "3")
+        ));
+    }
+
+    #[test]
+    fn test_find_drift_repairs_under_agent() {
+        let mut owned = task("1", "Research", "pending");
+        owned.owner = Some("researcher".to_string());
+        let tasks = vec![owned];
+        // The agent sub-header is there, its task was deleted in totui
+        let agent = (
+            todo(
+                &agent_header_id("abc", "researcher"),
+                "AGENT researcher: idle",
+                FfiTodoState::Empty,
+            ),
+            TodoMetadata::agent("abc", "researcher"),
+        );
+        let todos = vec![header("abc"), agent];
+
+        let drift = find_drift(&tasks, &todos, "abc", Layout::ByAgent);
+        assert_eq!(drift.summary(), "1 missing");
+
+        let commands = drift.commands("abc", None);
+        assert!(matches!(
+            &commands[0],
+            FfiCommand::CreateTodo { parent_id: ROption::RSome(parent), indent_level: 2, .. }
+                if parent.as_str() == agent_header_id("abc", "researcher")
+        ));
+    }
+}
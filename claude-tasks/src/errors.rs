@@ -16,6 +16,8 @@ pub enum PluginError {
     DirectoryNotFound,
     /// Invalid configuration file
     ConfigParseError(String),
+    /// Configuration file could not be written
+    ConfigWriteError(String),
 }
 
 impl fmt::Display for PluginError {
@@ -25,6 +27,7 @@ impl fmt::Display for PluginError {
             PluginError::WatcherFailed(msg) => write!(f, "Watch failed: {}", msg),
             PluginError::DirectoryNotFound => write!(f, "Tasks directory not found"),
             PluginError::ConfigParseError(path) => write!(f, "Invalid config: {}", path),
+            PluginError::ConfigWriteError(msg) => write!(f, "Could not write config: {}", msg),
         }
     }
 }
@@ -0,0 +1,333 @@
+//! In-memory totui host for end-to-end tests.
+//!
+//! [`FakeHost`] implements [`TodoHost`] over a todo store and applies the
+//! plugin's commands the way totui does: a `temp_id` becomes the todo ID,
+//! parents are looked up by ID and metadata is stored per todo. Commands
+//! totui would reject (unknown IDs, duplicate temp_ids, missing parents)
+//! are recorded as errors, so a test can check that a scenario ran cleanly
+//! and then assert the resulting todo tree.
+//!
+//! [`notifier`] gives an [`UpdateNotifier`] that counts wake-up calls.
+
+use crate::host::TodoHost;
+use abi_stable::std_types::{ROption, RString};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use totui_plugin_interface::{FfiCommand, FfiTodoItem, FfiTodoState, UpdateNotifier};
+
+/// Number of notifier calls since the process started
+static NOTIFY_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+extern "C" fn record_notify() {
+    NOTIFY_CALLS.fetch_add(1, Ordering::SeqCst);
+}
+
+/// A notifier that counts its calls (see [`notify_calls`]).
+pub fn notifier() -> UpdateNotifier {
+    UpdateNotifier {
+        func: record_notify,
+    }
+}
+
+/// Number of notifier calls so far, across all plugins in the process.
+pub fn notify_calls() -> usize {
+    NOTIFY_CALLS.load(Ordering::SeqCst)
+}
+
+/// An in-memory todo store standing in for totui.
+#[derive(Debug, Default)]
+pub struct FakeHost {
+    /// Todos in creation order
+    todos: Vec<FfiTodoItem>,
+    /// Metadata JSON by todo ID
+    metadata: HashMap<String, String>,
+    /// Commands that totui would reject
+    errors: Vec<String>,
+    /// Counter for todos created without a temp_id
+    next_id: usize,
+}
+
+impl FakeHost {
+    /// An empty host.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply commands in order, recording the ones that fail.
+    pub fn apply(&mut self, commands: &[FfiCommand]) {
+        for command in commands {
+            if let Err(error) = self.apply_one(command) {
+                self.errors.push(error);
+            }
+        }
+    }
+
+    fn apply_one(&mut self, command: &FfiCommand) -> Result<(), String> {
+        match command {
+            FfiCommand::CreateTodo {
+                content,
+                parent_id,
+                temp_id,
+                state,
+                priority,
+                indent_level,
+            } => {
+                let id = match temp_id {
+                    ROption::RSome(id) => id.to_string(),
+                    ROption::RNone => {
+                        self.next_id += 1;
+                        format!("todo-{}", self.next_id)
+                    }
+                };
+                if self.todo(&id).is_some() {
+                    return Err(format!("create: duplicate todo {}", id));
+                }
+                if let ROption::RSome(parent) = parent_id {
+                    if self.todo(parent).is_none() {
+                        return Err(format!("create {}: unknown parent {}", id, parent));
+                    }
+                }
+
+                self.todos.push(FfiTodoItem {
+                    id: RString::from(id),
+                    content: content.clone(),
+                    state: *state,
+                    priority: *priority,
+                    due_date: ROption::RNone,
+                    description: ROption::RNone,
+                    parent_id: parent_id.clone(),
+                    indent_level: *indent_level,
+                    created_at: 0,
+                    modified_at: 0,
+                    completed_at: ROption::RNone,
+                    position: self.todos.len() as u32,
+                });
+            }
+            FfiCommand::UpdateTodo {
+                id,
+                content,
+                state,
+                priority,
+                due_date,
+                description,
+            } => {
+                let todo = self.todo_mut(id)?;
+                if let ROption::RSome(content) = content {
+                    todo.content = content.clone();
+                }
+                if let ROption::RSome(state) = state {
+                    todo.state = *state;
+                }
+                if let ROption::RSome(priority) = priority {
+                    todo.priority = ROption::RSome(*priority);
+                }
+                if let ROption::RSome(due_date) = due_date {
+                    todo.due_date = ROption::RSome(due_date.clone());
+                }
+                if let ROption::RSome(description) = description {
+                    todo.description = ROption::RSome(description.clone());
+                }
+            }
+            FfiCommand::DeleteTodo { id } => {
+                self.todo(id)
+                    .ok_or_else(|| format!("delete: unknown todo {}", id))?;
+                self.todos.retain(|todo| todo.id != *id);
+                self.metadata.remove(id.as_str());
+            }
+            FfiCommand::MoveTodo { id, position } => {
+                self.todo_mut(id)?.position = *position;
+            }
+            FfiCommand::SetTodoMetadata {
+                todo_id,
+                data,
+                merge,
+            } => {
+                self.todo(todo_id)
+                    .ok_or_else(|| format!("metadata: unknown todo {}", todo_id))?;
+                let mut value: Value = serde_json::from_str(data)
+                    .map_err(|e| format!("metadata {}: {}", todo_id, e))?;
+                if *merge {
+                    if let Some(Value::Object(mut old)) = self
+                        .metadata
+                        .get(todo_id.as_str())
+                        .and_then(|json| serde_json::from_str(json).ok())
+                    {
+                        if let Value::Object(new) = value {
+                            old.extend(new);
+                        }
+                        value = Value::Object(old);
+                    }
+                }
+                self.metadata.insert(todo_id.to_string(), value.to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// Look up a todo by ID.
+    pub fn todo(&self, id: &str) -> Option<&FfiTodoItem> {
+        self.todos.iter().find(|todo| todo.id.as_str() == id)
+    }
+
+    fn todo_mut(&mut self, id: &str) -> Result<&mut FfiTodoItem, String> {
+        self.todos
+            .iter_mut()
+            .find(|todo| todo.id.as_str() == id)
+            .ok_or_else(|| format!("unknown todo {}", id))
+    }
+
+    /// All todos, in creation order.
+    pub fn todos(&self) -> &[FfiTodoItem] {
+        &self.todos
+    }
+
+    /// Commands that failed so far.
+    pub fn errors(&self) -> &[String] {
+        &self.errors
+    }
+
+    /// Render the todos as an indented tree, one line per todo.
+    ///
+    /// Lines look like `"  [x] Write tests"`: two spaces per nesting level,
+    /// then the state. Siblings keep their creation order; todos whose
+    /// parent is gone are shown at the top level.
+    pub fn tree(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        let roots = self.todos.iter().filter(|todo| match &todo.parent_id {
+            ROption::RSome(parent) => self.todo(parent).is_none(),
+            ROption::RNone => true,
+        });
+        for root in roots {
+            self.render(root, 0, &mut lines);
+        }
+        lines
+    }
+
+    fn render(&self, todo: &FfiTodoItem, depth: usize, lines: &mut Vec<String>) {
+        let marker = match todo.state {
+            FfiTodoState::Empty => "[ ]",
+            FfiTodoState::Checked => "[x]",
+            FfiTodoState::InProgress => "[*]",
+            FfiTodoState::Question => "[?]",
+            FfiTodoState::Exclamation => "[!]",
+            FfiTodoState::Cancelled => "[-]",
+        };
+        lines.push(format!("{}{} {}", "  ".repeat(depth), marker, todo.content));
+
+        let children = self
+            .todos
+            .iter()
+            .filter(|child| matches!(&child.parent_id, ROption::RSome(p) if *p == todo.id));
+        for child in children {
+            self.render(child, depth + 1, lines);
+        }
+    }
+}
+
+impl TodoHost for FakeHost {
+    fn query_todos_by_metadata(&self, key: &str, value: &str) -> Vec<FfiTodoItem> {
+        let Ok(wanted) = serde_json::from_str::<Value>(value) else {
+            return Vec::new();
+        };
+        self.todos
+            .iter()
+            .filter(|todo| {
+                self.metadata
+                    .get(todo.id.as_str())
+                    .and_then(|json| serde_json::from_str::<Value>(json).ok())
+                    .is_some_and(|metadata| metadata.get(key) == Some(&wanted))
+            })
+            .cloned()
+            .collect()
+    }
+
+    fn get_todo_metadata(&self, todo_id: &str) -> String {
+        self.metadata.get(todo_id).cloned().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create(content: &str, temp_id: &str, parent: Option<&str>) -> FfiCommand {
+        FfiCommand::CreateTodo {
+            content: RString::from(content),
+            parent_id: parent.map(RString::from).into(),
+            temp_id: ROption::RSome(RString::from(temp_id)),
+            state: FfiTodoState::Empty,
+            priority: ROption::RNone,
+            indent_level: u32::from(parent.is_some()),
+        }
+    }
+
+    fn set_metadata(todo_id: &str, data: &str, merge: bool) -> FfiCommand {
+        FfiCommand::SetTodoMetadata {
+            todo_id: RString::from(todo_id),
+            data: RString::from(data),
+            merge,
+        }
+    }
+
+    #[test]
+    fn test_apply_builds_tree() {
+        let mut host = FakeHost::new();
+        host.apply(&[
+            create("Header", "h", None),
+            create("First", "a", Some("h")),
+            create("Second", "b", Some("h")),
+            FfiCommand::UpdateTodo {
+                id: RString::from("a"),
+                content: ROption::RNone,
+                state: ROption::RSome(FfiTodoState::Checked),
+                priority: ROption::RNone,
+                due_date: ROption::RNone,
+                description: ROption::RNone,
+            },
+            FfiCommand::DeleteTodo {
+                id: RString::from("b"),
+            },
+        ]);
+
+        assert!(host.errors().is_empty());
+        assert_eq!(host.tree(), vec!["[ ] Header", "  [x] First"]);
+    }
+
+    #[test]
+    fn test_apply_records_rejected_commands() {
+        let mut host = FakeHost::new();
+        host.apply(&[
+            create("Header", "h", None),
+            create("Again", "h", None),
+            create("Orphan", "a", Some("missing")),
+            FfiCommand::DeleteTodo {
+                id: RString::from("missing"),
+            },
+        ]);
+
+        assert_eq!(host.errors().len(), 3);
+        assert_eq!(host.todos().len(), 1);
+    }
+
+    #[test]
+    fn test_metadata_query_and_merge() {
+        let mut host = FakeHost::new();
+        host.apply(&[
+            create("A", "a", None),
+            create("B", "b", None),
+            set_metadata("a", r#"{"source":"claude-tasks","task_id":"1"}"#, false),
+            set_metadata("b", r#"{"source":"other"}"#, false),
+            set_metadata("a", r#"{"hash":"x"}"#, true),
+        ]);
+
+        let found = host.query_todos_by_metadata("source", "\"claude-tasks\"");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id.as_str(), "a");
+
+        let metadata: Value = serde_json::from_str(&host.get_todo_metadata("a")).unwrap();
+        assert_eq!(metadata["task_id"], "1");
+        assert_eq!(metadata["hash"], "x");
+        assert_eq!(host.get_todo_metadata("missing"), "");
+    }
+}
@@ -5,7 +5,9 @@
 //! - No tasklists exist (setup instructions)
 //! - Tasklist is empty (waiting for tasks)
 //! - Errors occur (recovery guidance)
+//! - The selected tasklist is archived
 
+use crate::metadata::TodoMetadata;
 use abi_stable::std_types::{ROption, RString};
 use totui_plugin_interface::{FfiCommand, FfiTodoState};
 
@@ -31,6 +33,10 @@ pub const GUIDANCE_ERROR_HEADER_ID: &str = "claude-error-header";
 pub const GUIDANCE_ERROR_DETAIL_ID: &str = "claude-error-detail";
 /// ID for error action todo
 pub const GUIDANCE_ERROR_ACTION_ID: &str = "claude-error-action";
+/// ID for the archived tasklist header todo
+pub const GUIDANCE_ARCHIVED_HEADER_ID: &str = "claude-guidance-archived-header";
+/// ID for the "unarchive to sync again" guidance todo
+pub const GUIDANCE_UNARCHIVE_ID: &str = "claude-guidance-unarchive";
 
 /// All guidance IDs for clearing
 pub const GUIDANCE_IDS: &[&str] = &[
@@ -43,6 +49,8 @@ pub const GUIDANCE_IDS: &[&str] = &[
     GUIDANCE_ERROR_HEADER_ID,
     GUIDANCE_ERROR_DETAIL_ID,
     GUIDANCE_ERROR_ACTION_ID,
+    GUIDANCE_ARCHIVED_HEADER_ID,
+    GUIDANCE_UNARCHIVE_ID,
 ];
 
 // ============================================================================
@@ -52,13 +60,18 @@ pub const GUIDANCE_IDS: &[&str] = &[
 /// Header text for setup required state
 pub const MSG_HEADER_SETUP_REQUIRED: &str = "CLAUDE TASKS - Setup Required";
 /// Message when no tasklists are found
-pub const MSG_NO_TASKLISTS: &str = "No Claude tasklists found in ~/.claude/tasks/";
+pub const MSG_NO_TASKLISTS: &str =
+    "No Claude tasklists found in the task roots (default ~/.claude/tasks/)";
 /// Message instructing to start Claude Code
 pub const MSG_START_CLAUDE: &str = "Start a Claude Code session to create a tasklist";
 /// Message when tasklist has no tasks yet
 pub const MSG_NO_TASKS_YET: &str = "Claude hasn't created any tasks yet";
 /// Message that tasks will appear
 pub const MSG_TASKS_WILL_APPEAR: &str = "Tasks will appear here as Claude works";
+/// Header text for invalid config files
+pub const MSG_HEADER_INVALID_CONFIG: &str = "CLAUDE TASKS - Invalid Config";
+/// Recovery action for invalid config files
+pub const MSG_FIX_CONFIG: &str = "Fix the file; the last valid settings stay in use until then";
 
 // ============================================================================
 // Guidance Creation Functions
@@ -68,7 +81,7 @@ pub const MSG_TASKS_WILL_APPEAR: &str = "Tasks will appear here as Claude works"
 ///
 /// Displays:
 /// - Header: "CLAUDE TASKS - Setup Required" (Question state)
-/// - Child: "No Claude tasklists found in ~/.claude/tasks/"
+/// - Child: "No Claude tasklists found in the task roots (default ~/.claude/tasks/)"
 /// - Child: "Start a Claude Code session to create a tasklist"
 ///
 /// Returns 4 commands: 3 CreateTodo + 1 SetTodoMetadata
@@ -107,11 +120,7 @@ pub fn create_no_tasklist_guidance() -> Vec<FfiCommand> {
     });
 
     // Set metadata on header
-    commands.push(FfiCommand::SetTodoMetadata {
-        todo_id: RString::from(GUIDANCE_HEADER_ID),
-        data: RString::from(r#"{"source":"claude-tasks","type":"guidance"}"#),
-        merge: false,
-    });
+    commands.push(TodoMetadata::guidance().set_command(GUIDANCE_HEADER_ID));
 
     commands
 }
@@ -161,11 +170,7 @@ pub fn create_empty_tasklist_guidance(display_name: &str) -> Vec<FfiCommand> {
     });
 
     // Set metadata on header
-    commands.push(FfiCommand::SetTodoMetadata {
-        todo_id: RString::from(GUIDANCE_WAITING_HEADER_ID),
-        data: RString::from(r#"{"source":"claude-tasks","type":"guidance"}"#),
-        merge: false,
-    });
+    commands.push(TodoMetadata::guidance().set_command(GUIDANCE_WAITING_HEADER_ID));
 
     commands
 }
@@ -214,15 +219,56 @@ pub fn create_error_guidance(title: &str, explanation: &str, action: &str) -> Ve
     });
 
     // Set metadata on header with error flag
-    commands.push(FfiCommand::SetTodoMetadata {
-        todo_id: RString::from(GUIDANCE_ERROR_HEADER_ID),
-        data: RString::from(r#"{"source":"claude-tasks","type":"guidance","error":true}"#),
-        merge: false,
-    });
+    let metadata = TodoMetadata {
+        error: true,
+        ..TodoMetadata::guidance()
+    };
+    commands.push(metadata.set_command(GUIDANCE_ERROR_HEADER_ID));
 
     commands
 }
 
+/// Create error guidance for config files that failed to parse.
+///
+/// `errors` are the parse errors (file, line and message); they are joined
+/// into the explanation todo.
+pub fn create_config_error_guidance(errors: &[String]) -> Vec<FfiCommand> {
+    create_error_guidance(
+        MSG_HEADER_INVALID_CONFIG,
+        &errors.join("; "),
+        MSG_FIX_CONFIG,
+    )
+}
+
+/// Create guidance todos for a selected tasklist that is archived.
+///
+/// Displays:
+/// - Header: "CLAUDE TASKLIST: {display_name} - Archived"
+/// - Child: "Run `unarchive {tasklist_id}` to sync it again"
+///
+/// Returns 3 commands: 2 CreateTodo + 1 SetTodoMetadata
+pub fn create_archived_guidance(display_name: &str, tasklist_id: &str) -> Vec<FfiCommand> {
+    vec![
+        FfiCommand::CreateTodo {
+            content: RString::from(format!("CLAUDE TASKLIST: {} - Archived", display_name)),
+            parent_id: ROption::RNone,
+            temp_id: ROption::RSome(RString::from(GUIDANCE_ARCHIVED_HEADER_ID)),
+            state: FfiTodoState::Empty,
+            priority: ROption::RNone,
+            indent_level: 0,
+        },
+        FfiCommand::CreateTodo {
+            content: RString::from(format!("Run `unarchive {}` to sync it again", tasklist_id)),
+            parent_id: ROption::RSome(RString::from(GUIDANCE_ARCHIVED_HEADER_ID)),
+            temp_id: ROption::RSome(RString::from(GUIDANCE_UNARCHIVE_ID)),
+            state: FfiTodoState::Empty,
+            priority: ROption::RNone,
+            indent_level: 1,
+        },
+        TodoMetadata::guidance().set_command(GUIDANCE_ARCHIVED_HEADER_ID),
+    ]
+}
+
 /// Clear all guidance todos.
 ///
 /// Returns DeleteTodo commands for all guidance IDs.
@@ -246,7 +292,7 @@ mod tests {
 
     #[test]
     fn test_guidance_ids_count() {
-        assert_eq!(GUIDANCE_IDS.len(), 9);
+        assert_eq!(GUIDANCE_IDS.len(), 11);
     }
 
     #[test]
@@ -541,6 +587,25 @@ mod tests {
         assert!(!MSG_START_CLAUDE.is_empty());
         assert!(!MSG_NO_TASKS_YET.is_empty());
         assert!(!MSG_TASKS_WILL_APPEAR.is_empty());
+        assert!(!MSG_HEADER_INVALID_CONFIG.is_empty());
+        assert!(!MSG_FIX_CONFIG.is_empty());
+    }
+
+    #[test]
+    fn test_create_config_error_guidance() {
+        let errors = vec![
+            "Invalid config: a.toml line 1: x".to_string(),
+            "b".to_string(),
+        ];
+        let commands = create_config_error_guidance(&errors);
+        assert_eq!(commands.len(), 4);
+
+        match &commands[1] {
+            FfiCommand::CreateTodo { content, .. } => {
+                assert_eq!(content.as_str(), "Invalid config: a.toml line 1: x; b");
+            }
+            _ => panic!("Expected CreateTodo command"),
+        }
     }
 
     #[test]
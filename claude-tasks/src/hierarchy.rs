@@ -135,6 +135,7 @@ mod tests {
             status: "pending".to_string(),
             blocks: vec![],
             blocked_by: blocked_by.into_iter().map(String::from).collect(),
+            owner: None,
         }
     }
 
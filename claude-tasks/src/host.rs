@@ -0,0 +1,29 @@
+//! Host access used by the sync code.
+//!
+//! The plugin only reads from totui through two `HostApi` calls. Sync code
+//! takes them as a [`TodoHost`] rather than `HostApi_TO`, so tests can drive
+//! the plugin against an in-memory host instead of a running totui.
+
+use abi_stable::std_types::{RBox, RString};
+use totui_plugin_interface::{FfiTodoItem, HostApi_TO};
+
+/// Read access to the todos in totui.
+pub trait TodoHost {
+    /// Find todos whose metadata has `key` set to the JSON `value`.
+    fn query_todos_by_metadata(&self, key: &str, value: &str) -> Vec<FfiTodoItem>;
+
+    /// Get the metadata JSON of a todo (empty if it has none).
+    fn get_todo_metadata(&self, todo_id: &str) -> String;
+}
+
+impl TodoHost for HostApi_TO<'_, RBox<()>> {
+    fn query_todos_by_metadata(&self, key: &str, value: &str) -> Vec<FfiTodoItem> {
+        HostApi_TO::query_todos_by_metadata(self, RString::from(key), RString::from(value))
+            .into_iter()
+            .collect()
+    }
+
+    fn get_todo_metadata(&self, todo_id: &str) -> String {
+        HostApi_TO::get_todo_metadata(self, RString::from(todo_id)).to_string()
+    }
+}
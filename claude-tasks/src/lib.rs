@@ -7,34 +7,62 @@
 
 #![allow(non_local_definitions)]
 
+pub mod agents;
+pub mod archive;
+pub mod cache;
 pub mod claude_task;
 pub mod commands;
 pub mod config;
 pub mod discovery;
+pub mod drift;
 pub mod errors;
+#[cfg(test)]
+mod fake_host;
 pub mod guidance;
 pub mod hierarchy;
+pub mod host;
 pub mod log;
+pub mod metadata;
+pub mod paths;
+pub mod prompt;
+pub mod prune;
+pub mod reconcile;
+pub mod schema;
+pub mod session;
 pub mod staleness;
 pub mod state;
+pub mod status;
 pub mod sync;
 pub mod watcher;
 
+pub use plugin_log::{plugin_debug, plugin_error, plugin_info, plugin_trace, plugin_warn};
+
 use abi_stable::{
     export_root_module,
     prefix_type::PrefixTypeTrait,
     sabi_trait::TD_Opaque,
-    std_types::{RBox, RHashMap, ROption, RResult, RString, RVec},
+    std_types::{RBox, RHashMap, RResult, RString, RVec},
 };
-use config::{format_tasklist_display, generate_tasklist_options, load_config};
+use agents::AgentLayout;
+use config::{
+    format_tasklist_display, generate_tasklist_options, load_config, load_config_report,
+    ConfigLoad, ConfigScope, PluginConfig, PruneAction, PruneOnFirstInvocation,
+};
+use discovery::TasklistInfo;
 use guidance::{clear_guidance, create_empty_tasklist_guidance, create_no_tasklist_guidance};
-use state::{new_shared_state, GuidanceState, SharedSyncState, SyncEvent};
+use host::TodoHost;
+use prompt::PromptCommand;
+use reconcile::Snapshot;
+use staleness::HeaderStatus;
+use state::{new_shared_state, GuidanceState, SharedSyncState, SyncEvent, SyncState};
+use std::collections::HashSet;
+use std::path::PathBuf;
 use std::sync::mpsc;
 use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime};
 use totui_plugin_interface::{
-    FfiCommand, FfiConfigField, FfiConfigSchema, FfiConfigType, FfiConfigValue, FfiEvent,
-    FfiEventType, FfiHookResponse, FfiTodoItem, HostApi_TO, Plugin, PluginModule, PluginModule_Ref,
-    Plugin_TO, UpdateNotifier,
+    FfiCommand, FfiConfigSchema, FfiConfigValue, FfiEvent, FfiEventType, FfiHookResponse,
+    FfiTodoItem, HostApi_TO, Plugin, PluginModule, PluginModule_Ref, Plugin_TO, UpdateNotifier,
 };
 use watcher::WatcherHandle;
 
@@ -55,6 +83,9 @@ extern "C" fn create_plugin() -> Plugin_TO<'static, RBox<()>> {
 // Plugin implementation
 // ============================================================================
 
+/// Error message for prompt commands that need a selected tasklist.
+const NO_TASKLIST_SELECTED: &str = "No tasklist selected. Use: switch <alias|uuid>";
+
 /// Shared notifier that can be passed to watcher thread.
 pub type SharedNotifier = Arc<Mutex<Option<UpdateNotifier>>>;
 
@@ -70,6 +101,10 @@ pub struct ClaudeTasksPlugin {
     state: SharedSyncState,
     /// Handle to the file watcher thread
     watcher_handle: Mutex<Option<WatcherHandle>>,
+    /// Handle to the config file watcher thread
+    config_watcher_handle: Mutex<Option<WatcherHandle>>,
+    /// Handle to the staleness ticker thread
+    ticker_handle: Mutex<Option<WatcherHandle>>,
     /// Notifier callback to signal host when updates are ready.
     /// Wrapped in Arc so it can be shared with the watcher thread.
     notifier: SharedNotifier,
@@ -83,6 +118,8 @@ impl ClaudeTasksPlugin {
             tx: Mutex::new(None),
             state: new_shared_state(),
             watcher_handle: Mutex::new(None),
+            config_watcher_handle: Mutex::new(None),
+            ticker_handle: Mutex::new(None),
             notifier: Arc::new(Mutex::new(None)),
         }
     }
@@ -96,6 +133,30 @@ impl ClaudeTasksPlugin {
     }
 }
 
+/// Report the problems of a config load.
+///
+/// Unknown keys are logged (the status report lists them too). Parse
+/// errors return error guidance; once the files parse again, guidance
+/// shown for them is cleared.
+fn note_config_problems(state: &mut SyncState, load: &ConfigLoad) -> Vec<FfiCommand> {
+    for warning in &load.warnings {
+        plugin_warn!("config: {}", warning);
+    }
+
+    if !load.errors.is_empty() {
+        let errors: Vec<String> = load.errors.iter().map(|e| e.to_string()).collect();
+        plugin_error!("config: {}", errors.join("; "));
+        state.set_guidance(GuidanceState::InvalidConfig);
+        return guidance::create_config_error_guidance(&errors);
+    }
+
+    if state.guidance_state == GuidanceState::InvalidConfig {
+        state.clear_guidance();
+        return clear_guidance();
+    }
+    Vec::new()
+}
+
 impl Default for ClaudeTasksPlugin {
     fn default() -> Self {
         Self::new()
@@ -103,26 +164,23 @@ impl Default for ClaudeTasksPlugin {
 }
 
 impl ClaudeTasksPlugin {
-    /// Process all pending sync events using local state tracking.
+    /// Process all pending sync events.
     ///
-    /// Unlike process_sync_events, this doesn't need HostApi - it uses
-    /// local known_tasks tracking to determine create vs update.
-    fn process_sync_events_local(&self) -> Vec<FfiCommand> {
+    /// Both sync paths go through the reconciliation engine ([`reconcile`]).
+    /// With `host` (from `execute_with_host`) the synced todos are queried,
+    /// so only what differs is sent; without it (`on_event`) the local
+    /// known_tasks tracking and agent layout stand in for them. With
+    /// `group_by_agent`, tasks are placed under their agent's sub-header
+    /// (see [`agents`]).
+    fn process_sync_events(&self, host: Option<&dyn TodoHost>) -> Vec<FfiCommand> {
         let mut commands = Vec::new();
 
         // Get tasklist info from state
         let (tasklist_path, tasklist_id) = {
             let state = self.state.lock().unwrap();
-            match &state.selected_tasklist {
-                Some(path) => {
-                    let id = path
-                        .file_name()
-                        .and_then(|n| n.to_str())
-                        .unwrap_or("unknown")
-                        .to_string();
-                    (path.clone(), id)
-                }
-                None => return commands,
+            match (&state.selected_tasklist, state.selected_tasklist_id()) {
+                (Some(path), Some(id)) => (path.clone(), id),
+                _ => return commands,
             }
         };
 
@@ -147,15 +205,38 @@ impl ClaudeTasksPlugin {
         drop(rx_guard);
 
         if !events.is_empty() {
-            plugin_debug!("process_sync_events_local: Draining {} events from channel", events.len());
+            plugin_debug!(
+                "process_sync_events: Draining {} events from channel",
+                events.len()
+            );
         }
 
-        // Record update if we received any events
+        // Config edits are applied before the batch; they aren't task updates
+        let config_changed = events.iter().any(|e| matches!(e, SyncEvent::ConfigChanged));
+        events.retain(|e| !matches!(e, SyncEvent::ConfigChanged));
+        let mut rebuild = false;
+        if config_changed {
+            let (cmds, needs_rebuild) = self.reload_config(&tasklist_id);
+            commands.extend(cmds);
+            rebuild = needs_rebuild;
+        }
+
+        // Record update if we received any events, timed by the files on disk
         if !events.is_empty() {
+            let activity = discovery::last_activity(&tasklist_path);
             let mut state = self.state.lock().unwrap();
+            state.stats.record_events(events.len());
+            if let Some(time) = activity {
+                state.staleness_tracker.set_last_activity(time);
+            }
             state.staleness_tracker.record_update();
         }
 
+        // A full scan recreates the tasklist in its new layout
+        if rebuild {
+            events = vec![SyncEvent::InitialScan];
+        }
+
         // Only clear guidance when real tasks arrive (FileChanged events)
         // InitialScan and FileRemoved don't indicate new tasks arriving
         let has_file_changed_events = events
@@ -176,65 +257,821 @@ impl ClaudeTasksPlugin {
             state.clear_guidance();
         }
 
-        // Process events
-        for event in events {
-            match event {
-                SyncEvent::InitialScan => {
-                    // Get alias from config
-                    let alias = {
-                        let state = self.state.lock().unwrap();
-                        state.config.get_alias(&tasklist_id).map(|s| s.to_string())
-                    };
-
-                    let (cmds, task_ids) =
-                        sync::process_initial_scan_local(&tasklist_path, &tasklist_id, alias.as_deref());
-                    commands.extend(cmds);
-
-                    // Mark all tasks as known
+        let (alias, layout) = {
+            let state = self.state.lock().unwrap();
+            let layout = sync::layout(&state.config, &tasklist_path);
+            (state.display_name(&tasklist_id), layout)
+        };
+
+        // A full scan covers the other events of the batch
+        if events.iter().any(|e| matches!(e, SyncEvent::InitialScan)) {
+            // Nothing is known before the scan; a rebuild's deletes aren't
+            // applied by the host yet
+            let snapshot = match host {
+                Some(host) if !rebuild => {
+                    Snapshot::from_todos(&sync::synced_todos(host, &tasklist_id), &tasklist_id)
+                }
+                _ => Snapshot::default(),
+            };
+            let outcome = sync::sync_tasklist(
+                &tasklist_path,
+                &tasklist_id,
+                snapshot,
+                alias.as_deref(),
+                layout,
+            );
+            commands.extend(outcome.commands);
+
+            let mut state = self.state.lock().unwrap();
+            state.clear_known_tasks();
+            for id in outcome.synced {
+                state.mark_task_known(&id);
+            }
+            state.agent_layout = outcome.agent_layout.unwrap_or_default();
+            return commands;
+        }
+
+        let paths: Vec<PathBuf> = events
+            .into_iter()
+            .filter_map(|e| match e {
+                SyncEvent::FileChanged(path) | SyncEvent::FileRemoved(path) => Some(path),
+                _ => None,
+            })
+            .collect();
+        if paths.is_empty() {
+            return commands;
+        }
+
+        let snapshot = {
+            let state = self.state.lock().unwrap();
+            sync::snapshot(host, &tasklist_id, &state.known_tasks, &state.agent_layout)
+        };
+
+        // Legacy TodoWrite files hold the whole list; sync it as a unit
+        if discovery::is_legacy_todo_file(&tasklist_path) {
+            if paths.contains(&tasklist_path) {
+                let outcome = sync::sync_tasklist(
+                    &tasklist_path,
+                    &tasklist_id,
+                    snapshot,
+                    alias.as_deref(),
+                    layout,
+                );
+                commands.extend(outcome.commands);
+
+                let mut state = self.state.lock().unwrap();
+                state.clear_known_tasks();
+                for id in outcome.synced {
+                    state.mark_task_known(&id);
+                }
+                if let Some(agent_layout) = outcome.agent_layout {
+                    state.agent_layout = agent_layout;
+                }
+            }
+            return commands;
+        }
+
+        let outcome = sync::sync_task_files(
+            &tasklist_path,
+            &tasklist_id,
+            &paths,
+            &snapshot,
+            alias.as_deref(),
+            layout,
+        );
+        commands.extend(outcome.commands);
+
+        let mut state = self.state.lock().unwrap();
+        for id in outcome.removed {
+            state.forget_task(&id);
+        }
+        for id in outcome.synced {
+            state.mark_task_known(&id);
+        }
+        if let Some(agent_layout) = outcome.agent_layout {
+            state.agent_layout = agent_layout;
+        }
+
+        commands
+    }
+
+    /// Stop syncing the selected tasklist.
+    ///
+    /// Shuts down the watcher, drops the event channel and resets the
+    /// per-tasklist state. The config is kept so aliases stay available.
+    fn detach_tasklist(&self) {
+        // Dropping the handles shuts down the watcher threads
+        let handle = self.watcher_handle.lock().unwrap().take();
+        drop(handle);
+        let handle = self.config_watcher_handle.lock().unwrap().take();
+        drop(handle);
+        let handle = self.ticker_handle.lock().unwrap().take();
+        drop(handle);
+
+        *self.rx.lock().unwrap() = None;
+        *self.tx.lock().unwrap() = None;
+
+        let mut state = self.state.lock().unwrap();
+        state.selected_tasklist = None;
+        state.header_todo_id = None;
+        state.project_name = None;
+        state.session_cwd = None;
+        state.agent_layout = AgentLayout::default();
+        state.clear_known_tasks();
+        state.staleness_tracker =
+            staleness::StalenessTracker::with_thresholds(state.config.staleness_thresholds());
+        state.shown_staleness = None;
+        state.drift_check = None;
+    }
+
+    /// Archive the selected tasklist and detach from it.
+    ///
+    /// Converts the synced header and tasks into ordinary todos (see
+    /// [`archive::create_archive_commands`]) and records the tasklist as
+    /// archived so it isn't synced again. An `automatic` archive only holds
+    /// until Claude writes to the tasklist again.
+    fn archive_selected_tasklist(&self, automatic: bool) -> Vec<FfiCommand> {
+        let (tasklist_path, tasklist_id, alias, mut synced_task_ids, agent_header_ids) = {
+            let state = self.state.lock().unwrap();
+            let (Some(path), Some(id)) = (
+                state.selected_tasklist.clone(),
+                state.selected_tasklist_id(),
+            ) else {
+                return Vec::new();
+            };
+            let alias = state.display_name(&id);
+            let known: Vec<String> = state.known_tasks.iter().cloned().collect();
+            let agent_header_ids = state.agent_layout.header_ids(&id);
+            (path, id, alias, known, agent_header_ids)
+        };
+        synced_task_ids.sort();
+
+        let tasks = discovery::scan_tasklist(&tasklist_path);
+        let commands = archive::create_archive_commands(
+            &tasks,
+            &tasklist_id,
+            alias.as_deref(),
+            &synced_task_ids,
+            &agent_header_ids,
+        );
+
+        plugin_info!(
+            "archive: Archived tasklist {} ({} tasks)",
+            tasklist_id,
+            tasks.len()
+        );
+        let until_activity_after = if automatic {
+            Some(discovery::last_activity(&tasklist_path).unwrap_or_else(SystemTime::now))
+        } else {
+            None
+        };
+        archive::record_archived(&tasklist_id, until_activity_after);
+        self.detach_tasklist();
+
+        commands
+    }
+
+    /// Archive the selected tasklist if an auto-archive trigger applies.
+    fn check_auto_archive(&self) -> Option<Vec<FfiCommand>> {
+        let (tasklist_path, is_stale, on_complete, on_stale) = {
+            let state = self.state.lock().unwrap();
+            let path = state.selected_tasklist.clone()?;
+            if state.known_tasks.is_empty() {
+                return None;
+            }
+            (
+                path,
+                state.staleness_tracker.check_staleness().is_some(),
+                state.config.archive_on_complete(),
+                state.config.archive_on_stale(),
+            )
+        };
+
+        if !on_complete && !on_stale {
+            return None;
+        }
+
+        let tasks = discovery::scan_tasklist(&tasklist_path);
+        let reason = archive::should_auto_archive(&tasks, is_stale, on_complete, on_stale)?;
+        plugin_info!("archive: Auto-archive triggered ({:?})", reason);
+
+        Some(self.archive_selected_tasklist(true))
+    }
+
+    /// Start syncing a tasklist.
+    ///
+    /// Stores the tasklist and config in state, starts the file watcher and
+    /// queues an InitialScan event. Guidance (empty tasklist, watcher failure)
+    /// is stored in pending_commands.
+    fn start_syncing(&self, selected: &TasklistInfo, plugin_config: PluginConfig) {
+        let display_name = format_tasklist_display(&selected.id, &plugin_config);
+        let watch_mode = plugin_config.watch_mode();
+
+        // Store selected tasklist path and config in state
+        // Initialize staleness tracker with configured threshold
+        {
+            let mut state = self.state.lock().unwrap();
+            state.selected_tasklist = Some(selected.path.clone());
+            state.project_name = selected.project.as_ref().map(|p| p.name());
+            state.session_cwd = selected.project.as_ref().map(|p| p.cwd.clone());
+            state.staleness_tracker =
+                staleness::StalenessTracker::with_thresholds(plugin_config.staleness_thresholds());
+            state.shown_staleness = None;
+            state.config = plugin_config;
+            // The archived tasklist's guidance doesn't apply to this one
+            if state.guidance_state == GuidanceState::Archived {
+                state.pending_commands.extend(clear_guidance());
+                state.clear_guidance();
+            }
+        }
+
+        // Turn the header stale on time, even without file events
+        let ticker = watcher::start_staleness_ticker(self.state.clone(), self.notifier.clone());
+        *self.ticker_handle.lock().unwrap() = Some(ticker);
+
+        // Create mpsc channel for watcher -> plugin communication
+        let (tx, rx) = mpsc::channel::<SyncEvent>();
+
+        // Store receiver
+        *self.rx.lock().unwrap() = Some(rx);
+
+        // Clone tx for InitialScan send (before moving to watcher)
+        let tx_for_initial = tx.clone();
+
+        // Store sender
+        *self.tx.lock().unwrap() = Some(tx.clone());
+
+        // Watch the config files so edits apply while syncing
+        match watcher::start_config_watcher(
+            config::config_paths(),
+            tx.clone(),
+            self.notifier.clone(),
+            watch_mode,
+        ) {
+            Ok(handle) => *self.config_watcher_handle.lock().unwrap() = Some(handle),
+            Err(e) => plugin_warn!("start_syncing: Not watching config files: {}", e),
+        }
+
+        // Start file watcher with notifier for immediate host wakeup
+        match watcher::start_watcher(selected.path.clone(), tx, self.notifier.clone(), watch_mode) {
+            Ok(handle) => {
+                *self.watcher_handle.lock().unwrap() = Some(handle);
+
+                // Check if tasklist has any tasks - if empty, show waiting guidance
+                let tasks = discovery::scan_tasklist(&selected.path);
+                if tasks.is_empty() {
                     let mut state = self.state.lock().unwrap();
-                    state.clear_known_tasks();
-                    for id in task_ids {
-                        state.mark_task_known(&id);
-                    }
+                    state
+                        .pending_commands
+                        .extend(create_empty_tasklist_guidance(&display_name));
+                    state.set_guidance(GuidanceState::EmptyTasklist);
                 }
-                SyncEvent::FileChanged(path) => {
-                    let is_known = {
-                        let state = self.state.lock().unwrap();
-                        // Extract task_id from path to check if known
-                        path.file_stem()
-                            .and_then(|s| s.to_str())
-                            .map(|id| state.is_task_known(id))
-                            .unwrap_or(false)
-                    };
-
-                    if let Some((cmds, task_id)) =
-                        sync::process_file_change_local(&path, &tasklist_id, is_known)
-                    {
-                        commands.extend(cmds);
-
-                        // Mark as known if new
-                        if !is_known {
-                            let mut state = self.state.lock().unwrap();
-                            state.mark_task_known(&task_id);
-                        }
-                    }
+            }
+            Err(e) => {
+                let error_msg = e.to_string();
+                let mut state = self.state.lock().unwrap();
+                state.pending_commands = guidance::create_error_guidance(
+                    "CLAUDE TASKS - Watcher Failed",
+                    &error_msg,
+                    "Restart totui to retry",
+                );
+                state.set_guidance(GuidanceState::Error);
+                return;
+            }
+        }
+
+        // Send InitialScan event to trigger first sync
+        let _ = tx_for_initial.send(SyncEvent::InitialScan);
+    }
+
+    /// Handle a plugin invocation with host access (see `execute_with_host`).
+    ///
+    /// Errors are user-facing messages.
+    fn execute(&self, input: &str, host: &dyn TodoHost) -> Result<Vec<FfiCommand>, String> {
+        // Run the prompt command first so errors are returned before any
+        // sync events are drained (drained events would otherwise be lost)
+        let mut commands = prompt::parse_command(input)
+            .and_then(|command| self.run_prompt_command(command, host))?;
+
+        // Host access is only available here, so the configured prune runs now
+        commands.extend(self.run_first_invocation_prune(host));
+        commands.extend(self.run_metadata_migration(host));
+        if commands.is_empty() {
+            commands.extend(self.run_leftover_cleanup(host));
+        }
+
+        // Process sync events against the todos in totui; with commands
+        // already returned in this invocation (not applied by the host yet),
+        // fall back to local state like on_event
+        let snapshot_host = commands.is_empty().then_some(host);
+        let synced = self.process_sync_events(snapshot_host);
+
+        // Only check for drift with nothing in flight; the host hasn't
+        // applied commands returned in this invocation yet
+        if commands.is_empty() && synced.is_empty() {
+            commands.extend(self.check_drift(host, false));
+        }
+        commands.extend(synced);
+
+        self.count_commands(&commands);
+        Ok(commands)
+    }
+
+    /// Run a command entered in the plugin prompt.
+    ///
+    /// `None` (empty input) does nothing. Errors are user-facing messages.
+    fn run_prompt_command(
+        &self,
+        command: Option<PromptCommand>,
+        host: &dyn TodoHost,
+    ) -> Result<Vec<FfiCommand>, String> {
+        let Some(command) = command else {
+            return Ok(Vec::new());
+        };
+        plugin_info!("prompt: Running {:?}", command);
+
+        match command {
+            PromptCommand::Switch(query) => self.switch_tasklist(&query, host),
+            PromptCommand::Refresh => self.refresh_tasklist().map(|_| Vec::new()),
+            PromptCommand::Alias {
+                tasklist,
+                name,
+                scope,
+            } => self.set_alias(&tasklist, &name, scope),
+            PromptCommand::Unalias(tasklist) => self.remove_alias(&tasklist),
+            PromptCommand::Prune { dry_run } => Ok(self.run_prune(host, dry_run)),
+            PromptCommand::Status => Ok(self.show_status()),
+            PromptCommand::Archive => {
+                if self.selected_tasklist_id().is_none() {
+                    return Err(NO_TASKLIST_SELECTED.to_string());
                 }
-                SyncEvent::FileRemoved(path) => {
-                    if let Some((cmd, task_id)) =
-                        sync::process_file_removal_local(&path, &tasklist_id)
-                    {
-                        commands.push(cmd);
-
-                        // Forget the task
-                        let mut state = self.state.lock().unwrap();
-                        state.forget_task(&task_id);
-                    }
+                Ok(self.archive_selected_tasklist(false))
+            }
+            PromptCommand::Unarchive(query) => self.unarchive_tasklist(&query, host),
+            PromptCommand::Repair => {
+                if self.selected_tasklist_id().is_none() {
+                    return Err(NO_TASKLIST_SELECTED.to_string());
                 }
+                Ok(self.check_drift(host, true))
+            }
+        }
+    }
+
+    /// Compare the selected tasklist with its todos in totui and repair drift.
+    ///
+    /// Runs at most every [`drift::DRIFT_CHECK_INTERVAL`] unless `force`d.
+    /// Skipped while task files fail to parse (e.g., caught mid-write), so a
+    /// half-written task isn't taken for a deleted one. Known tasks are reset
+    /// to the folder's tasks, so later file events update the repaired todos.
+    fn check_drift(&self, host: &dyn TodoHost, force: bool) -> Vec<FfiCommand> {
+        let (tasklist_path, tasklist_id) = {
+            let state = self.state.lock().unwrap();
+            let due = state
+                .drift_check
+                .as_ref()
+                .is_none_or(|(at, _)| at.elapsed() >= drift::DRIFT_CHECK_INTERVAL);
+            match (&state.selected_tasklist, state.selected_tasklist_id()) {
+                (Some(path), Some(id)) if force || due => (path.clone(), id),
+                _ => return Vec::new(),
+            }
+        };
+
+        let (_, unreadable) = discovery::task_files(&tasklist_path);
+        let tasks = discovery::scan_tasklist(&tasklist_path);
+        let todos = sync::synced_todos(host, &tasklist_id);
+        let mut state = self.state.lock().unwrap();
+        let layout = sync::layout(&state.config, &tasklist_path);
+        let found = drift::find_drift(&tasks, &todos, &tasklist_id, layout);
+
+        if !unreadable.is_empty() {
+            let summary = format!("skipped ({} unreadable task files)", unreadable.len());
+            state.drift_check = Some((Instant::now(), summary));
+            return Vec::new();
+        }
+        state.drift_check = Some((Instant::now(), found.summary()));
+        if found.is_empty() {
+            return Vec::new();
+        }
+
+        plugin_warn!("drift: {} ({}), repairing", found.summary(), tasklist_id);
+        state.clear_known_tasks();
+        for task in &tasks {
+            state.mark_task_known(&task.id);
+        }
+        state.agent_layout = found.layout.clone();
+        let name = state.display_name(&tasklist_id);
+        found.commands(&tasklist_id, name.as_deref())
+    }
+
+    /// ID of the selected tasklist, if any.
+    fn selected_tasklist_id(&self) -> Option<String> {
+        self.state.lock().unwrap().selected_tasklist_id()
+    }
+
+    /// Switch syncing to another tasklist (alias, UUID or UUID prefix).
+    ///
+    /// The initial scan is queued; the caller drains it afterwards.
+    ///
+    /// The todos of the previous tasklist are removed. The switch lasts for
+    /// the session: it isn't written to totui's plugin settings, and the
+    /// tasklist chosen there is synced again after a restart or once that
+    /// setting changes. After a restart, the switched-to tasklist's todos
+    /// are removed on the first invocation (see `run_leftover_cleanup`).
+    fn switch_tasklist(&self, query: &str, host: &dyn TodoHost) -> Result<Vec<FfiCommand>, String> {
+        let config = self.state.lock().unwrap().config.clone();
+        let tasklists = discovery::discover_tasklists(&config);
+        let selected = prompt::resolve_tasklist(query, &tasklists, &config)?.clone();
+
+        if archive::is_archived(&selected.id, selected.last_modified) {
+            return Err(format!(
+                "Tasklist {} is archived. Use: unarchive {}",
+                selected.id, selected.id
+            ));
+        }
+        if self.selected_tasklist_id().as_deref() == Some(selected.id.as_str()) {
+            return Ok(Vec::new());
+        }
+
+        let previous: Vec<String> = match self.selected_tasklist_id() {
+            Some(previous_id) => sync::synced_todos(host, &previous_id)
+                .into_iter()
+                .map(|(todo, _)| todo.id.to_string())
+                .collect(),
+            None => Vec::new(),
+        };
+        self.detach_tasklist();
+        self.start_syncing(&selected, config);
+
+        let mut commands = commands::clear_todos_commands(&previous);
+        commands.extend(self.state.lock().unwrap().take_pending_commands());
+        Ok(commands)
+    }
+
+    /// Remove a tasklist from the archive record and start syncing it.
+    fn unarchive_tasklist(
+        &self,
+        query: &str,
+        host: &dyn TodoHost,
+    ) -> Result<Vec<FfiCommand>, String> {
+        let config = self.state.lock().unwrap().config.clone();
+        let tasklists = discovery::discover_tasklists(&config);
+        let tasklist_id = prompt::resolve_tasklist(query, &tasklists, &config)?
+            .id
+            .clone();
+
+        if !archive::remove_archived(&tasklist_id)? {
+            return Err(format!("Tasklist {} is not archived", tasklist_id));
+        }
+        plugin_info!("archive: Unarchived tasklist {}", tasklist_id);
+
+        self.switch_tasklist(&tasklist_id, host)
+    }
+
+    /// Rescan the selected tasklist from disk.
+    ///
+    /// Queues FileChanged for every task file and FileRemoved for known tasks
+    /// whose file is gone; the caller drains them afterwards.
+    fn refresh_tasklist(&self) -> Result<(), String> {
+        let (path, known) = {
+            let state = self.state.lock().unwrap();
+            let path = state
+                .selected_tasklist
+                .clone()
+                .ok_or_else(|| NO_TASKLIST_SELECTED.to_string())?;
+            (path, state.known_tasks.clone())
+        };
+        let tx = self
+            .tx
+            .lock()
+            .unwrap()
+            .clone()
+            .ok_or_else(|| NO_TASKLIST_SELECTED.to_string())?;
+
+        // Legacy TodoWrite files are rescanned as a whole
+        if discovery::is_legacy_todo_file(&path) {
+            let _ = tx.send(SyncEvent::FileChanged(path));
+            return Ok(());
+        }
+
+        let tasks = discovery::scan_tasklist(&path);
+        for task in &tasks {
+            let _ = tx.send(SyncEvent::FileChanged(
+                path.join(format!("{}.json", task.id)),
+            ));
+        }
+        for task_id in known {
+            if !tasks.iter().any(|t| t.id == task_id) {
+                let _ = tx.send(SyncEvent::FileRemoved(
+                    path.join(format!("{}.json", task_id)),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Set a display name for a tasklist (UUID or UUID prefix).
+    ///
+    /// The alias is written to the global or local config file (see
+    /// [`ConfigScope::default_for_alias`]) and the config is reloaded, so the
+    /// header and the tasklist picker pick it up immediately.
+    fn set_alias(
+        &self,
+        query: &str,
+        name: &str,
+        scope: Option<ConfigScope>,
+    ) -> Result<Vec<FfiCommand>, String> {
+        let config = self.state.lock().unwrap().config.clone();
+        let tasklists = discovery::discover_tasklists(&config);
+        let tasklist_id = prompt::resolve_tasklist(query, &tasklists, &config)?
+            .id
+            .clone();
+
+        let scope = scope.unwrap_or_else(ConfigScope::default_for_alias);
+        let path = config::save_alias(&tasklist_id, name, scope).map_err(|e| e.to_string())?;
+        plugin_info!("alias: {} = {} ({})", tasklist_id, name, path.display());
+
+        Ok(self.reload_aliases(&tasklist_id))
+    }
+
+    /// Remove a tasklist's display name from all config files.
+    fn remove_alias(&self, query: &str) -> Result<Vec<FfiCommand>, String> {
+        let config = self.state.lock().unwrap().config.clone();
+        let tasklists = discovery::discover_tasklists(&config);
+        // The tasklist folder may be gone while its alias remains
+        let tasklist_id = match prompt::resolve_tasklist(query, &tasklists, &config) {
+            Ok(t) => t.id.clone(),
+            Err(_) if config.aliases.contains_key(query) => query.to_string(),
+            Err(message) => return Err(message),
+        };
+
+        let paths = config::remove_alias(&tasklist_id).map_err(|e| e.to_string())?;
+        if paths.is_empty() {
+            return Err(format!("Tasklist {} has no alias", tasklist_id));
+        }
+        plugin_info!("alias: Removed alias for {}", tasklist_id);
+
+        Ok(self.reload_aliases(&tasklist_id))
+    }
+
+    /// Reload aliases from the config files after an alias change.
+    ///
+    /// Returns a header update if `tasklist_id` is the synced tasklist.
+    fn reload_aliases(&self, tasklist_id: &str) -> Vec<FfiCommand> {
+        let aliases = load_config().aliases;
+        let mut state = self.state.lock().unwrap();
+        state.config.aliases = aliases;
+
+        let is_selected = state.selected_tasklist_id().as_deref() == Some(tasklist_id);
+        if !is_selected {
+            return Vec::new();
+        }
+
+        let status = state.staleness_tracker.header_status();
+        state.shown_staleness = status.as_ref().map(HeaderStatus::label);
+        let name = state.display_name(tasklist_id);
+        vec![commands::update_header_command(
+            tasklist_id,
+            name.as_deref(),
+            status.as_ref(),
+        )]
+    }
+
+    /// Reload the config files after one of them changed.
+    ///
+    /// Applies the new config to the synced tasklist: the header is renamed
+    /// and re-evaluated against the staleness threshold. If `group_by_agent`
+    /// changed, the synced todos are removed and `true` is returned so the
+    /// tasklist is rebuilt from a full scan in the new layout. Files that
+    /// fail to parse are reported as error guidance and keep their last
+    /// valid settings.
+    fn reload_config(&self, tasklist_id: &str) -> (Vec<FfiCommand>, bool) {
+        let load = load_config_report();
+        log::configure(load.config.log_settings());
+        plugin_info!("reload_config: Config files changed, reloading");
+
+        let mut state = self.state.lock().unwrap();
+        let mut cmds = note_config_problems(&mut state, &load);
+        let config = load.config;
+        let regroup = config.group_by_agent() != state.config.group_by_agent();
+        state
+            .staleness_tracker
+            .set_thresholds(config.staleness_thresholds());
+        state.config = config;
+
+        let rebuild = regroup
+            && state
+                .selected_tasklist
+                .as_deref()
+                .is_some_and(|path| !discovery::is_legacy_todo_file(path));
+        if !rebuild {
+            let status = state.staleness_tracker.header_status();
+            state.shown_staleness = status.as_ref().map(HeaderStatus::label);
+            let name = state.display_name(tasklist_id);
+            cmds.push(commands::update_header_command(
+                tasklist_id,
+                name.as_deref(),
+                status.as_ref(),
+            ));
+            return (cmds, false);
+        }
+
+        // Children first, so nothing is left without a parent
+        let mut known: Vec<String> = state.known_tasks.iter().cloned().collect();
+        known.sort();
+        cmds.extend(
+            known
+                .iter()
+                .map(|id| commands::delete_todo_command(&commands::task_todo_id(tasklist_id, id))),
+        );
+        cmds.extend(
+            state
+                .agent_layout
+                .header_ids(tasklist_id)
+                .iter()
+                .map(|id| commands::delete_todo_command(id)),
+        );
+        let hdr_id = commands::header_id(tasklist_id);
+        cmds.push(commands::delete_todo_command(&hdr_id));
+
+        state.clear_known_tasks();
+        state.agent_layout = AgentLayout::default();
+        (cmds, true)
+    }
+
+    /// Count commands returned to the host for the status report.
+    fn count_commands(&self, commands: &[FfiCommand]) {
+        let mut state = self.state.lock().unwrap();
+        state.stats.record_commands(commands.len());
+    }
+
+    /// Show a status report, replacing the previous one.
+    fn show_status(&self) -> Vec<FfiCommand> {
+        let load = load_config_report();
+        let watcher = self.watcher_handle.lock().unwrap();
+        let watcher = watcher.as_ref().map(WatcherHandle::health);
+        let selected = self.state.lock().unwrap().selected_tasklist.clone();
+        let (task_files, unreadable_files) = selected
+            .as_deref()
+            .map(discovery::task_files)
+            .unwrap_or_default();
+        let mut state = self.state.lock().unwrap();
+
+        let mut lines = Vec::new();
+        match state.selected_tasklist_id() {
+            Some(id) => {
+                lines.push(format!(
+                    "Syncing: {}",
+                    format_tasklist_display(&id, &state.config)
+                ));
+                let last_update = match state.staleness_tracker.time_since_update() {
+                    Some(elapsed) => format!("{} ago", staleness::format_duration(elapsed)),
+                    None => "none yet".to_string(),
+                };
+                lines.push(format!("Last update: {}", last_update));
             }
+            None => lines.push("Not syncing any tasklist".to_string()),
+        }
+        lines.extend(status::diagnostic_lines(&status::Diagnostics {
+            watcher,
+            synced_tasks: state.known_tasks.len(),
+            task_files: task_files.len(),
+            unreadable_files,
+            guidance: state.guidance_state.clone(),
+            pending_commands: state.pending_commands.len(),
+            stats: state.stats.clone(),
+            drift_check: state
+                .drift_check
+                .as_ref()
+                .map(|(at, summary)| (at.elapsed(), summary.clone())),
+        }));
+        if load.files.is_empty() {
+            lines.push("Config: defaults (no config files)".to_string());
+        }
+        for path in &load.files {
+            lines.push(format!("Config: {}", path.display()));
+        }
+        for error in &load.errors {
+            lines.push(error.to_string());
+        }
+        for warning in &load.warnings {
+            lines.push(format!("Config warning: {}", warning));
+        }
+        lines.push(prompt::USAGE.to_string());
+
+        let mut commands = commands::clear_todos_commands(&state.status_ids);
+        let (report, ids) = status::create_status_commands(&lines);
+        state.status_ids = ids;
+        commands.extend(report);
+        commands
+    }
+
+    /// Prune synced todos whose tasklist folder no longer exists.
+    ///
+    /// With `dry_run`, renders a preview of the orphans instead of removing
+    /// them. Any previous preview is cleared first.
+    fn run_prune(&self, host: &dyn TodoHost, dry_run: bool) -> Vec<FfiCommand> {
+        let config = load_config();
+        let action = config.prune_action();
+        let (mut live_ids, previous_preview) = {
+            let mut state = self.state.lock().unwrap();
+            let selected: Vec<String> = state.selected_tasklist_id().into_iter().collect();
+            (selected, std::mem::take(&mut state.prune_preview_ids))
+        };
+        live_ids.extend(discovery::discover_tasklist_ids(&config));
+
+        let mut commands = commands::clear_todos_commands(&previous_preview);
+        let orphans = prune::find_orphans(host, &live_ids);
+
+        if dry_run {
+            let (preview, ids) = prune::create_prune_preview_commands(&orphans, action);
+            self.state.lock().unwrap().prune_preview_ids = ids;
+            commands.extend(preview);
+        } else {
+            plugin_info!(
+                "prune: Removing {} orphaned tasklists ({:?})",
+                orphans.len(),
+                action
+            );
+            commands.extend(prune::create_prune_commands(&orphans, action));
         }
 
         commands
     }
+
+    /// Run the configured prune once, on the first host invocation.
+    fn run_first_invocation_prune(&self, host: &dyn TodoHost) -> Vec<FfiCommand> {
+        let mode = {
+            let mut state = self.state.lock().unwrap();
+            if state.first_invocation_prune_done {
+                return Vec::new();
+            }
+            state.first_invocation_prune_done = true;
+            load_config().prune_on_first_invocation()
+        };
+
+        match mode {
+            PruneOnFirstInvocation::Off => Vec::new(),
+            PruneOnFirstInvocation::Preview => self.run_prune(host, true),
+            PruneOnFirstInvocation::Apply => self.run_prune(host, false),
+        }
+    }
+
+    /// Rewrite metadata of older schema versions once, on the first host
+    /// invocation (see [`metadata`]).
+    fn run_metadata_migration(&self, host: &dyn TodoHost) -> Vec<FfiCommand> {
+        {
+            let mut state = self.state.lock().unwrap();
+            if state.metadata_migration_done {
+                return Vec::new();
+            }
+            state.metadata_migration_done = true;
+        }
+
+        let commands = metadata::migration_commands(&metadata::query_synced(host));
+        if !commands.is_empty() {
+            plugin_info!(
+                "metadata: Migrating {} todos to version {}",
+                commands.len(),
+                metadata::METADATA_VERSION
+            );
+        }
+        commands
+    }
+
+    /// Remove the todos of tasklists other than the selected one, once.
+    ///
+    /// Only the selected tasklist is synced, so todos of another existing
+    /// tasklist are left over from a `switch` in an earlier session (the
+    /// switch isn't saved). Todos of vanished tasklists are left to prune.
+    /// Runs on the first host invocation with no other commands in flight,
+    /// since those may already delete some of the same todos.
+    fn run_leftover_cleanup(&self, host: &dyn TodoHost) -> Vec<FfiCommand> {
+        let (selected, config) = {
+            let mut state = self.state.lock().unwrap();
+            if state.leftover_cleanup_done {
+                return Vec::new();
+            }
+            state.leftover_cleanup_done = true;
+            (state.selected_tasklist_id(), state.config.clone())
+        };
+
+        let existing: HashSet<String> = discovery::discover_tasklist_ids(&config)
+            .into_iter()
+            .collect();
+        let selected: Vec<String> = selected.into_iter().collect();
+        let leftovers: Vec<prune::OrphanTasklist> = prune::find_orphans(host, &selected)
+            .into_iter()
+            .filter(|group| existing.contains(&group.tasklist_id))
+            .collect();
+        if !leftovers.is_empty() {
+            plugin_info!(
+                "cleanup: Removing todos of {} tasklists from an earlier switch",
+                leftovers.len()
+            );
+        }
+        prune::create_prune_commands(&leftovers, PruneAction::Delete)
+    }
 }
 
 impl std::fmt::Debug for ClaudeTasksPlugin {
@@ -265,7 +1102,8 @@ impl Plugin for ClaudeTasksPlugin {
 
     fn config_schema(&self) -> FfiConfigSchema {
         // Load config to resolve aliases for display
-        let plugin_config = load_config();
+        let load = load_config_report();
+        let plugin_config = load.config;
 
         // Generate options from discovered tasklists
         let options = generate_tasklist_options(&plugin_config);
@@ -277,37 +1115,32 @@ impl Plugin for ClaudeTasksPlugin {
             .map(|(display, uuid)| RString::from(format!("{}|{}", display, uuid)))
             .collect();
 
-        // Create the tasklist field
-        let tasklist_field = FfiConfigField {
-            name: RString::from("tasklist"),
-            field_type: FfiConfigType::Select,
-            required: false, // Will auto-select first if not specified
-            default: ROption::RNone,
-            description: ROption::RSome(RString::from("Select which Claude tasklist to sync")),
-            options: option_strings,
-        };
-
-        FfiConfigSchema {
-            fields: vec![tasklist_field].into_iter().collect(),
-            config_required: false,
-        }
+        // The tasklist field followed by every plugin setting
+        schema::config_schema(option_strings, &load.sources)
     }
 
     fn execute_with_host(
         &self,
-        _input: RString,
-        _host: HostApi_TO<'_, RBox<()>>,
+        input: RString,
+        host: HostApi_TO<'_, RBox<()>>,
     ) -> RResult<RVec<FfiCommand>, RString> {
-        // Process sync events using local state (same as on_event)
-        // This allows manual plugin invocation to also work
-        let commands = self.process_sync_events_local();
-        RResult::ROk(commands.into_iter().collect())
+        match self.execute(input.as_str(), &host) {
+            Ok(commands) => RResult::ROk(commands.into_iter().collect()),
+            Err(message) => RResult::RErr(RString::from(message)),
+        }
     }
 
     fn on_config_loaded(&self, config: RHashMap<RString, FfiConfigValue>) {
+        // Plugin settings from totui are merged below the config files
+        let (host_config, warnings) = schema::host_config(&config);
+        for warning in &warnings {
+            plugin_info!("config: {}", warning);
+        }
+        config::set_host_config(host_config);
+
         // Check if user is changing tasklist selection
         let new_tasklist_id = config
-            .get(&RString::from("tasklist"))
+            .get(&RString::from(schema::TASKLIST_FIELD))
             .and_then(|v| match v {
                 FfiConfigValue::String(s) => Some(s.to_string()),
                 _ => None,
@@ -316,15 +1149,22 @@ impl Plugin for ClaudeTasksPlugin {
         // Prevent duplicate initialization - if watcher already exists for same tasklist, skip
         {
             let handle = self.watcher_handle.lock().unwrap();
-            let state = self.state.lock().unwrap();
+            let mut state = self.state.lock().unwrap();
+            let configured_before =
+                std::mem::replace(&mut state.configured_tasklist_id, new_tasklist_id.clone());
             if handle.is_some() {
-                // Check if this is a change to a different tasklist
-                let current_id = state.selected_tasklist.as_ref().and_then(|p| {
-                    p.file_name().and_then(|n| n.to_str()).map(|s| s.to_string())
-                });
-
-                if new_tasklist_id == current_id || new_tasklist_id.is_none() {
-                    // Already watching this tasklist
+                // Check if this is a change to a different tasklist; a prompt
+                // `switch` holds until the tasklist setting itself changes
+                let current_id = state.selected_tasklist_id();
+
+                if new_tasklist_id == current_id
+                    || new_tasklist_id.is_none()
+                    || new_tasklist_id == configured_before
+                {
+                    // Already watching this tasklist - apply changed settings live
+                    if let Some(tx) = self.tx.lock().unwrap().as_ref() {
+                        let _ = tx.send(SyncEvent::ConfigChanged);
+                    }
                     return;
                 }
                 // User is switching tasklists - need to stop old watcher and start new one
@@ -332,14 +1172,22 @@ impl Plugin for ClaudeTasksPlugin {
         }
 
         // Load plugin configuration (global + local merged)
-        let plugin_config = load_config();
+        // Stored right away so prompt commands can resolve aliases before a tasklist is selected
+        let load = load_config_report();
+        log::configure(load.config.log_settings());
+        let plugin_config = load.config.clone();
+        {
+            let mut state = self.state.lock().unwrap();
+            state.config = plugin_config.clone();
+            state.pending_commands = note_config_problems(&mut state, &load);
+        }
 
         // Discover available tasklists
-        let tasklists = discovery::discover_tasklists();
+        let tasklists = discovery::discover_tasklists(&plugin_config);
 
         if tasklists.is_empty() {
             let mut state = self.state.lock().unwrap();
-            state.pending_commands = create_no_tasklist_guidance();
+            state.pending_commands.extend(create_no_tasklist_guidance());
             state.set_guidance(GuidanceState::NoTasklists);
             return;
         }
@@ -347,7 +1195,7 @@ impl Plugin for ClaudeTasksPlugin {
         // Check if user selected a specific tasklist via config
         // If no tasklist is explicitly selected, do nothing (wait for user to select one)
         let selected = if let Some(FfiConfigValue::String(uuid)) =
-            config.get(&RString::from("tasklist"))
+            config.get(&RString::from(schema::TASKLIST_FIELD))
         {
             let uuid_str = uuid.as_str();
             if uuid_str.is_empty() {
@@ -355,70 +1203,48 @@ impl Plugin for ClaudeTasksPlugin {
                 return;
             }
             // Find tasklist with matching UUID
-            match tasklists.iter().find(|t| t.id == uuid_str).cloned() {
+            let selected = match tasklists.iter().find(|t| t.id == uuid_str).cloned() {
                 Some(t) => t,
                 None => {
                     // Configured tasklist not found - don't auto-select another
                     return;
                 }
+            };
+            // Archived tasklists are kept as ordinary todos - don't sync them again
+            if archive::is_archived(&selected.id, selected.last_modified) {
+                plugin_info!("on_config_loaded: Tasklist {} is archived", uuid_str);
+                let display_name = format_tasklist_display(&selected.id, &plugin_config);
+                let mut state = self.state.lock().unwrap();
+                if state.guidance_state != GuidanceState::Archived {
+                    let archived = guidance::create_archived_guidance(&display_name, &selected.id);
+                    state.pending_commands.extend(archived);
+                    state.set_guidance(GuidanceState::Archived);
+                }
+                return;
             }
+            selected
         } else {
             // No tasklist specified in config - wait for user to select one
             return;
         };
 
-        let display_name = format_tasklist_display(&selected.id, &plugin_config);
-
-        // Store selected tasklist path and config in state
-        // Initialize staleness tracker with configured threshold
-        {
-            let mut state = self.state.lock().unwrap();
-            state.selected_tasklist = Some(selected.path.clone());
-            state.staleness_tracker =
-                staleness::StalenessTracker::new(plugin_config.staleness_threshold());
-            state.config = plugin_config;
-        }
-
-        // Create mpsc channel for watcher -> plugin communication
-        let (tx, rx) = mpsc::channel::<SyncEvent>();
-
-        // Store receiver
-        *self.rx.lock().unwrap() = Some(rx);
-
-        // Clone tx for InitialScan send (before moving to watcher)
-        let tx_for_initial = tx.clone();
-
-        // Store sender
-        *self.tx.lock().unwrap() = Some(tx.clone());
-
-        // Start file watcher with notifier for immediate host wakeup
-        match watcher::start_watcher(selected.path.clone(), tx, self.notifier.clone()) {
-            Ok(handle) => {
-                *self.watcher_handle.lock().unwrap() = Some(handle);
-
-                // Check if tasklist has any tasks - if empty, show waiting guidance
-                let tasks = discovery::scan_tasks_directory(&selected.path);
-                if tasks.is_empty() {
-                    let mut state = self.state.lock().unwrap();
-                    state.pending_commands = create_empty_tasklist_guidance(&display_name);
-                    state.set_guidance(GuidanceState::EmptyTasklist);
-                }
-            }
-            Err(e) => {
-                let error_msg = e.to_string();
-                let mut state = self.state.lock().unwrap();
-                state.pending_commands = guidance::create_error_guidance(
-                    "CLAUDE TASKS - Watcher Failed",
-                    &error_msg,
-                    "Restart totui to retry",
-                );
-                state.set_guidance(GuidanceState::Error);
-                return;
+        // The previous tasklist's todos are removed, as with `switch`
+        let previous = {
+            let state = self.state.lock().unwrap();
+            match state.selected_tasklist_id() {
+                Some(id) if id != selected.id => state.synced_todo_ids(),
+                _ => Vec::new(),
             }
+        };
+        if !previous.is_empty() {
+            self.detach_tasklist();
         }
+        self.start_syncing(&selected, plugin_config);
 
-        // Send InitialScan event to trigger first sync
-        let _ = tx_for_initial.send(SyncEvent::InitialScan);
+        let mut state = self.state.lock().unwrap();
+        let pending = state.take_pending_commands();
+        state.pending_commands = commands::clear_todos_commands(&previous);
+        state.pending_commands.extend(pending);
     }
 
     fn subscribed_events(&self) -> RVec<FfiEventType> {
@@ -440,47 +1266,46 @@ impl Plugin for ClaudeTasksPlugin {
 
             if !pending.is_empty() {
                 plugin_info!("on_event: Returning {} pending guidance commands", pending.len());
+                self.count_commands(&pending);
                 return RResult::ROk(FfiHookResponse {
                     commands: pending.into_iter().collect(),
                 });
             }
 
             // Process pending sync events and return commands
-            let mut commands = self.process_sync_events_local();
+            let mut commands = self.process_sync_events(None);
             plugin_info!("on_event: Processed sync events, got {} commands", commands.len());
 
             // Check staleness and update header if needed
-            let staleness_info = {
-                let state = self.state.lock().unwrap();
-                if let Some(ref tasklist_path) = state.selected_tasklist {
-                    let tasklist_id = tasklist_path
-                        .file_name()
-                        .and_then(|n| n.to_str())
-                        .unwrap_or("unknown")
-                        .to_string();
-                    let alias = state.config.get_alias(&tasklist_id).map(|s| s.to_string());
-                    let staleness = state.staleness_tracker.format_staleness();
-                    let is_tracking = state.staleness_tracker.is_tracking();
-                    Some((tasklist_id, alias, staleness, is_tracking))
-                } else {
-                    None
+            // Add header update if the staleness text changed, or after syncing
+            // Only update header if we're actively tracking (have received at least one update)
+            let header_update = {
+                let mut state = self.state.lock().unwrap();
+                let synced = state.staleness_tracker.is_tracking() && !commands.is_empty();
+                match state.selected_tasklist_id() {
+                    Some(tasklist_id) if synced || state.staleness_display_changed() => {
+                        let alias = state.display_name(&tasklist_id);
+                        let status = state.staleness_tracker.header_status();
+                        state.shown_staleness = status.as_ref().map(HeaderStatus::label);
+                        // Update header with or without staleness indicator
+                        Some(commands::update_header_command(
+                            &tasklist_id,
+                            alias.as_deref(),
+                            status.as_ref(),
+                        ))
+                    }
+                    _ => None,
                 }
             };
+            commands.extend(header_update);
 
-            // Add header update if stale, or if transitioning from stale to fresh
-            // Only update header if we're actively tracking (have received at least one update)
-            if let Some((tasklist_id, alias, staleness, is_tracking)) = staleness_info {
-                if is_tracking && (staleness.is_some() || !commands.is_empty()) {
-                    // Update header with or without staleness indicator
-                    commands.push(commands::update_header_command(
-                        &tasklist_id,
-                        alias.as_deref(),
-                        staleness.as_deref(),
-                    ));
-                }
+            // Archive (and detach) if an auto-archive trigger applies
+            if let Some(archive_cmds) = self.check_auto_archive() {
+                commands.extend(archive_cmds);
             }
 
             plugin_info!("on_event: Returning {} total commands", commands.len());
+            self.count_commands(&commands);
             return RResult::ROk(FfiHookResponse {
                 commands: commands.into_iter().collect(),
             });
@@ -496,8 +1321,16 @@ impl Plugin for ClaudeTasksPlugin {
 
 #[cfg(test)]
 mod tests {
+    use super::fake_host::{self, FakeHost};
+    use super::paths::{set_plugin_paths, PluginPaths};
     use super::state::SyncEvent;
-    use std::path::PathBuf;
+    use super::watcher::WatcherBackend;
+    use super::ClaudeTasksPlugin;
+    use abi_stable::std_types::{RHashMap, RString};
+    use std::path::{Path, PathBuf};
+    use std::sync::{Mutex, MutexGuard};
+    use std::time::Duration;
+    use totui_plugin_interface::{FfiConfigValue, FfiEvent, Plugin};
 
     #[test]
     fn test_file_changed_event_detection() {
@@ -531,4 +1364,353 @@ mod tests {
             "FileRemoved alone should not trigger clearing"
         );
     }
+
+    // ========================================================================
+    // Plugin lifecycle tests against the in-memory host
+    // ========================================================================
+
+    /// A temporary folder holding the task root and every folder the plugin
+    /// would otherwise read from the user's setup (see [`PluginPaths`]).
+    ///
+    /// Lifecycle tests share the host config and plugin paths, so they run
+    /// one at a time; the user's folders are restored on drop.
+    struct TestEnv {
+        dir: tempfile::TempDir,
+        _lock: MutexGuard<'static, ()>,
+    }
+
+    impl TestEnv {
+        fn new() -> Self {
+            static LOCK: Mutex<()> = Mutex::new(());
+            let lock = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+            let dir = tempfile::tempdir().unwrap();
+            let paths = PluginPaths::in_dir(dir.path());
+            // Stop the `.totui/` lookup at the project
+            let project = paths.working_dir.clone().unwrap();
+            std::fs::create_dir_all(project.join(".git")).unwrap();
+            set_plugin_paths(Some(paths));
+            Self { dir, _lock: lock }
+        }
+
+        fn tasks(&self) -> PathBuf {
+            self.dir.path().join("tasks")
+        }
+
+        /// Host settings selecting `tasklist`, logging into the folder.
+        fn host_settings(&self, tasklist: &str) -> RHashMap<RString, FfiConfigValue> {
+            let mut config = RHashMap::new();
+            config.insert(
+                RString::from("tasklist"),
+                FfiConfigValue::String(RString::from(tasklist)),
+            );
+            let roots = vec![RString::from(self.tasks().to_string_lossy().as_ref())];
+            config.insert(
+                RString::from("task_roots"),
+                FfiConfigValue::StringArray(roots.into()),
+            );
+            let log_file = self.dir.path().join("plugin.log");
+            config.insert(
+                RString::from("log_file"),
+                FfiConfigValue::String(RString::from(log_file.to_string_lossy().as_ref())),
+            );
+            config
+        }
+
+        fn start_plugin(&self, tasklist: &str) -> ClaudeTasksPlugin {
+            self.start_plugin_with(self.host_settings(tasklist))
+        }
+
+        fn start_plugin_with(
+            &self,
+            settings: RHashMap<RString, FfiConfigValue>,
+        ) -> ClaudeTasksPlugin {
+            let plugin = ClaudeTasksPlugin::new();
+            plugin.set_notifier(fake_host::notifier());
+            plugin.on_config_loaded(settings);
+            plugin
+        }
+    }
+
+    impl Drop for TestEnv {
+        fn drop(&mut self) {
+            set_plugin_paths(None);
+        }
+    }
+
+    fn write_task(dir: &Path, id: &str, subject: &str, status: &str, blocked_by: &[&str]) {
+        std::fs::create_dir_all(dir).unwrap();
+        let task = serde_json::json!({
+            "id": id,
+            "subject": subject,
+            "description": "",
+            "activeForm": "",
+            "status": status,
+            "blocks": [],
+            "blockedBy": blocked_by,
+        });
+        std::fs::write(dir.join(format!("{}.json", id)), task.to_string()).unwrap();
+    }
+
+    fn write_owned_task(dir: &Path, id: &str, subject: &str, status: &str, owner: &str) {
+        write_task(dir, id, subject, status, &[]);
+        let path = dir.join(format!("{}.json", id));
+        let mut task: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        task["owner"] = serde_json::json!(owner);
+        std::fs::write(path, task.to_string()).unwrap();
+    }
+
+    /// Queue sync events as the file watcher would.
+    fn send_events(plugin: &ClaudeTasksPlugin, events: Vec<SyncEvent>) {
+        let tx = plugin.tx.lock().unwrap().clone().unwrap();
+        for event in events {
+            tx.send(event).unwrap();
+        }
+    }
+
+    /// Deliver one OnLoad event and apply its commands.
+    fn load(plugin: &ClaudeTasksPlugin, host: &mut FakeHost) {
+        let event = FfiEvent::OnLoad {
+            project_name: RString::from("test"),
+        };
+        let response = plugin.on_event(event).unwrap();
+        host.apply(&response.commands);
+    }
+
+    /// The todo tree without the header status (staleness label).
+    fn task_lines(host: &FakeHost) -> Vec<String> {
+        host.tree()
+            .into_iter()
+            .filter(|line| !line.contains("CLAUDE TASKLIST"))
+            .collect()
+    }
+
+    #[test]
+    fn test_lifecycle_initial_sync_and_burst() {
+        let env = TestEnv::new();
+        let tasklist = env.tasks().join("list-a");
+        write_task(&tasklist, "1", "Write tests", "in_progress", &[]);
+        write_task(&tasklist, "2", "Ship it", "pending", &["1"]);
+
+        // The initial scan is queued when syncing starts
+        let plugin = env.start_plugin("list-a");
+        let mut host = FakeHost::new();
+        load(&plugin, &mut host);
+        let tree = host.tree();
+        assert!(
+            tree[0].starts_with("[ ] CLAUDE TASKLIST: list-a"),
+            "{:?}",
+            tree
+        );
+        assert_eq!(
+            task_lines(&host),
+            vec![
+                "  [*] Write tests",
+                "  [ ] \u{1F512} Ship it (blocked by: Write tests)",
+            ]
+        );
+
+        // A burst of changes lands as one consistent tree
+        write_task(&tasklist, "1", "Write tests", "completed", &[]);
+        std::fs::remove_file(tasklist.join("2.json")).unwrap();
+        let mut events = vec![
+            SyncEvent::FileChanged(tasklist.join("1.json")),
+            SyncEvent::FileRemoved(tasklist.join("2.json")),
+        ];
+        for id in ["3", "4", "5"] {
+            write_task(&tasklist, id, &format!("Task {}", id), "pending", &[]);
+            let path = tasklist.join(format!("{}.json", id));
+            events.push(SyncEvent::FileChanged(path));
+        }
+        send_events(&plugin, events);
+        load(&plugin, &mut host);
+
+        assert_eq!(
+            task_lines(&host),
+            vec![
+                "  [x] Write tests",
+                "  [ ] Task 3",
+                "  [ ] Task 4",
+                "  [ ] Task 5",
+            ]
+        );
+        assert!(host.errors().is_empty(), "{:?}", host.errors());
+    }
+
+    #[test]
+    fn test_lifecycle_watcher_delivers_changes() {
+        let env = TestEnv::new();
+        let tasklist = env.tasks().join("list-a");
+        write_task(&tasklist, "1", "Write tests", "pending", &[]);
+
+        let plugin = env.start_plugin("list-a");
+        let mut host = FakeHost::new();
+        load(&plugin, &mut host);
+
+        // Wait until the watcher is watching, so the change isn't missed
+        let watching = || {
+            let watcher = plugin.watcher_handle.lock().unwrap();
+            watcher
+                .as_ref()
+                .is_some_and(|handle| handle.health().backend != WatcherBackend::Starting)
+        };
+        for _ in 0..100 {
+            if watching() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert!(watching(), "watcher did not start");
+
+        let wakeups = fake_host::notify_calls();
+        write_task(&tasklist, "1", "Write tests", "completed", &[]);
+        for _ in 0..100 {
+            load(&plugin, &mut host);
+            if task_lines(&host) == vec!["  [x] Write tests"] {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        assert_eq!(task_lines(&host), vec!["  [x] Write tests"]);
+        assert!(fake_host::notify_calls() > wakeups);
+    }
+
+    #[test]
+    fn test_lifecycle_restart_keeps_todos() {
+        let env = TestEnv::new();
+        let tasklist = env.tasks().join("list-a");
+        write_task(&tasklist, "1", "Write tests", "pending", &[]);
+        write_task(&tasklist, "2", "Ship it", "pending", &[]);
+
+        let mut host = FakeHost::new();
+        let plugin = env.start_plugin("list-a");
+        load(&plugin, &mut host);
+        assert_eq!(host.todos().len(), 3);
+        drop(plugin);
+
+        // Changed while totui was closed
+        write_task(&tasklist, "2", "Ship it", "completed", &[]);
+
+        // On restart the host's todos are reconciled instead of recreated
+        let plugin = env.start_plugin("list-a");
+        let commands = plugin.execute("", &host).unwrap();
+        host.apply(&commands);
+
+        assert_eq!(commands.len(), 1);
+        assert_eq!(
+            task_lines(&host),
+            vec!["  [ ] Write tests", "  [x] Ship it"]
+        );
+        assert!(host.errors().is_empty(), "{:?}", host.errors());
+    }
+
+    #[test]
+    fn test_lifecycle_restart_with_agents() {
+        let env = TestEnv::new();
+        let tasklist = env.tasks().join("list-a");
+        write_owned_task(&tasklist, "1", "Research", "in_progress", "researcher");
+        write_owned_task(&tasklist, "2", "Review", "pending", "reviewer");
+        write_task(&tasklist, "3", "Unowned", "pending", &[]);
+        let mut settings = env.host_settings("list-a");
+        settings.insert(
+            RString::from("group_by_agent"),
+            FfiConfigValue::Boolean(true),
+        );
+
+        let mut host = FakeHost::new();
+        let plugin = env.start_plugin_with(settings.clone());
+        load(&plugin, &mut host);
+        assert_eq!(host.todos().len(), 6);
+        drop(plugin);
+
+        // The reviewer's task was claimed while totui was closed
+        write_owned_task(&tasklist, "2", "Review", "in_progress", "researcher");
+
+        // On restart the agent layout is read from the host, not recreated
+        let plugin = env.start_plugin_with(settings.clone());
+        host.apply(&plugin.execute("", &host).unwrap());
+        assert_eq!(
+            task_lines(&host),
+            vec![
+                "  [ ] AGENT researcher: \u{25B6} Research",
+                "    [*] Research",
+                "    [*] Review",
+                "  [ ] Unowned",
+            ]
+        );
+        assert!(host.errors().is_empty(), "{:?}", host.errors());
+
+        // Reloading the settings rescans without duplicates
+        plugin.on_config_loaded(settings);
+        host.apply(&plugin.execute("", &host).unwrap());
+        assert_eq!(host.todos().len(), 5);
+        assert!(host.errors().is_empty(), "{:?}", host.errors());
+    }
+
+    #[test]
+    fn test_lifecycle_switch_tasklist() {
+        let env = TestEnv::new();
+        let (list_a, list_b) = (env.tasks().join("list-a"), env.tasks().join("list-b"));
+        write_task(&list_a, "1", "First list", "pending", &[]);
+        write_task(&list_b, "1", "Second list", "pending", &[]);
+
+        let mut host = FakeHost::new();
+        let plugin = env.start_plugin("list-a");
+        load(&plugin, &mut host);
+        assert_eq!(host.todos().len(), 2);
+
+        let commands = plugin.execute("switch list-b", &host).unwrap();
+        host.apply(&commands);
+
+        // A prompt switch removes the old tasklist's todos
+        let tree = host.tree();
+        assert_eq!(tree.len(), 2, "{:?}", tree);
+        assert!(tree[0].starts_with("[ ] CLAUDE TASKLIST: list-b"));
+        assert_eq!(tree[1], "  [ ] Second list");
+        assert!(host.errors().is_empty(), "{:?}", host.errors());
+
+        // Reloading unchanged settings keeps the switched tasklist
+        plugin.on_config_loaded(env.host_settings("list-a"));
+        assert_eq!(plugin.selected_tasklist_id().as_deref(), Some("list-b"));
+
+        // Choosing another tasklist in the settings still switches
+        plugin.on_config_loaded(env.host_settings("list-c"));
+        assert_eq!(plugin.selected_tasklist_id().as_deref(), Some("list-b"));
+        plugin.on_config_loaded(env.host_settings("list-a"));
+        assert_eq!(plugin.selected_tasklist_id().as_deref(), Some("list-a"));
+
+        // ... and removes the switched-to tasklist's todos as well
+        load(&plugin, &mut host);
+        load(&plugin, &mut host);
+        let tree = host.tree();
+        assert_eq!(tree.len(), 2, "{:?}", tree);
+        assert!(tree[0].starts_with("[ ] CLAUDE TASKLIST: list-a"));
+        assert_eq!(tree[1], "  [ ] First list");
+        assert!(host.errors().is_empty(), "{:?}", host.errors());
+    }
+
+    #[test]
+    fn test_lifecycle_restart_after_switch() {
+        let env = TestEnv::new();
+        let (list_a, list_b) = (env.tasks().join("list-a"), env.tasks().join("list-b"));
+        write_task(&list_a, "1", "First list", "pending", &[]);
+        write_task(&list_b, "1", "Second list", "pending", &[]);
+
+        let mut host = FakeHost::new();
+        let plugin = env.start_plugin("list-a");
+        load(&plugin, &mut host);
+        host.apply(&plugin.execute("switch list-b", &host).unwrap());
+        drop(plugin);
+
+        // The switch isn't saved: list-a is synced again, list-b's todos go
+        let plugin = env.start_plugin("list-a");
+        load(&plugin, &mut host);
+        host.apply(&plugin.execute("", &host).unwrap());
+
+        let tree = host.tree();
+        assert_eq!(tree.len(), 2, "{:?}", tree);
+        assert!(tree[0].starts_with("[ ] CLAUDE TASKLIST: list-a"));
+        assert_eq!(tree[1], "  [ ] First list");
+        assert!(host.errors().is_empty(), "{:?}", host.errors());
+    }
 }
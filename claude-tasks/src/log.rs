@@ -1,65 +1,41 @@
-//! Simple file-based logging for plugin debugging.
+//! Leveled, rotating file log for plugin debugging.
 //!
-//! Plugins don't share the host's tracing subscriber, so we use
-//! a simple file-based approach for debugging.
+//! Messages go to `{cache_dir}/claude-tasks-plugin.log` by default; see the shared
+//! `plugin-log` crate for levels, formats and rotation.
+//!
+//! Level, format and file are set from the config (see [`configure`]); the
+//! `CLAUDE_TASKS_LOG`, `CLAUDE_TASKS_LOG_FORMAT` and `CLAUDE_TASKS_LOG_FILE`
+//! environment variables override them.
 
-use std::fs::OpenOptions;
-use std::io::Write;
-use std::sync::Once;
+use std::fmt;
+use std::path::PathBuf;
 
-static INIT: Once = Once::new();
+pub use plugin_log::{Level, LogFormat, LogSettings, KEPT_LOGS, MAX_LOG_BYTES};
 
-fn log_file_path() -> std::path::PathBuf {
-    dirs::cache_dir()
-        .unwrap_or_else(|| std::path::PathBuf::from("/tmp"))
-        .join("claude-tasks-plugin.log")
-}
+static LOG: plugin_log::PluginLog = plugin_log::PluginLog::new(
+    "claude-tasks",
+    env!("CARGO_PKG_VERSION"),
+    "CLAUDE_TASKS_LOG",
+);
 
-/// Initialize the log file (truncate on first use).
-fn init_log() {
-    INIT.call_once(|| {
-        if let Ok(mut file) = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .truncate(true)
-            .open(log_file_path())
-        {
-            let _ = writeln!(file, "=== Claude Tasks Plugin Log ===");
-            let _ = writeln!(file, "Log file: {}", log_file_path().display());
-            let _ = writeln!(file, "");
-        }
-    });
+/// Apply logging settings, e.g. after the config was (re)loaded.
+pub fn configure(settings: LogSettings) {
+    LOG.configure(settings)
 }
 
-/// Log a message to the plugin log file.
-pub fn log(level: &str, message: &str) {
-    init_log();
-
-    if let Ok(mut file) = OpenOptions::new()
-        .append(true)
-        .create(true)
-        .open(log_file_path())
-    {
-        let timestamp = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .map(|d| d.as_secs())
-            .unwrap_or(0);
-        let _ = writeln!(file, "[{}] {}: {}", timestamp, level, message);
-    }
+/// Path of the log file currently written to.
+pub fn log_path() -> PathBuf {
+    LOG.log_path()
 }
 
-/// Log an info message.
-#[macro_export]
-macro_rules! plugin_info {
-    ($($arg:tt)*) => {
-        $crate::log::log("INFO", &format!($($arg)*))
-    };
+/// Whether messages at `level` are written.
+pub fn enabled(level: Level) -> bool {
+    LOG.enabled(level)
 }
 
-/// Log a debug message.
-#[macro_export]
-macro_rules! plugin_debug {
-    ($($arg:tt)*) => {
-        $crate::log::log("DEBUG", &format!($($arg)*))
-    };
+/// Log a message to the plugin log file if `level` is enabled.
+///
+/// Called by the `plugin_*!` macros.
+pub fn log(level: Level, args: fmt::Arguments) {
+    LOG.log(level, args)
 }
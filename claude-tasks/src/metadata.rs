@@ -0,0 +1,343 @@
+//! Typed metadata attached to synced todos.
+//!
+//! Every todo the plugin creates carries JSON metadata with `source:
+//! "claude-tasks"`, which is how synced todos are found again via
+//! `query_todos_by_metadata` and correlated with their tasks. The metadata
+//! is (de)serialized with serde, so values are escaped properly and lookups
+//! parse the JSON instead of matching substrings.
+//!
+//! The schema is versioned ([`METADATA_VERSION`]). Metadata written before
+//! versioning counts as version 1 and parses into the same struct; todos
+//! carrying an older version are rewritten once per session (see
+//! [`migration_commands`]). Fields unknown to this version are kept on
+//! rewrite, so newer fields (hashes, timestamps, owner) don't break
+//! correlation.
+
+use crate::commands::header_id;
+use crate::host::TodoHost;
+use abi_stable::std_types::RString;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeSet, HashSet};
+use totui_plugin_interface::{FfiCommand, FfiTodoItem};
+
+/// Value of the `source` field on every todo created by this plugin
+pub const SOURCE: &str = "claude-tasks";
+
+/// Current metadata schema version
+///
+/// - 1: unversioned; task todos have no `type`
+/// - 2: `version` field; every todo has a `type`
+pub const METADATA_VERSION: u32 = 2;
+
+/// What a synced todo stands for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TodoKind {
+    /// A Claude task (version 1 task todos have no `type`)
+    #[default]
+    Task,
+    /// The "CLAUDE TASKLIST" header
+    Header,
+    /// An agent sub-header with `group_by_agent`
+    Agent,
+    /// Guidance, status and preview todos
+    Guidance,
+}
+
+/// Metadata of a synced todo.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TodoMetadata {
+    /// Always [`SOURCE`]; used to query every synced todo
+    pub source: String,
+    /// Schema version; absent before versioning
+    #[serde(default = "unversioned")]
+    pub version: u32,
+    /// What the todo stands for
+    #[serde(rename = "type", default)]
+    pub kind: TodoKind,
+    /// UUID of the tasklist (not set on guidance)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tasklist_id: Option<String>,
+    /// ID of the task within the tasklist
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task_id: Option<String>,
+    /// Owning agent of an agent sub-header
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent: Option<String>,
+    /// IDs of the tasks blocking this one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blocked_by: Vec<String>,
+    /// Managed by the plugin, not to be edited in totui
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub read_only: bool,
+    /// Guidance shown for an error
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub error: bool,
+    /// Fields unknown to this version, kept on rewrite
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+fn unversioned() -> u32 {
+    1
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+impl TodoMetadata {
+    fn new(kind: TodoKind, tasklist_id: Option<&str>) -> Self {
+        Self {
+            source: SOURCE.to_string(),
+            version: METADATA_VERSION,
+            kind,
+            tasklist_id: tasklist_id.map(String::from),
+            task_id: None,
+            agent: None,
+            blocked_by: Vec::new(),
+            read_only: true,
+            error: false,
+            extra: Map::new(),
+        }
+    }
+
+    /// Metadata of a task todo.
+    pub fn task(tasklist_id: &str, task_id: &str, blocked_by: &[String]) -> Self {
+        Self {
+            task_id: Some(task_id.to_string()),
+            blocked_by: blocked_by.to_vec(),
+            ..Self::new(TodoKind::Task, Some(tasklist_id))
+        }
+    }
+
+    /// Metadata of a tasklist header.
+    pub fn header(tasklist_id: &str) -> Self {
+        Self::new(TodoKind::Header, Some(tasklist_id))
+    }
+
+    /// Metadata of an agent sub-header.
+    pub fn agent(tasklist_id: &str, agent: &str) -> Self {
+        Self {
+            agent: Some(agent.to_string()),
+            ..Self::new(TodoKind::Agent, Some(tasklist_id))
+        }
+    }
+
+    /// Metadata of guidance, status and preview todos.
+    pub fn guidance() -> Self {
+        Self {
+            read_only: false,
+            ..Self::new(TodoKind::Guidance, None)
+        }
+    }
+
+    /// Parse metadata JSON, if it belongs to this plugin.
+    pub fn parse(json: &str) -> Option<Self> {
+        serde_json::from_str::<Self>(json)
+            .ok()
+            .filter(|metadata| metadata.source == SOURCE)
+    }
+
+    /// Serialize to JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("metadata serializes to JSON")
+    }
+
+    /// Whether this is a task todo of `tasklist_id`.
+    pub fn is_task_of(&self, tasklist_id: &str) -> bool {
+        self.kind == TodoKind::Task && self.tasklist_id.as_deref() == Some(tasklist_id)
+    }
+
+    /// Whether the metadata predates [`METADATA_VERSION`].
+    pub fn is_outdated(&self) -> bool {
+        self.version < METADATA_VERSION
+    }
+
+    /// Bring the metadata to [`METADATA_VERSION`].
+    ///
+    /// Older versions already parse into the current layout (a missing
+    /// `type` is a task), so this only stamps the version.
+    pub fn migrate(mut self) -> Self {
+        self.version = METADATA_VERSION;
+        self
+    }
+
+    /// Create the command that sets this metadata on a todo.
+    pub fn set_command(&self, todo_id: &str) -> FfiCommand {
+        FfiCommand::SetTodoMetadata {
+            todo_id: RString::from(todo_id),
+            data: RString::from(self.to_json()),
+            merge: false,
+        }
+    }
+}
+
+/// Query all todos created by this plugin, with their parsed metadata.
+///
+/// Todos whose metadata fails to parse are skipped.
+pub fn query_synced(host: &dyn TodoHost) -> Vec<(FfiTodoItem, TodoMetadata)> {
+    let source = serde_json::to_string(SOURCE).expect("string serializes to JSON");
+    host.query_todos_by_metadata("source", &source)
+        .into_iter()
+        .filter_map(|todo| {
+            let metadata = TodoMetadata::parse(&host.get_todo_metadata(todo.id.as_str()))?;
+            Some((todo, metadata))
+        })
+        .collect()
+}
+
+/// Create the commands that migrate todos carrying older metadata versions.
+///
+/// Headers created before headers had metadata can't be queried at all; a
+/// header that synced todos still hang under is tagged too, so prune finds
+/// it once its tasks are gone.
+pub fn migration_commands(todos: &[(FfiTodoItem, TodoMetadata)]) -> Vec<FfiCommand> {
+    let mut commands: Vec<FfiCommand> = todos
+        .iter()
+        .filter(|(_, metadata)| metadata.is_outdated())
+        .map(|(todo, metadata)| metadata.clone().migrate().set_command(todo.id.as_str()))
+        .collect();
+
+    let tagged: HashSet<&str> = todos.iter().map(|(todo, _)| todo.id.as_str()).collect();
+    let untagged_headers: BTreeSet<&str> = todos
+        .iter()
+        .filter_map(|(todo, metadata)| {
+            let parent = todo.parent_id.as_ref().into_option()?;
+            let tasklist_id = metadata.tasklist_id.as_deref()?;
+            (parent.as_str() == header_id(tasklist_id) && !tagged.contains(parent.as_str()))
+                .then_some(tasklist_id)
+        })
+        .collect();
+    commands.extend(
+        untagged_headers.into_iter().map(|tasklist_id| {
+            TodoMetadata::header(tasklist_id).set_command(&header_id(tasklist_id))
+        }),
+    );
+
+    commands
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use abi_stable::std_types::ROption;
+    use totui_plugin_interface::FfiTodoState;
+
+    fn todo(id: &str) -> FfiTodoItem {
+        FfiTodoItem {
+            id: RString::from(id),
+            content: RString::from("x"),
+            state: FfiTodoState::Empty,
+            priority: ROption::RNone,
+            due_date: ROption::RNone,
+            description: ROption::RNone,
+            parent_id: ROption::RNone,
+            indent_level: 1,
+            created_at: 0,
+            modified_at: 0,
+            completed_at: ROption::RNone,
+            position: 0,
+        }
+    }
+
+    #[test]
+    fn test_task_metadata_round_trip() {
+        let metadata = TodoMetadata::task("abc", "1", &["2".to_string()]);
+        let json = metadata.to_json();
+
+        assert!(json.starts_with(r#"{"source":"claude-tasks","version":2,"type":"task""#));
+        assert_eq!(TodoMetadata::parse(&json), Some(metadata));
+    }
+
+    #[test]
+    fn test_metadata_escapes_values() {
+        let metadata = TodoMetadata::task("abc", "1\"x", &[]);
+        let parsed = TodoMetadata::parse(&metadata.to_json()).unwrap();
+        assert_eq!(parsed.task_id.as_deref(), Some("1\"x"));
+    }
+
+    #[test]
+    fn test_parse_version_1() {
+        let task = r#"{"source":"claude-tasks","tasklist_id":"abc","task_id":"1","read_only":true,"blocked_by":[]}"#;
+        let metadata = TodoMetadata::parse(task).unwrap();
+        assert_eq!(metadata.version, 1);
+        assert!(metadata.is_task_of("abc"));
+        assert!(!metadata.is_task_of("other"));
+        assert!(metadata.is_outdated());
+
+        let header =
+            r#"{"source":"claude-tasks","tasklist_id":"abc","type":"header","read_only":true}"#;
+        let metadata = TodoMetadata::parse(header).unwrap();
+        assert_eq!(metadata.kind, TodoKind::Header);
+        assert!(!metadata.is_task_of("abc"));
+    }
+
+    #[test]
+    fn test_parse_rejects_foreign_metadata() {
+        assert_eq!(TodoMetadata::parse("{}"), None);
+        assert_eq!(TodoMetadata::parse("invalid"), None);
+        assert_eq!(TodoMetadata::parse(r#"{"source":"jira"}"#), None);
+    }
+
+    #[test]
+    fn test_unknown_fields_are_kept() {
+        let json =
+            r#"{"source":"claude-tasks","version":3,"type":"task","task_id":"1","hash":"abc"}"#;
+        let metadata = TodoMetadata::parse(json).unwrap();
+        assert!(!metadata.is_outdated());
+        assert!(metadata.to_json().contains(r#""hash":"abc""#));
+    }
+
+    #[test]
+    fn test_migration_commands() {
+        let old = TodoMetadata::parse(
+            r#"{"source":"claude-tasks","tasklist_id":"abc","task_id":"1","read_only":true}"#,
+        )
+        .unwrap();
+        let todos = vec![
+            (todo("claude-abc-1"), old),
+            (todo("claude-abc-2"), TodoMetadata::task("abc", "2", &[])),
+        ];
+
+        let commands = migration_commands(&todos);
+        assert_eq!(commands.len(), 1);
+        match &commands[0] {
+            FfiCommand::SetTodoMetadata { todo_id, data, .. } => {
+                assert_eq!(todo_id.as_str(), "claude-abc-1");
+                let migrated = TodoMetadata::parse(data.as_str()).unwrap();
+                assert_eq!(migrated.version, METADATA_VERSION);
+                assert_eq!(migrated.kind, TodoKind::Task);
+                assert_eq!(migrated.task_id.as_deref(), Some("1"));
+            }
+            _ => panic!("Expected SetTodoMetadata"),
+        }
+    }
+
+    #[test]
+    fn test_migration_tags_untagged_headers() {
+        let mut task = todo("claude-abc-1");
+        task.parent_id = ROption::RSome(RString::from("claude-header-abc"));
+        let todos = vec![(task.clone(), TodoMetadata::task("abc", "1", &[]))];
+
+        let commands = migration_commands(&todos);
+        assert_eq!(commands.len(), 1);
+        match &commands[0] {
+            FfiCommand::SetTodoMetadata { todo_id, data, .. } => {
+                assert_eq!(todo_id.as_str(), "claude-header-abc");
+                let header = TodoMetadata::parse(data.as_str()).unwrap();
+                assert_eq!(header.kind, TodoKind::Header);
+                assert_eq!(header.tasklist_id.as_deref(), Some("abc"));
+            }
+            _ => panic!("Expected SetTodoMetadata"),
+        }
+
+        // Nothing to do once the header is tagged
+        let todos = vec![
+            (task, TodoMetadata::task("abc", "1", &[])),
+            (todo("claude-header-abc"), TodoMetadata::header("abc")),
+        ];
+        assert!(migration_commands(&todos).is_empty());
+    }
+}
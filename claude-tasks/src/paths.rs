@@ -0,0 +1,72 @@
+//! Folders the plugin reads and writes outside the tasklists.
+//!
+//! By default these are the user's config and cache folders and the folder
+//! totui was started in. [`set_plugin_paths`] points them elsewhere, e.g. at
+//! a temporary folder in tests, so nothing of the user's setup leaks in.
+
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+/// Folders used for config files, cached state and the current project.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PluginPaths {
+    /// Folder of the global config file, `claude-tasks.toml` (default: `~/.config/totui`)
+    pub config_dir: Option<PathBuf>,
+    /// Folder for the archive record and discovery cache (default: the user cache folder)
+    pub cache_dir: Option<PathBuf>,
+    /// Folder the current project and its `.totui/` folder are found from
+    /// (default: the working directory)
+    pub working_dir: Option<PathBuf>,
+}
+
+impl PluginPaths {
+    /// The user's folders.
+    pub fn user() -> Self {
+        Self {
+            config_dir: dirs::config_dir().map(|p| p.join("totui")),
+            cache_dir: dirs::cache_dir(),
+            working_dir: std::env::current_dir().ok(),
+        }
+    }
+
+    /// All folders inside `root`: `config/`, `cache/` and `project/`.
+    pub fn in_dir(root: &Path) -> Self {
+        Self {
+            config_dir: Some(root.join("config")),
+            cache_dir: Some(root.join("cache")),
+            working_dir: Some(root.join("project")),
+        }
+    }
+}
+
+fn overridden() -> &'static Mutex<Option<PluginPaths>> {
+    static PATHS: OnceLock<Mutex<Option<PluginPaths>>> = OnceLock::new();
+    PATHS.get_or_init(|| Mutex::new(None))
+}
+
+/// Use `paths` instead of the user's folders (`None` restores them).
+pub fn set_plugin_paths(paths: Option<PluginPaths>) {
+    *overridden().lock().unwrap_or_else(|e| e.into_inner()) = paths;
+}
+
+/// The folders currently in use.
+pub fn plugin_paths() -> PluginPaths {
+    overridden()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .unwrap_or_else(PluginPaths::user)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_in_dir_keeps_everything_inside() {
+        let paths = PluginPaths::in_dir(Path::new("/tmp/run"));
+        assert_eq!(paths.config_dir, Some(PathBuf::from("/tmp/run/config")));
+        assert_eq!(paths.cache_dir, Some(PathBuf::from("/tmp/run/cache")));
+        assert_eq!(paths.working_dir, Some(PathBuf::from("/tmp/run/project")));
+    }
+}
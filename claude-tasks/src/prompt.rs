@@ -0,0 +1,322 @@
+//! Text command language for the totui plugin prompt.
+//!
+//! `execute_with_host` receives the text typed into totui's plugin prompt.
+//! This module parses it into a [`PromptCommand`]:
+//!
+//! - `switch <alias|uuid>` - sync a different tasklist
+//! - `refresh` - rescan the selected tasklist
+//! - `alias <uuid> <name> [--global|--local]` - set a display name for a tasklist
+//! - `unalias <uuid>` - remove a tasklist's display name
+//! - `prune [--dry-run]` - remove todos of vanished tasklists
+//! - `status` - show what the plugin is doing
+//! - `archive` - convert the selected tasklist into ordinary todos
+//! - `unarchive <alias|uuid>` - sync an archived tasklist again
+//! - `repair` - check the selected tasklist against totui and fix differences
+//!
+//! Empty input is not a command; it just processes pending sync events.
+
+use crate::config::{ConfigScope, PluginConfig};
+use crate::discovery::TasklistInfo;
+
+/// Usage line listing every command, used in error messages.
+pub const USAGE: &str = "Commands: switch <alias|uuid>, refresh, \
+alias <uuid> <name> [--global|--local], unalias <uuid>, prune [--dry-run], status, archive, unarchive <alias|uuid>, repair";
+
+/// Error message for a malformed alias command.
+const ALIAS_USAGE: &str =
+    "alias needs a tasklist and a name: alias <uuid> <name> [--global|--local]";
+
+/// A command entered in the plugin prompt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PromptCommand {
+    /// Sync a different tasklist (alias, UUID or UUID prefix)
+    Switch(String),
+    /// Rescan the selected tasklist from disk
+    Refresh,
+    /// Set a display name for a tasklist (UUID or UUID prefix).
+    /// `scope` is None when no --global/--local flag was given.
+    Alias {
+        tasklist: String,
+        name: String,
+        scope: Option<ConfigScope>,
+    },
+    /// Remove a tasklist's display name (UUID, UUID prefix or alias)
+    Unalias(String),
+    /// Remove (or preview removing) todos of vanished tasklists
+    Prune { dry_run: bool },
+    /// Show plugin status
+    Status,
+    /// Archive the selected tasklist
+    Archive,
+    /// Sync an archived tasklist again (alias, UUID or UUID prefix)
+    Unarchive(String),
+    /// Check the selected tasklist for drift and repair it
+    Repair,
+}
+
+/// Parse prompt input into a command.
+///
+/// Returns `Ok(None)` for empty input and `Err` with a user-facing message
+/// for unknown commands or missing arguments. Command names are
+/// case-insensitive; arguments keep their case.
+pub fn parse_command(input: &str) -> Result<Option<PromptCommand>, String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Ok(None);
+    }
+
+    let (name, rest) = match input.split_once(char::is_whitespace) {
+        Some((name, rest)) => (name, rest.trim()),
+        None => (input, ""),
+    };
+
+    let command = match name.to_lowercase().as_str() {
+        "switch" => {
+            if rest.is_empty() {
+                return Err("switch needs a tasklist: switch <alias|uuid>".to_string());
+            }
+            PromptCommand::Switch(rest.to_string())
+        }
+        "alias" => {
+            // The name is the rest of the line verbatim, less a trailing flag
+            let (tasklist, name) = rest
+                .split_once(char::is_whitespace)
+                .ok_or_else(|| ALIAS_USAGE.to_string())?;
+            let (name, scope) = split_scope_flag(name.trim_start());
+            if name.is_empty() {
+                return Err(ALIAS_USAGE.to_string());
+            }
+            PromptCommand::Alias {
+                tasklist: tasklist.to_string(),
+                name: name.to_string(),
+                scope,
+            }
+        }
+        "unalias" => {
+            if rest.is_empty() {
+                return Err("unalias needs a tasklist: unalias <uuid>".to_string());
+            }
+            PromptCommand::Unalias(rest.to_string())
+        }
+        "unarchive" => {
+            if rest.is_empty() {
+                return Err("unarchive needs a tasklist: unarchive <alias|uuid>".to_string());
+            }
+            PromptCommand::Unarchive(rest.to_string())
+        }
+        "prune" => match rest {
+            "" => PromptCommand::Prune { dry_run: false },
+            "--dry-run" | "dry-run" => PromptCommand::Prune { dry_run: true },
+            other => {
+                return Err(format!(
+                    "Unknown prune option '{}': prune [--dry-run]",
+                    other
+                ))
+            }
+        },
+        "refresh" => no_args(PromptCommand::Refresh, "refresh", rest)?,
+        "status" => no_args(PromptCommand::Status, "status", rest)?,
+        "archive" => no_args(PromptCommand::Archive, "archive", rest)?,
+        "repair" => no_args(PromptCommand::Repair, "repair", rest)?,
+        other => return Err(format!("Unknown command '{}'. {}", other, USAGE)),
+    };
+
+    Ok(Some(command))
+}
+
+/// Reject arguments for commands that take none.
+fn no_args(command: PromptCommand, name: &str, rest: &str) -> Result<PromptCommand, String> {
+    if rest.is_empty() {
+        Ok(command)
+    } else {
+        Err(format!("{} takes no arguments", name))
+    }
+}
+
+/// Split a trailing --global/--local flag off an alias name.
+fn split_scope_flag(name: &str) -> (&str, Option<ConfigScope>) {
+    let (rest, flag) = name.rsplit_once(char::is_whitespace).unwrap_or(("", name));
+    let scope = match flag {
+        "--global" => ConfigScope::Global,
+        "--local" => ConfigScope::Local,
+        _ => return (name, None),
+    };
+    (rest.trim_end(), Some(scope))
+}
+
+/// Resolve a tasklist reference to one of the discovered tasklists.
+///
+/// Matches, in order:
+/// 1. Exact UUID
+/// 2. Alias (case-insensitive)
+/// 3. Unique UUID prefix
+pub fn resolve_tasklist<'a>(
+    query: &str,
+    tasklists: &'a [TasklistInfo],
+    config: &PluginConfig,
+) -> Result<&'a TasklistInfo, String> {
+    if let Some(t) = tasklists.iter().find(|t| t.id == query) {
+        return Ok(t);
+    }
+
+    if let Some(t) = tasklists.iter().find(|t| {
+        config
+            .get_alias(&t.id)
+            .is_some_and(|alias| alias.eq_ignore_ascii_case(query))
+    }) {
+        return Ok(t);
+    }
+
+    let matches: Vec<&TasklistInfo> = tasklists
+        .iter()
+        .filter(|t| t.id.starts_with(query))
+        .collect();
+
+    match matches.as_slice() {
+        [single] => Ok(single),
+        [] => Err(format!("No tasklist matches '{}'", query)),
+        many => Err(format!(
+            "'{}' matches {} tasklists, use more of the UUID",
+            query,
+            many.len()
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::TasklistSummary;
+    use std::path::PathBuf;
+    use std::time::SystemTime;
+
+    fn make_tasklist(id: &str) -> TasklistInfo {
+        TasklistInfo::new(
+            id.to_string(),
+            PathBuf::from("/tmp").join(id),
+            PathBuf::from("/tmp"),
+            SystemTime::now(),
+            None,
+        )
+        .with_summary(TasklistSummary::default())
+    }
+
+    #[test]
+    fn test_parse_empty_input() {
+        assert_eq!(parse_command(""), Ok(None));
+        assert_eq!(parse_command("   "), Ok(None));
+    }
+
+    #[test]
+    fn test_parse_simple_commands() {
+        assert_eq!(parse_command("refresh"), Ok(Some(PromptCommand::Refresh)));
+        assert_eq!(parse_command("STATUS"), Ok(Some(PromptCommand::Status)));
+        assert_eq!(parse_command(" archive "), Ok(Some(PromptCommand::Archive)));
+        assert_eq!(parse_command("repair"), Ok(Some(PromptCommand::Repair)));
+        assert_eq!(
+            parse_command("prune"),
+            Ok(Some(PromptCommand::Prune { dry_run: false }))
+        );
+        assert_eq!(
+            parse_command("prune --dry-run"),
+            Ok(Some(PromptCommand::Prune { dry_run: true }))
+        );
+    }
+
+    #[test]
+    fn test_parse_switch() {
+        assert_eq!(
+            parse_command("switch My Project"),
+            Ok(Some(PromptCommand::Switch("My Project".to_string())))
+        );
+        assert!(parse_command("switch")
+            .unwrap_err()
+            .contains("switch <alias|uuid>"));
+    }
+
+    #[test]
+    fn test_parse_alias() {
+        assert_eq!(
+            parse_command("alias abc123 My Project"),
+            Ok(Some(PromptCommand::Alias {
+                tasklist: "abc123".to_string(),
+                name: "My Project".to_string(),
+                scope: None,
+            }))
+        );
+        assert_eq!(
+            parse_command("alias abc123 Backend --local"),
+            Ok(Some(PromptCommand::Alias {
+                tasklist: "abc123".to_string(),
+                name: "Backend".to_string(),
+                scope: Some(ConfigScope::Local),
+            }))
+        );
+        assert_eq!(
+            parse_command("alias abc123 My  Project (v2)"),
+            Ok(Some(PromptCommand::Alias {
+                tasklist: "abc123".to_string(),
+                name: "My  Project (v2)".to_string(),
+                scope: None,
+            }))
+        );
+        assert!(parse_command("alias abc123").is_err());
+        assert!(parse_command("alias abc123 --global").is_err());
+    }
+
+    #[test]
+    fn test_parse_unalias() {
+        assert_eq!(
+            parse_command("unalias abc123"),
+            Ok(Some(PromptCommand::Unalias("abc123".to_string())))
+        );
+        assert!(parse_command("unalias").is_err());
+    }
+
+    #[test]
+    fn test_parse_unarchive() {
+        assert_eq!(
+            parse_command("unarchive abc123"),
+            Ok(Some(PromptCommand::Unarchive("abc123".to_string())))
+        );
+        assert!(parse_command("unarchive").is_err());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = parse_command("frobnicate").unwrap_err();
+        assert!(err.contains("Unknown command 'frobnicate'"));
+        assert!(err.contains(USAGE));
+
+        assert!(parse_command("refresh now").is_err());
+        assert!(parse_command("prune --force").is_err());
+    }
+
+    #[test]
+    fn test_resolve_tasklist() {
+        let tasklists = vec![make_tasklist("abc-111"), make_tasklist("abd-222")];
+        let mut config = PluginConfig::default();
+        config
+            .aliases
+            .insert("abd-222".to_string(), "Backend".to_string());
+
+        assert_eq!(
+            resolve_tasklist("abc-111", &tasklists, &config).unwrap().id,
+            "abc-111"
+        );
+        assert_eq!(
+            resolve_tasklist("backend", &tasklists, &config).unwrap().id,
+            "abd-222"
+        );
+        assert_eq!(
+            resolve_tasklist("abc", &tasklists, &config).unwrap().id,
+            "abc-111"
+        );
+        assert!(resolve_tasklist("ab", &tasklists, &config)
+            .unwrap_err()
+            .contains("matches 2 tasklists"));
+        assert!(resolve_tasklist("zzz", &tasklists, &config)
+            .unwrap_err()
+            .contains("No tasklist matches"));
+    }
+}
@@ -0,0 +1,407 @@
+//! Garbage collection for todos from vanished tasklists.
+//!
+//! Claude Code cleans up old tasklist folders, but the todos synced from them
+//! stay in totui. Pruning finds synced todos (via `source` metadata) whose
+//! `tasklist_id` no longer exists in any task root and deletes or
+//! archives them. A dry-run renders a temporary preview instead.
+
+use crate::archive::create_archive_group_commands;
+use crate::commands::{delete_todo_command, HEADER_ID_PREFIX};
+use crate::config::PruneAction;
+use crate::host::TodoHost;
+use crate::metadata::{query_synced, TodoKind, TodoMetadata};
+use abi_stable::std_types::{ROption, RString};
+use std::collections::{BTreeMap, HashSet};
+use totui_plugin_interface::{FfiCommand, FfiTodoItem, FfiTodoState};
+
+/// ID for the prune preview header todo
+pub const PRUNE_PREVIEW_HEADER_ID: &str = "claude-prune-preview";
+
+/// Synced todos belonging to a tasklist whose folder no longer exists.
+#[derive(Debug, Clone)]
+pub struct OrphanTasklist {
+    /// UUID of the vanished tasklist
+    pub tasklist_id: String,
+    /// The synced header todo, if it was found
+    pub header: Option<FfiTodoItem>,
+    /// Synced task todos (excluding the header)
+    pub todos: Vec<FfiTodoItem>,
+    /// Synced agent sub-headers
+    pub agents: Vec<FfiTodoItem>,
+}
+
+impl OrphanTasklist {
+    /// Display name taken from the header content, falling back to the UUID.
+    pub fn display_name(&self) -> String {
+        self.header
+            .as_ref()
+            .and_then(|h| h.content.as_str().strip_prefix("CLAUDE TASKLIST: "))
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| self.tasklist_id.clone())
+    }
+
+    /// Number of todos (including the headers) that would be pruned.
+    pub fn todo_count(&self) -> usize {
+        self.todos.len() + self.agents.len() + usize::from(self.header.is_some())
+    }
+}
+
+/// Find synced todos whose tasklist is not in `live_tasklist_ids`.
+///
+/// Queries all todos with `source: claude-tasks` metadata and groups them
+/// by `tasklist_id`. Todos without a tasklist_id (guidance) are ignored.
+pub fn find_orphans(host: &dyn TodoHost, live_tasklist_ids: &[String]) -> Vec<OrphanTasklist> {
+    group_orphans(query_synced(host), live_tasklist_ids)
+}
+
+/// Group `(todo, metadata)` pairs into orphaned tasklists.
+///
+/// Headers are recognized by their metadata `type` or, for headers tagged
+/// by early versions, by their ID. Results are ordered by tasklist_id for
+/// stable output.
+pub fn group_orphans(
+    entries: Vec<(FfiTodoItem, TodoMetadata)>,
+    live_tasklist_ids: &[String],
+) -> Vec<OrphanTasklist> {
+    let live: HashSet<&str> = live_tasklist_ids.iter().map(|s| s.as_str()).collect();
+    let mut groups: BTreeMap<String, OrphanTasklist> = BTreeMap::new();
+
+    for (todo, metadata) in entries {
+        let header_of = todo
+            .id
+            .as_str()
+            .strip_prefix(HEADER_ID_PREFIX)
+            .map(String::from);
+        let is_header = metadata.kind == TodoKind::Header || header_of.is_some();
+        let Some(tasklist_id) = metadata.tasklist_id.or(header_of) else {
+            continue;
+        };
+        if live.contains(tasklist_id.as_str()) {
+            continue;
+        }
+
+        let group = groups
+            .entry(tasklist_id.clone())
+            .or_insert_with(|| OrphanTasklist {
+                tasklist_id,
+                header: None,
+                todos: Vec::new(),
+                agents: Vec::new(),
+            });
+
+        if is_header {
+            group.header = Some(todo);
+        } else if metadata.kind == TodoKind::Agent {
+            group.agents.push(todo);
+        } else {
+            group.todos.push(todo);
+        }
+    }
+
+    groups.into_values().collect()
+}
+
+/// Create commands that prune the given orphans.
+///
+/// - Delete: DeleteTodo for every task todo and agent sub-header, then the
+///   header if one was found (children before their parents)
+/// - Archive: archive group (header + tasks as ordinary todos, without the
+///   agent sub-headers), then deletes
+pub fn create_prune_commands(orphans: &[OrphanTasklist], action: PruneAction) -> Vec<FfiCommand> {
+    let mut commands = Vec::new();
+
+    for orphan in orphans {
+        if action == PruneAction::Archive {
+            let title = format!("ARCHIVED CLAUDE TASKLIST: {}", orphan.display_name());
+            let entries: Vec<(String, FfiTodoState)> = orphan
+                .todos
+                .iter()
+                .map(|t| (t.content.to_string(), t.state))
+                .collect();
+            commands.extend(create_archive_group_commands(&title, &entries));
+        }
+
+        let children = orphan.todos.iter().chain(&orphan.agents);
+        commands.extend(children.map(|todo| delete_todo_command(todo.id.as_str())));
+        if let Some(header) = &orphan.header {
+            commands.push(delete_todo_command(header.id.as_str()));
+        }
+    }
+
+    commands
+}
+
+/// Create a dry-run preview of what prune would do.
+///
+/// Displays:
+/// - Header: "CLAUDE TASKS - Prune preview: N orphaned tasklists (M todos)"
+/// - Child per orphan: "{name} - M todos"
+/// - Child: "Action: invoke with `prune` to {delete|archive} them"
+///
+/// Returns the commands and the IDs of the created preview todos, so the
+/// preview can be cleared with [`crate::commands::clear_todos_commands`].
+pub fn create_prune_preview_commands(
+    orphans: &[OrphanTasklist],
+    action: PruneAction,
+) -> (Vec<FfiCommand>, Vec<String>) {
+    let mut commands = Vec::new();
+    let mut ids = vec![PRUNE_PREVIEW_HEADER_ID.to_string()];
+
+    let total: usize = orphans.iter().map(|o| o.todo_count()).sum();
+    let header_content = if orphans.is_empty() {
+        "CLAUDE TASKS - Prune preview: nothing to prune".to_string()
+    } else {
+        format!(
+            "CLAUDE TASKS - Prune preview: {} orphaned tasklists ({} todos)",
+            orphans.len(),
+            total
+        )
+    };
+
+    commands.push(FfiCommand::CreateTodo {
+        content: RString::from(header_content),
+        parent_id: ROption::RNone,
+        temp_id: ROption::RSome(RString::from(PRUNE_PREVIEW_HEADER_ID)),
+        state: FfiTodoState::Question,
+        priority: ROption::RNone,
+        indent_level: 0,
+    });
+
+    for orphan in orphans {
+        let id = format!("{}-{}", PRUNE_PREVIEW_HEADER_ID, orphan.tasklist_id);
+        commands.push(preview_child(
+            &id,
+            format!("{} - {} todos", orphan.display_name(), orphan.todo_count()),
+        ));
+        ids.push(id);
+    }
+
+    if !orphans.is_empty() {
+        let verb = match action {
+            PruneAction::Delete => "delete",
+            PruneAction::Archive => "archive",
+        };
+        let id = format!("{}-action", PRUNE_PREVIEW_HEADER_ID);
+        commands.push(preview_child(
+            &id,
+            format!("Action: invoke with `prune` to {} them", verb),
+        ));
+        ids.push(id);
+    }
+
+    commands.push(TodoMetadata::guidance().set_command(PRUNE_PREVIEW_HEADER_ID));
+
+    (commands, ids)
+}
+
+/// Create a preview child todo under the preview header.
+fn preview_child(id: &str, content: String) -> FfiCommand {
+    FfiCommand::CreateTodo {
+        content: RString::from(content),
+        parent_id: ROption::RSome(RString::from(PRUNE_PREVIEW_HEADER_ID)),
+        temp_id: ROption::RSome(RString::from(id)),
+        state: FfiTodoState::Empty,
+        priority: ROption::RNone,
+        indent_level: 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_todo(id: &str, content: &str) -> FfiTodoItem {
+        FfiTodoItem {
+            id: id.into(),
+            content: content.into(),
+            state: FfiTodoState::Empty,
+            priority: ROption::RNone,
+            due_date: ROption::RNone,
+            description: ROption::RNone,
+            parent_id: ROption::RNone,
+            indent_level: 1,
+            created_at: 0,
+            modified_at: 0,
+            completed_at: ROption::RNone,
+            position: 0,
+        }
+    }
+
+    fn task_metadata(tasklist_id: &str, task_id: &str) -> TodoMetadata {
+        TodoMetadata::task(tasklist_id, task_id, &[])
+    }
+
+    fn header_metadata(tasklist_id: &str) -> TodoMetadata {
+        TodoMetadata::header(tasklist_id)
+    }
+
+    #[test]
+    fn test_group_orphans_skips_live_tasklists() {
+        let entries = vec![
+            (
+                make_todo("claude-live-1", "Live"),
+                task_metadata("live", "1"),
+            ),
+            (
+                make_todo("claude-gone-1", "Gone"),
+                task_metadata("gone", "1"),
+            ),
+        ];
+        let orphans = group_orphans(entries, &["live".to_string()]);
+
+        assert_eq!(orphans.len(), 1);
+        assert_eq!(orphans[0].tasklist_id, "gone");
+        assert_eq!(orphans[0].todos.len(), 1);
+    }
+
+    #[test]
+    fn test_group_orphans_separates_header() {
+        let entries = vec![
+            (
+                make_todo("claude-header-gone", "CLAUDE TASKLIST: Old Project"),
+                header_metadata("gone"),
+            ),
+            (make_todo("claude-gone-1", "A"), task_metadata("gone", "1")),
+            (make_todo("claude-gone-2", "B"), task_metadata("gone", "2")),
+        ];
+        let orphans = group_orphans(entries, &[]);
+
+        assert_eq!(orphans.len(), 1);
+        assert!(orphans[0].header.is_some());
+        assert_eq!(orphans[0].todos.len(), 2);
+        assert_eq!(orphans[0].todo_count(), 3);
+        assert_eq!(orphans[0].display_name(), "Old Project");
+    }
+
+    #[test]
+    fn test_group_orphans_finds_untyped_header() {
+        let untyped = TodoMetadata::parse(r#"{"source":"claude-tasks"}"#).unwrap();
+        let entries = vec![(
+            make_todo("claude-header-gone", "CLAUDE TASKLIST: Old Project"),
+            untyped,
+        )];
+        let orphans = group_orphans(entries, &[]);
+
+        assert_eq!(orphans.len(), 1);
+        assert_eq!(orphans[0].tasklist_id, "gone");
+        assert!(orphans[0].todos.is_empty());
+        assert_eq!(orphans[0].display_name(), "Old Project");
+    }
+
+    #[test]
+    fn test_group_orphans_ignores_guidance() {
+        let entries = vec![(
+            make_todo("claude-guidance-header", "Setup"),
+            TodoMetadata::guidance(),
+        )];
+        assert!(group_orphans(entries, &[]).is_empty());
+    }
+
+    fn deleted_ids(cmds: &[FfiCommand]) -> Vec<&str> {
+        cmds.iter()
+            .filter_map(|c| match c {
+                FfiCommand::DeleteTodo { id } => Some(id.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Orphaned tasklist "gone": header first, then an agent and its task.
+    fn orphan_with_agent() -> Vec<OrphanTasklist> {
+        group_orphans(
+            vec![
+                (
+                    make_todo("claude-header-gone", "CLAUDE TASKLIST: gone"),
+                    header_metadata("gone"),
+                ),
+                (
+                    make_todo("claude-agent-gone-tester", "tester"),
+                    TodoMetadata::agent("gone", "tester"),
+                ),
+                (make_todo("claude-gone-1", "A"), task_metadata("gone", "1")),
+            ],
+            &[],
+        )
+    }
+
+    #[test]
+    fn test_create_prune_commands_delete() {
+        let orphans = orphan_with_agent();
+        assert_eq!(orphans[0].todo_count(), 3);
+        let cmds = create_prune_commands(&orphans, PruneAction::Delete);
+
+        // Tasks and agent sub-headers go before the header
+        assert_eq!(
+            deleted_ids(&cmds),
+            vec![
+                "claude-gone-1",
+                "claude-agent-gone-tester",
+                "claude-header-gone"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_create_prune_commands_without_header() {
+        let orphans = group_orphans(
+            vec![(make_todo("claude-gone-1", "A"), task_metadata("gone", "1"))],
+            &[],
+        );
+        let cmds = create_prune_commands(&orphans, PruneAction::Delete);
+
+        // The header wasn't found, so it isn't deleted
+        assert_eq!(deleted_ids(&cmds), vec!["claude-gone-1"]);
+    }
+
+    #[test]
+    fn test_create_prune_commands_archive() {
+        let orphans = orphan_with_agent();
+        let cmds = create_prune_commands(&orphans, PruneAction::Archive);
+
+        // archive header + 1 archived task (no agent) + 3 deletes
+        assert_eq!(cmds.len(), 5);
+        assert_eq!(deleted_ids(&cmds).len(), 3);
+        match &cmds[0] {
+            FfiCommand::CreateTodo { content, .. } => {
+                assert_eq!(content.as_str(), "ARCHIVED CLAUDE TASKLIST: gone");
+            }
+            _ => panic!("Expected CreateTodo command"),
+        }
+    }
+
+    #[test]
+    fn test_create_prune_preview_commands() {
+        let orphans = group_orphans(
+            vec![(make_todo("claude-gone-1", "A"), task_metadata("gone", "1"))],
+            &[],
+        );
+        let (cmds, ids) = create_prune_preview_commands(&orphans, PruneAction::Delete);
+
+        // header + orphan + action + metadata
+        assert_eq!(cmds.len(), 4);
+        assert_eq!(ids.len(), 3);
+        assert_eq!(ids[0], PRUNE_PREVIEW_HEADER_ID);
+        match &cmds[0] {
+            FfiCommand::CreateTodo { content, .. } => {
+                assert!(content.as_str().contains("1 orphaned tasklists (1 todos)"));
+            }
+            _ => panic!("Expected CreateTodo command"),
+        }
+        // Preview never deletes anything
+        assert!(!cmds
+            .iter()
+            .any(|c| matches!(c, FfiCommand::DeleteTodo { .. })));
+    }
+
+    #[test]
+    fn test_create_prune_preview_nothing_to_prune() {
+        let (cmds, ids) = create_prune_preview_commands(&[], PruneAction::Delete);
+        assert_eq!(ids, vec![PRUNE_PREVIEW_HEADER_ID.to_string()]);
+        match &cmds[0] {
+            FfiCommand::CreateTodo { content, .. } => {
+                assert!(content.as_str().contains("nothing to prune"));
+            }
+            _ => panic!("Expected CreateTodo command"),
+        }
+    }
+}
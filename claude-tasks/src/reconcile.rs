@@ -0,0 +1,803 @@
+//! Reconciliation between the tasks of a tasklist and their todos in totui.
+//!
+//! Every sync goes through [`reconcile`]: it compares the desired tasks with
+//! a [`Snapshot`] of the synced todos and returns the [`Plan`] that brings
+//! totui in line. The snapshot is queried via `query_todos_by_metadata` when
+//! host access is available (`execute_with_host`), and built from the
+//! locally tracked `known_tasks` otherwise (`on_event`). A task only known
+//! locally is assumed to have a todo with unknown content, so it is always
+//! updated when in scope.
+//!
+//! With `group_by_agent` ([`Layout::ByAgent`]) the engine also places each
+//! task under its owner's sub-header: sub-headers are created, relabelled
+//! and removed with the tasks, and a task whose owner changed is recreated
+//! under its new agent, since todos can't be moved.
+//!
+//! The engine is pure: reading task files and querying the host are left to
+//! [`crate::sync`] and [`crate::drift`].
+
+use crate::agents::{agent_label, AgentLayout};
+use crate::claude_task::{map_status_to_state, ClaudeTask};
+use crate::commands::{
+    agent_header_id, create_agent_header_commands, create_header_command,
+    create_todo_commands_with_hierarchy, delete_todo_command, format_hierarchy_content, header_id,
+    header_metadata_command, task_todo_id, update_agent_header_command, update_todo_command,
+};
+use crate::hierarchy::{build_hierarchy, TaskHierarchy};
+use crate::metadata::{TodoKind, TodoMetadata};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use totui_plugin_interface::{FfiCommand, FfiTodoItem, FfiTodoState};
+
+/// A synced todo as observed in a [`Snapshot`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObservedTodo {
+    /// Todo ID in totui
+    pub id: String,
+    /// Content and state, if read from the host
+    pub seen: Option<(String, FfiTodoState)>,
+    /// Agent whose sub-header the todo is nested under
+    pub agent: Option<String>,
+}
+
+/// The synced todos of a tasklist as far as they are known.
+#[derive(Debug, Clone, Default)]
+pub struct Snapshot {
+    /// Whether the tasklist header todo exists
+    pub header: bool,
+    /// Labels of the agent sub-headers, by agent
+    pub agents: HashMap<String, String>,
+    /// Task todos by task ID
+    pub todos: HashMap<String, ObservedTodo>,
+}
+
+impl Snapshot {
+    /// Snapshot of the `(todo, metadata)` pairs of a tasklist.
+    ///
+    /// Task todos are placed under the agent sub-header that is their parent.
+    pub fn from_todos(todos: &[(FfiTodoItem, TodoMetadata)], tasklist_id: &str) -> Self {
+        let hdr_id = header_id(tasklist_id);
+        let header = todos.iter().any(|(todo, _)| todo.id.as_str() == hdr_id);
+
+        let mut agents = HashMap::new();
+        let mut agent_ids = HashMap::new();
+        for (todo, metadata) in todos {
+            let Some(agent) = &metadata.agent else {
+                continue;
+            };
+            if metadata.kind == TodoKind::Agent {
+                agents.insert(agent.clone(), todo.content.to_string());
+                agent_ids.insert(todo.id.to_string(), agent.clone());
+            }
+        }
+
+        let todos = todos
+            .iter()
+            .filter(|(_, metadata)| metadata.is_task_of(tasklist_id))
+            .filter_map(|(todo, metadata)| {
+                let task_id = metadata.task_id.clone()?;
+                let parent_id = todo.parent_id.as_ref().map(|id| id.as_str()).into_option();
+                let observed = ObservedTodo {
+                    id: todo.id.to_string(),
+                    seen: Some((todo.content.to_string(), todo.state)),
+                    agent: parent_id.and_then(|id| agent_ids.get(id).cloned()),
+                };
+                Some((task_id, observed))
+            })
+            .collect();
+        Self {
+            header,
+            agents,
+            todos,
+        }
+    }
+
+    /// Snapshot of the locally known tasks, using the predictable todo IDs.
+    ///
+    /// The header is assumed to exist once tasks are tracked; agent
+    /// sub-headers and placements are taken from `layout`.
+    pub fn from_known(known: &HashSet<String>, layout: &AgentLayout, tasklist_id: &str) -> Self {
+        let todos = known
+            .iter()
+            .map(|task_id| {
+                let observed = ObservedTodo {
+                    id: task_todo_id(tasklist_id, task_id),
+                    seen: None,
+                    agent: layout.placements.get(task_id).cloned(),
+                };
+                (task_id.clone(), observed)
+            })
+            .collect();
+        Self {
+            header: true,
+            agents: layout.labels.clone().into_iter().collect(),
+            todos,
+        }
+    }
+
+    /// Leave tasks out, so they are neither updated nor deleted.
+    pub fn without<I: IntoIterator<Item = String>>(mut self, task_ids: I) -> Self {
+        for task_id in task_ids {
+            self.todos.remove(&task_id);
+        }
+        self
+    }
+}
+
+/// The tasks a reconciliation may touch.
+#[derive(Debug, Clone)]
+pub enum Scope {
+    /// The whole tasklist; todos without a task are deleted
+    All,
+    /// Only these task IDs (e.g., the files of a change batch)
+    Only(HashSet<String>),
+}
+
+impl Scope {
+    fn contains(&self, task_id: &str) -> bool {
+        match self {
+            Scope::All => true,
+            Scope::Only(ids) => ids.contains(task_id),
+        }
+    }
+}
+
+/// How the tasks of a tasklist are laid out below its header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    /// Every task directly under the tasklist header
+    Flat,
+    /// Owned tasks under one sub-header per agent (`group_by_agent`)
+    ByAgent,
+}
+
+/// Where a task todo is created.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Placement {
+    /// Todo ID of the tasklist header or agent sub-header
+    pub parent_id: String,
+    /// Indent level below the parent
+    pub indent_level: u32,
+}
+
+impl Placement {
+    fn new(tasklist_id: &str, agent: Option<&str>) -> Self {
+        match agent {
+            Some(agent) => Self {
+                parent_id: agent_header_id(tasklist_id, agent),
+                indent_level: 2,
+            },
+            None => Self {
+                parent_id: header_id(tasklist_id),
+                indent_level: 1,
+            },
+        }
+    }
+}
+
+/// The changes that bring totui in line with the desired tasks.
+#[derive(Debug, Default)]
+pub struct Plan {
+    /// The tasklist header todo has to be created
+    pub header_missing: bool,
+    /// Agent sub-headers to create, as `(agent, label)`
+    pub agents_missing: Vec<(String, String)>,
+    /// Agent sub-headers to relabel, as `(agent, label)`
+    pub agents_outdated: Vec<(String, String)>,
+    /// Agent sub-headers without tasks, sorted by agent
+    pub agents_orphaned: Vec<String>,
+    /// Tasks without a todo, in task order
+    pub missing: Vec<(ClaudeTask, Placement)>,
+    /// Tasks under the wrong parent, with the todo ID to recreate
+    pub moved: Vec<(ClaudeTask, String, Placement)>,
+    /// Tasks whose todo is out of date, with the todo ID
+    pub outdated: Vec<(ClaudeTask, String)>,
+    /// Todos without a task, as `(task_id, todo_id)` sorted by task ID
+    pub orphaned: Vec<(String, String)>,
+    /// Agent sub-headers and placements once the plan is applied
+    pub layout: AgentLayout,
+    hierarchy: TaskHierarchy,
+}
+
+impl Plan {
+    /// Whether nothing needs to change.
+    pub fn is_empty(&self) -> bool {
+        !self.header_missing
+            && self.agents_missing.is_empty()
+            && self.agents_outdated.is_empty()
+            && self.agents_orphaned.is_empty()
+            && self.missing.is_empty()
+            && self.moved.is_empty()
+            && self.outdated.is_empty()
+            && self.orphaned.is_empty()
+    }
+
+    /// One-line description, e.g. "2 missing, 1 orphaned".
+    pub fn summary(&self) -> String {
+        if self.is_empty() {
+            return "in sync".to_string();
+        }
+
+        let mut parts = Vec::new();
+        if self.header_missing {
+            parts.push("header missing".to_string());
+        }
+        let counts = [
+            (self.agents_missing.len(), "agent headers missing"),
+            (self.missing.len(), "missing"),
+            (self.moved.len(), "moved"),
+            (self.outdated.len(), "out of date"),
+            (self.agents_outdated.len(), "agent headers out of date"),
+            (self.orphaned.len(), "orphaned"),
+            (self.agents_orphaned.len(), "agent headers orphaned"),
+        ];
+        for (count, what) in counts {
+            if count > 0 {
+                parts.push(format!("{} {}", count, what));
+            }
+        }
+        parts.join(", ")
+    }
+
+    /// Build the commands, in order: headers, creates, updates, deletes.
+    ///
+    /// The header (shown as `display_name`) and agent sub-headers come first
+    /// so new todos have a parent; they are created with their hierarchy
+    /// annotations. Moved tasks are deleted and recreated, and agent
+    /// sub-headers are removed last, after their tasks.
+    pub fn commands(&self, tasklist_id: &str, display_name: Option<&str>) -> Vec<FfiCommand> {
+        let mut commands = Vec::new();
+
+        if self.header_missing {
+            commands.push(create_header_command(tasklist_id, display_name));
+            commands.push(header_metadata_command(tasklist_id));
+        }
+        for (agent, label) in &self.agents_missing {
+            commands.extend(create_agent_header_commands(tasklist_id, agent, label));
+        }
+        for (agent, label) in &self.agents_outdated {
+            commands.push(update_agent_header_command(tasklist_id, agent, label));
+        }
+        for (task, todo_id, placement) in &self.moved {
+            commands.push(delete_todo_command(todo_id));
+            commands.extend(self.create_commands(task, tasklist_id, placement));
+        }
+        for (task, placement) in &self.missing {
+            commands.extend(self.create_commands(task, tasklist_id, placement));
+        }
+        for (task, todo_id) in &self.outdated {
+            commands.push(update_todo_command(task, todo_id));
+        }
+        for (_, todo_id) in &self.orphaned {
+            commands.push(delete_todo_command(todo_id));
+        }
+        for agent in &self.agents_orphaned {
+            commands.push(delete_todo_command(&agent_header_id(tasklist_id, agent)));
+        }
+
+        commands
+    }
+
+    fn create_commands(
+        &self,
+        task: &ClaudeTask,
+        tasklist_id: &str,
+        placement: &Placement,
+    ) -> Vec<FfiCommand> {
+        create_todo_commands_with_hierarchy(
+            task,
+            tasklist_id,
+            &placement.parent_id,
+            placement.indent_level,
+            &self.hierarchy,
+        )
+    }
+}
+
+/// Reconcile the desired tasks of a tasklist with a snapshot of its todos.
+///
+/// `desired` is the whole tasklist, so blocked annotations and agent labels
+/// see every task; only tasks in `scope` are created, moved, updated or
+/// deleted. Shown agent sub-headers are relabelled from the whole tasklist,
+/// but a missing one is only created for an in-scope task, and one is only
+/// removed once no todo is left under it. Todos out of scope keep their
+/// placement. Unchanged todos are left alone.
+pub fn reconcile(
+    tasklist_id: &str,
+    desired: &[ClaudeTask],
+    snapshot: &Snapshot,
+    scope: &Scope,
+    layout: Layout,
+) -> Plan {
+    let hierarchy = build_hierarchy(desired);
+    let owner = |task: &ClaudeTask| match layout {
+        Layout::ByAgent => task.owner.clone(),
+        Layout::Flat => None,
+    };
+    let mut plan = Plan {
+        header_missing: !snapshot.header,
+        ..Default::default()
+    };
+
+    let mut by_agent: BTreeMap<String, Vec<&ClaudeTask>> = BTreeMap::new();
+    let mut scoped_agents = HashSet::new();
+    for task in desired {
+        if let Some(agent) = owner(task) {
+            if scope.contains(&task.id) {
+                plan.layout
+                    .placements
+                    .insert(task.id.clone(), agent.clone());
+                scoped_agents.insert(agent.clone());
+            }
+            by_agent.entry(agent).or_default().push(task);
+        }
+    }
+    for (task_id, todo) in &snapshot.todos {
+        if let Some(agent) = todo.agent.as_ref().filter(|_| !scope.contains(task_id)) {
+            plan.layout
+                .placements
+                .insert(task_id.clone(), agent.clone());
+        }
+    }
+    for (agent, tasks) in by_agent {
+        let label = agent_label(&agent, &tasks);
+        match snapshot.agents.get(&agent) {
+            // Created with its first in-scope task
+            None if !scoped_agents.contains(&agent) => continue,
+            None => plan.agents_missing.push((agent.clone(), label.clone())),
+            Some(shown) if *shown != label => {
+                plan.agents_outdated.push((agent.clone(), label.clone()))
+            }
+            Some(_) => {}
+        }
+        plan.layout.labels.insert(agent, label);
+    }
+
+    for task in desired.iter().filter(|task| scope.contains(&task.id)) {
+        let agent = owner(task);
+        match snapshot.todos.get(&task.id) {
+            None => {
+                let placement = Placement::new(tasklist_id, agent.as_deref());
+                plan.missing.push((task.clone(), placement));
+            }
+            Some(todo) if todo.agent != agent => {
+                let placement = Placement::new(tasklist_id, agent.as_deref());
+                plan.moved.push((task.clone(), todo.id.clone(), placement));
+            }
+            Some(todo) if needs_update(task, todo, &hierarchy) => {
+                plan.outdated.push((task.clone(), todo.id.clone()));
+            }
+            Some(_) => {}
+        }
+    }
+
+    let wanted: HashSet<&str> = desired.iter().map(|task| task.id.as_str()).collect();
+    plan.orphaned = snapshot
+        .todos
+        .iter()
+        .filter(|(task_id, _)| scope.contains(task_id) && !wanted.contains(task_id.as_str()))
+        .map(|(task_id, todo)| (task_id.clone(), todo.id.clone()))
+        .collect();
+    plan.orphaned.sort();
+
+    // Todos that stay where they are keep their agent sub-header
+    let leaving: HashSet<&str> = plan
+        .moved
+        .iter()
+        .map(|(task, _, _)| task.id.as_str())
+        .chain(plan.orphaned.iter().map(|(task_id, _)| task_id.as_str()))
+        .collect();
+    let occupied: HashSet<&str> = snapshot
+        .todos
+        .iter()
+        .filter(|(task_id, _)| !leaving.contains(task_id.as_str()))
+        .filter_map(|(_, todo)| todo.agent.as_deref())
+        .collect();
+    plan.agents_orphaned = snapshot
+        .agents
+        .keys()
+        .filter(|agent| !plan.layout.labels.contains_key(*agent))
+        .filter(|agent| !occupied.contains(agent.as_str()))
+        .cloned()
+        .collect();
+    plan.agents_orphaned.sort();
+
+    // Sub-headers that stay keep their label
+    for (agent, label) in &snapshot.agents {
+        if !plan.agents_orphaned.contains(agent) {
+            plan.layout
+                .labels
+                .entry(agent.clone())
+                .or_insert_with(|| label.clone());
+        }
+    }
+
+    plan.hierarchy = hierarchy;
+    plan
+}
+
+/// Check if a todo needs to be updated to match its task.
+///
+/// Compares state and content; the content may be the subject (as set on
+/// update) or the annotated subject (as set on create). A todo whose
+/// content wasn't observed is always updated.
+pub fn needs_update(task: &ClaudeTask, todo: &ObservedTodo, hierarchy: &TaskHierarchy) -> bool {
+    let Some((content, state)) = &todo.seen else {
+        return true;
+    };
+
+    if *state != map_status_to_state(&task.status) {
+        return true;
+    }
+
+    *content != task.subject && *content != format_hierarchy_content(task, hierarchy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use abi_stable::std_types::ROption;
+
+    fn task(id: &str, subject: &str, status: &str) -> ClaudeTask {
+        ClaudeTask {
+            id: id.to_string(),
+            subject: subject.to_string(),
+            description: String::new(),
+            active_form: String::new(),
+            status: status.to_string(),
+            blocks: vec![],
+            blocked_by: vec![],
+            owner: None,
+        }
+    }
+
+    fn blocked_task(id: &str, subject: &str, blocked_by: &str) -> ClaudeTask {
+        ClaudeTask {
+            blocked_by: vec![blocked_by.to_string()],
+            ..task(id, subject, "pending")
+        }
+    }
+
+    fn owned(id: &str, status: &str, owner: &str) -> ClaudeTask {
+        ClaudeTask {
+            active_form: format!("Doing {}", id),
+            owner: Some(owner.to_string()),
+            ..task(id, &format!("Task {}", id), status)
+        }
+    }
+
+    fn seen(id: &str, content: &str, state: FfiTodoState) -> ObservedTodo {
+        ObservedTodo {
+            id: id.to_string(),
+            seen: Some((content.to_string(), state)),
+            agent: None,
+        }
+    }
+
+    fn snapshot(todos: Vec<(&str, ObservedTodo)>) -> Snapshot {
+        Snapshot {
+            header: true,
+            agents: HashMap::new(),
+            todos: todos
+                .into_iter()
+                .map(|(task_id, todo)| (task_id.to_string(), todo))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_needs_update() {
+        let hierarchy = TaskHierarchy::default();
+        let pending = task("1", "Test task", "pending");
+
+        let same = seen("t", "Test task", FfiTodoState::Empty);
+        assert!(!needs_update(&pending, &same, &hierarchy));
+
+        let state_changed = seen("t", "Test task", FfiTodoState::Checked);
+        assert!(needs_update(&pending, &state_changed, &hierarchy));
+
+        let content_changed = seen("t", "Old task", FfiTodoState::Empty);
+        assert!(needs_update(&pending, &content_changed, &hierarchy));
+
+        let working = task("1", "Working", "in_progress");
+        let in_progress = seen("t", "Working", FfiTodoState::InProgress);
+        assert!(!needs_update(&working, &in_progress, &hierarchy));
+        let not_started = seen("t", "Working", FfiTodoState::Empty);
+        assert!(needs_update(&working, &not_started, &hierarchy));
+
+        let unseen = ObservedTodo {
+            id: "t".to_string(),
+            seen: None,
+            agent: None,
+        };
+        assert!(needs_update(&pending, &unseen, &hierarchy));
+    }
+
+    #[test]
+    fn test_needs_update_allows_annotation() {
+        let tasks = vec![
+            task("1", "First", "pending"),
+            blocked_task("2", "Second", "1"),
+        ];
+        let hierarchy = build_hierarchy(&tasks);
+        let annotated = format_hierarchy_content(&tasks[1], &hierarchy);
+        assert_ne!(annotated, "Second");
+
+        let created = seen("t", &annotated, FfiTodoState::Empty);
+        assert!(!needs_update(&tasks[1], &created, &hierarchy));
+        let updated = seen("t", "Second", FfiTodoState::Empty);
+        assert!(!needs_update(&tasks[1], &updated, &hierarchy));
+    }
+
+    #[test]
+    fn test_reconcile_empty_snapshot_creates_everything() {
+        let tasks = vec![
+            task("1", "First", "pending"),
+            blocked_task("2", "Second", "1"),
+        ];
+
+        let plan = reconcile(
+            "tl",
+            &tasks,
+            &Snapshot::default(),
+            &Scope::All,
+            Layout::Flat,
+        );
+        assert!(plan.header_missing);
+        assert_eq!(plan.missing.len(), 2);
+
+        let commands = plan.commands("tl", Some("My Project"));
+        // header + metadata, 2 x (create + metadata)
+        assert_eq!(commands.len(), 6);
+        assert!(matches!(
+            &commands[0],
+            FfiCommand::CreateTodo { content, .. } if content.as_str() == "CLAUDE TASKLIST: My Project"
+        ));
+        // Creates carry hierarchy annotations
+        assert!(matches!(
+            &commands[4],
+            FfiCommand::CreateTodo { content, .. } if content.as_str().starts_with('\u{1F512}')
+        ));
+    }
+
+    #[test]
+    fn test_reconcile_orders_creates_updates_deletes() {
+        let tasks = vec![
+            task("1", "Changed", "completed"),
+            task("2", "Same", "pending"),
+            task("3", "New", "pending"),
+        ];
+        let snap = snapshot(vec![
+            ("1", seen("claude-tl-1", "Old", FfiTodoState::Empty)),
+            ("2", seen("claude-tl-2", "Same", FfiTodoState::Empty)),
+            ("9", seen("claude-tl-9", "Gone", FfiTodoState::Empty)),
+        ]);
+
+        let plan = reconcile("tl", &tasks, &snap, &Scope::All, Layout::Flat);
+        assert_eq!(plan.summary(), "1 missing, 1 out of date, 1 orphaned");
+
+        let commands = plan.commands("tl", None);
+        assert_eq!(commands.len(), 4);
+        assert!(matches!(&commands[0], FfiCommand::CreateTodo { .. }));
+        assert!(matches!(&commands[1], FfiCommand::SetTodoMetadata { .. }));
+        assert!(
+            matches!(&commands[2], FfiCommand::UpdateTodo { id, .. } if id.as_str() == "claude-tl-1")
+        );
+        assert!(
+            matches!(&commands[3], FfiCommand::DeleteTodo { id } if id.as_str() == "claude-tl-9")
+        );
+    }
+
+    #[test]
+    fn test_reconcile_scope_limits_changes() {
+        let tasks = vec![
+            task("1", "First", "pending"),
+            task("2", "Second", "pending"),
+        ];
+        let known: HashSet<String> = ["1".to_string(), "3".to_string(), "4".to_string()].into();
+        let snap = Snapshot::from_known(&known, &AgentLayout::default(), "tl");
+
+        // Task 2 changed, task 3 was removed; 1 and 4 are untouched
+        let scope = Scope::Only(["2".to_string(), "3".to_string()].into());
+        let plan = reconcile("tl", &tasks, &snap, &scope, Layout::Flat);
+
+        assert!(!plan.header_missing);
+        assert_eq!(plan.missing.len(), 1);
+        assert_eq!(plan.missing[0].0.id, "2");
+        assert!(plan.outdated.is_empty());
+        assert_eq!(
+            plan.orphaned,
+            vec![("3".to_string(), task_todo_id("tl", "3"))]
+        );
+    }
+
+    #[test]
+    fn test_snapshot_without_keeps_todos() {
+        let known: HashSet<String> = ["1".to_string()].into();
+        let snap =
+            Snapshot::from_known(&known, &AgentLayout::default(), "tl").without(["1".to_string()]);
+
+        let plan = reconcile("tl", &[], &snap, &Scope::All, Layout::Flat);
+        assert!(plan.is_empty());
+    }
+
+    fn created_under(commands: &[FfiCommand]) -> Vec<(String, String, u32)> {
+        commands
+            .iter()
+            .filter_map(|c| match c {
+                FfiCommand::CreateTodo {
+                    temp_id: ROption::RSome(id),
+                    parent_id: ROption::RSome(parent),
+                    indent_level,
+                    ..
+                } => Some((id.to_string(), parent.to_string(), *indent_level)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_reconcile_nests_by_agent() {
+        let tasks = vec![
+            owned("1", "in_progress", "researcher"),
+            task("2", "Unowned", "pending"),
+        ];
+
+        let plan = reconcile(
+            "tl",
+            &tasks,
+            &Snapshot::default(),
+            &Scope::All,
+            Layout::ByAgent,
+        );
+        assert_eq!(
+            plan.missing[0].1,
+            Placement {
+                parent_id: "claude-agent-tl-researcher".to_string(),
+                indent_level: 2,
+            }
+        );
+
+        let commands = plan.commands("tl", None);
+        assert_eq!(
+            created_under(&commands),
+            vec![
+                (
+                    "claude-agent-tl-researcher".to_string(),
+                    "claude-header-tl".to_string(),
+                    1
+                ),
+                (
+                    "claude-tl-1".to_string(),
+                    "claude-agent-tl-researcher".to_string(),
+                    2
+                ),
+                ("claude-tl-2".to_string(), "claude-header-tl".to_string(), 1),
+            ]
+        );
+        assert_eq!(
+            plan.layout.labels["researcher"],
+            "AGENT researcher: \u{25B6} Doing 1"
+        );
+        assert_eq!(
+            plan.layout.header_ids("tl"),
+            vec!["claude-agent-tl-researcher"]
+        );
+    }
+
+    #[test]
+    fn test_reconcile_moves_claimed_task() {
+        let first = vec![owned("1", "pending", "old")];
+        let plan = reconcile(
+            "tl",
+            &first,
+            &Snapshot::default(),
+            &Scope::All,
+            Layout::ByAgent,
+        );
+        let known: HashSet<String> = ["1".to_string()].into();
+        let snap = Snapshot::from_known(&known, &plan.layout, "tl");
+
+        // Task 1 is claimed by another agent
+        let tasks = vec![owned("1", "in_progress", "new")];
+        let scope = Scope::Only(known.clone());
+        let plan = reconcile("tl", &tasks, &snap, &scope, Layout::ByAgent);
+        assert_eq!(
+            plan.summary(),
+            "1 agent headers missing, 1 moved, 1 agent headers orphaned"
+        );
+
+        // create agent + metadata, delete task, create task + metadata, delete old agent
+        let commands = plan.commands("tl", None);
+        assert_eq!(commands.len(), 6);
+        assert!(
+            matches!(&commands[2], FfiCommand::DeleteTodo { id } if id.as_str() == "claude-tl-1")
+        );
+        assert_eq!(
+            created_under(&commands[3..4]),
+            vec![(
+                "claude-tl-1".to_string(),
+                "claude-agent-tl-new".to_string(),
+                2
+            )]
+        );
+        assert!(
+            matches!(&commands[5], FfiCommand::DeleteTodo { id } if id.as_str() == "claude-agent-tl-old")
+        );
+        assert_eq!(plan.layout.labels.len(), 1);
+    }
+
+    #[test]
+    fn test_reconcile_relabels_agent_of_changed_task() {
+        let first = vec![owned("1", "in_progress", "a"), owned("2", "pending", "a")];
+        let plan = reconcile(
+            "tl",
+            &first,
+            &Snapshot::default(),
+            &Scope::All,
+            Layout::ByAgent,
+        );
+        let known: HashSet<String> = ["1".to_string(), "2".to_string()].into();
+        let snap = Snapshot::from_known(&known, &plan.layout, "tl");
+
+        // Task 1 completes; task 2 is untouched
+        let tasks = vec![owned("1", "completed", "a"), owned("2", "pending", "a")];
+        let scope = Scope::Only(["1".to_string()].into());
+        let commands = reconcile("tl", &tasks, &snap, &scope, Layout::ByAgent).commands("tl", None);
+
+        assert_eq!(commands.len(), 2);
+        assert!(matches!(
+            &commands[0],
+            FfiCommand::UpdateTodo { id, content: ROption::RSome(c), .. }
+                if id.as_str() == "claude-agent-tl-a" && c.as_str() == "AGENT a: idle"
+        ));
+        assert!(
+            matches!(&commands[1], FfiCommand::UpdateTodo { id, .. } if id.as_str() == "claude-tl-1")
+        );
+    }
+
+    #[test]
+    fn test_agent_kept_while_todos_remain() {
+        // An agent's last task was dropped by the owner, but the batch
+        // doesn't cover task 2, which still sits under the sub-header
+        let mut snap = snapshot(vec![(
+            "2",
+            ObservedTodo {
+                agent: Some("a".to_string()),
+                ..seen("claude-tl-2", "Task 2", FfiTodoState::Empty)
+            },
+        )]);
+        snap.agents
+            .insert("a".to_string(), "AGENT a: idle".to_string());
+        let tasks = vec![task("2", "Task 2", "pending")];
+
+        let scope = Scope::Only(HashSet::new());
+        let plan = reconcile("tl", &tasks, &snap, &scope, Layout::ByAgent);
+        assert!(plan.agents_orphaned.is_empty());
+        assert_eq!(plan.layout.placements["2"], "a");
+        assert!(plan.layout.labels.contains_key("a"));
+
+        let plan = reconcile("tl", &tasks, &snap, &Scope::All, Layout::ByAgent);
+        assert_eq!(plan.moved.len(), 1);
+        assert_eq!(plan.agents_orphaned, vec!["a".to_string()]);
+    }
+
+    #[test]
+    fn test_agent_of_out_of_scope_task_not_created() {
+        // Task 2's file failed to parse when it was first seen, so neither
+        // it nor its agent's sub-header is shown yet
+        let known: HashSet<String> = ["1".to_string()].into();
+        let snap = Snapshot::from_known(&known, &AgentLayout::default(), "tl");
+        let tasks = vec![task("1", "Task 1", "pending"), owned("2", "pending", "b")];
+
+        let scope = Scope::Only(["1".to_string()].into());
+        let plan = reconcile("tl", &tasks, &snap, &scope, Layout::ByAgent);
+        assert!(plan.agents_missing.is_empty());
+        assert!(plan.layout.labels.is_empty());
+        assert!(plan.layout.placements.is_empty());
+
+        let scope = Scope::Only(["2".to_string()].into());
+        let plan = reconcile("tl", &tasks, &snap, &scope, Layout::ByAgent);
+        assert_eq!(plan.agents_missing.len(), 1);
+        assert_eq!(plan.layout.placements["2"], "b");
+    }
+}
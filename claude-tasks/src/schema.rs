@@ -0,0 +1,373 @@
+//! Config schema offered to totui and conversion of the values it sends back.
+//!
+//! Besides the `tasklist` selection, every [`PluginConfig`] option is
+//! described so it can be edited from totui's plugin settings. Host values
+//! are the lowest-precedence config source: the TOML files override them
+//! (see [`crate::config`]), and the overridden fields name the file.
+
+use crate::config::{is_config_key, merge_overrides, PluginConfig};
+use crate::staleness::StalenessThresholds;
+use abi_stable::std_types::{RHashMap, ROption, RString, RVec};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use totui_plugin_interface::{FfiConfigField, FfiConfigSchema, FfiConfigType, FfiConfigValue};
+
+/// Name of the tasklist selection field.
+pub const TASKLIST_FIELD: &str = "tasklist";
+
+/// Build the config schema.
+///
+/// `tasklist_options` are the picker entries for the tasklist field, each
+/// formatted as "display_string|uuid". `sources` are the config files
+/// overriding settings (see [`crate::config::ConfigLoad::sources`]).
+pub fn config_schema(
+    tasklist_options: RVec<RString>,
+    sources: &HashMap<&'static str, PathBuf>,
+) -> FfiConfigSchema {
+    let tasklist_field = FfiConfigField {
+        name: RString::from(TASKLIST_FIELD),
+        field_type: FfiConfigType::Select,
+        required: false, // Will auto-select first if not specified
+        default: ROption::RNone,
+        description: ROption::RSome(RString::from("Select which Claude tasklist to sync")),
+        options: tasklist_options,
+    };
+
+    let mut fields = vec![tasklist_field];
+    fields.extend(
+        setting_fields()
+            .into_iter()
+            .map(|field| note_source(field, sources)),
+    );
+
+    FfiConfigSchema {
+        fields: fields.into_iter().collect(),
+        config_required: false,
+    }
+}
+
+/// Fields for the [`PluginConfig`] options, in
+/// [`CONFIG_KEYS`](crate::config::CONFIG_KEYS) order.
+fn setting_fields() -> Vec<FfiConfigField> {
+    use FfiConfigType::{Boolean, Integer, Select, StringArray};
+    let off = Some(FfiConfigValue::Boolean(false));
+    let thresholds = StalenessThresholds::default();
+    let minutes = |value: u64| Some(FfiConfigValue::Integer(value as i64));
+
+    vec![
+        field(
+            "aliases",
+            StringArray,
+            None,
+            "Display names for tasklists, as uuid=name entries",
+            &[],
+        ),
+        field(
+            "idle_threshold_minutes",
+            Integer,
+            minutes(thresholds.idle_minutes),
+            "Minutes without updates before the tasklist is marked idle",
+            &[],
+        ),
+        field(
+            "staleness_threshold_minutes",
+            Integer,
+            minutes(thresholds.stale_minutes),
+            "Minutes without updates before the tasklist is marked stale",
+            &[],
+        ),
+        field(
+            "abandoned_threshold_minutes",
+            Integer,
+            minutes(thresholds.abandoned_minutes),
+            "Minutes without updates before the tasklist is marked abandoned",
+            &[],
+        ),
+        field(
+            "archive_on_complete",
+            Boolean,
+            off.clone(),
+            "Archive the tasklist once every task is completed",
+            &[],
+        ),
+        field(
+            "archive_on_stale",
+            Boolean,
+            off.clone(),
+            "Archive the tasklist once it goes stale",
+            &[],
+        ),
+        field(
+            "prune_action",
+            Select,
+            Some(string_value("delete")),
+            "What prune does with todos of vanished tasklists",
+            &["delete", "archive"],
+        ),
+        field(
+            "prune_on_first_invocation",
+            Select,
+            Some(string_value("off")),
+            "Prune todos of vanished tasklists on the first invocation",
+            &["off", "preview", "apply"],
+        ),
+        field(
+            "current_project_only",
+            Boolean,
+            off.clone(),
+            "Only list tasklists of the current project in the picker",
+            &[],
+        ),
+        field(
+            "discovery_disk_cache",
+            Boolean,
+            off.clone(),
+            "Keep the tasklist discovery cache on disk between runs",
+            &[],
+        ),
+        field(
+            "task_roots",
+            StringArray,
+            None,
+            "Folders containing Claude tasklists (default: $CLAUDE_CONFIG_DIR/tasks or ~/.claude/tasks)",
+            &[],
+        ),
+        field(
+            "watch_mode",
+            Select,
+            Some(string_value("native")),
+            "Watch tasklists with native file system events or by polling",
+            &["native", "poll"],
+        ),
+        field(
+            "group_by_agent",
+            Boolean,
+            off,
+            "Nest tasks under one sub-header per owning agent",
+            &[],
+        ),
+        field(
+            "log_level",
+            Select,
+            Some(string_value("info")),
+            "Most verbose messages written to the plugin log",
+            &["off", "error", "warn", "info", "debug", "trace"],
+        ),
+        field(
+            "log_format",
+            Select,
+            Some(string_value("text")),
+            "Write the plugin log as text or as JSON lines",
+            &["text", "json"],
+        ),
+        field(
+            "log_file",
+            FfiConfigType::String,
+            None,
+            "Plugin log file (default: claude-tasks-plugin.log in the user cache directory)",
+            &[],
+        ),
+    ]
+}
+
+/// Build an optional config field.
+fn field(
+    name: &str,
+    field_type: FfiConfigType,
+    default: Option<FfiConfigValue>,
+    description: &str,
+    options: &[&str],
+) -> FfiConfigField {
+    FfiConfigField {
+        name: RString::from(name),
+        field_type,
+        required: false,
+        default: default.into(),
+        description: ROption::RSome(RString::from(description)),
+        options: options.iter().map(|o| RString::from(*o)).collect(),
+    }
+}
+
+/// Point a field's description at the config file overriding it.
+fn note_source(
+    mut field: FfiConfigField,
+    sources: &HashMap<&'static str, PathBuf>,
+) -> FfiConfigField {
+    if let (Some(path), ROption::RSome(description)) =
+        (sources.get(field.name.as_str()), &field.description)
+    {
+        let noted = format!("{} (overridden by {})", description, path.display());
+        field.description = ROption::RSome(RString::from(noted));
+    }
+    field
+}
+
+fn string_value(value: &str) -> FfiConfigValue {
+    FfiConfigValue::String(RString::from(value))
+}
+
+/// Convert the values sent by the host into a [`PluginConfig`].
+///
+/// Values are checked one at a time, so an invalid value only drops that
+/// setting. Empty strings count as unset. Returns the config and a warning
+/// per value that was dropped.
+pub fn host_config(values: &RHashMap<RString, FfiConfigValue>) -> (PluginConfig, Vec<String>) {
+    let mut config = PluginConfig::default();
+    let mut warnings = Vec::new();
+
+    let mut entries: Vec<_> = values.iter().map(|tuple| (tuple.0, tuple.1)).collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+
+    for (name, value) in entries {
+        let name = name.as_str();
+        if name == TASKLIST_FIELD {
+            continue;
+        }
+        if !is_config_key(name) {
+            warnings.push(format!("host config: unknown key `{}`", name));
+            continue;
+        }
+
+        let value = match (name, value) {
+            (_, FfiConfigValue::String(s)) if s.is_empty() => continue,
+            ("aliases", FfiConfigValue::StringArray(entries)) => match alias_table(entries) {
+                Ok(table) => toml::Value::Table(table),
+                Err(entry) => {
+                    warnings.push(format!(
+                        "host config: alias `{}` is not of the form uuid=name",
+                        entry
+                    ));
+                    continue;
+                }
+            },
+            (_, value) => toml_value(value),
+        };
+
+        let mut table = toml::Table::new();
+        table.insert(name.to_string(), value);
+        match toml::Value::Table(table).try_into::<PluginConfig>() {
+            Ok(setting) => merge_overrides(&mut config, setting),
+            Err(e) => warnings.push(format!("host config: `{}`: {}", name, e.message())),
+        }
+    }
+
+    (config, warnings)
+}
+
+/// Parse uuid=name alias entries; returns the first malformed entry on error.
+fn alias_table(entries: &RVec<RString>) -> Result<toml::Table, String> {
+    entries
+        .iter()
+        .map(|entry| match entry.split_once('=') {
+            Some((uuid, name)) if !uuid.trim().is_empty() => Ok((
+                uuid.trim().to_string(),
+                toml::Value::String(name.trim().to_string()),
+            )),
+            _ => Err(entry.to_string()),
+        })
+        .collect()
+}
+
+fn toml_value(value: &FfiConfigValue) -> toml::Value {
+    match value {
+        FfiConfigValue::String(s) => toml::Value::String(s.to_string()),
+        FfiConfigValue::Integer(i) => toml::Value::Integer(*i),
+        FfiConfigValue::Boolean(b) => toml::Value::Boolean(*b),
+        FfiConfigValue::StringArray(items) => toml::Value::Array(
+            items
+                .iter()
+                .map(|s| toml::Value::String(s.to_string()))
+                .collect(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{PruneAction, WatchMode, CONFIG_KEYS};
+
+    fn values(entries: Vec<(&str, FfiConfigValue)>) -> RHashMap<RString, FfiConfigValue> {
+        entries
+            .into_iter()
+            .map(|(k, v)| (RString::from(k), v))
+            .collect()
+    }
+
+    #[test]
+    fn test_schema_covers_every_option() {
+        let schema = config_schema(RVec::new(), &HashMap::new());
+        let names: Vec<&str> = schema.fields.iter().map(|f| f.name.as_str()).collect();
+
+        assert_eq!(names[0], TASKLIST_FIELD);
+        assert_eq!(&names[1..], CONFIG_KEYS);
+        assert!(schema.fields.iter().all(|f| !f.required));
+        assert!(schema
+            .fields
+            .iter()
+            .all(|f| matches!(f.description, ROption::RSome(_))));
+    }
+
+    #[test]
+    fn test_schema_names_overriding_file() {
+        let sources = HashMap::from([("log_level", PathBuf::from("/etc/claude-tasks.toml"))]);
+        let schema = config_schema(RVec::new(), &sources);
+        let description = |name: &str| {
+            let field = schema.fields.iter().find(|f| f.name == name).unwrap();
+            match &field.description {
+                ROption::RSome(description) => description.to_string(),
+                ROption::RNone => String::new(),
+            }
+        };
+
+        assert!(description("log_level").ends_with("(overridden by /etc/claude-tasks.toml)"));
+        assert!(!description("log_format").contains("overridden"));
+    }
+
+    #[test]
+    fn test_select_defaults_are_options() {
+        for field in setting_fields() {
+            if let (FfiConfigType::Select, ROption::RSome(FfiConfigValue::String(default))) =
+                (&field.field_type, &field.default)
+            {
+                assert!(field.options.contains(default), "{}", field.name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_host_config_converts_values() {
+        let aliases = vec![RString::from("abc = My Project")];
+        let (config, warnings) = host_config(&values(vec![
+            ("tasklist", string_value("abc")),
+            ("staleness_threshold_minutes", FfiConfigValue::Integer(30)),
+            ("group_by_agent", FfiConfigValue::Boolean(true)),
+            ("prune_action", string_value("archive")),
+            ("watch_mode", string_value("")),
+            ("aliases", FfiConfigValue::StringArray(aliases.into())),
+        ]));
+
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert_eq!(config.staleness_threshold(), 30);
+        assert!(config.group_by_agent());
+        assert_eq!(config.prune_action(), PruneAction::Archive);
+        assert_eq!(config.watch_mode, None);
+        assert_eq!(config.get_alias("abc"), Some("My Project"));
+    }
+
+    #[test]
+    fn test_host_config_drops_invalid_values() {
+        let (config, warnings) = host_config(&values(vec![
+            ("staleness_threshold_minutes", FfiConfigValue::Integer(-5)),
+            ("watch_mode", string_value("poll")),
+            ("prune_on_first_invocation", string_value("sometimes")),
+            ("colour", string_value("blue")),
+        ]));
+
+        assert_eq!(config.staleness_threshold_minutes, None);
+        assert_eq!(config.prune_on_first_invocation, None);
+        assert_eq!(config.watch_mode(), WatchMode::Poll);
+        assert_eq!(warnings.len(), 3);
+        assert!(warnings.iter().any(|w| w.contains("`colour`")));
+    }
+}
@@ -0,0 +1,95 @@
+//! Detection of ended Claude Code sessions.
+//!
+//! Staleness only guesses from inactivity whether a session is over. Where
+//! the platform exposes process information (`/proc` on Linux), the running
+//! Claude Code processes are checked instead: a session whose working
+//! directory no Claude Code process runs in has ended. Elsewhere nothing is
+//! known and the staleness levels apply as usual.
+//!
+//! Matching by working directory is a heuristic, and it can be wrong:
+//! - The cwd is the first one recorded in the session transcript. A process
+//!   running elsewhere (started from a subdirectory of that folder, or
+//!   moved to another directory since) makes a live session look ended
+//! - Another Claude Code session in the same directory keeps an ended
+//!   session looking alive
+
+use std::path::{Path, PathBuf};
+
+/// Check whether a Claude Code process runs in the session directory `cwd`.
+///
+/// Returns None if running processes can't be inspected on this platform.
+pub fn session_running(cwd: &Path) -> Option<bool> {
+    claude_process_cwds().map(|cwds| cwds.iter().any(|c| c == cwd))
+}
+
+/// Working directories of the running Claude Code processes.
+#[cfg(target_os = "linux")]
+fn claude_process_cwds() -> Option<Vec<PathBuf>> {
+    let entries = std::fs::read_dir("/proc").ok()?;
+
+    let cwds = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.bytes().all(|b| b.is_ascii_digit()))
+        })
+        .filter(|path| {
+            std::fs::read(path.join("cmdline")).is_ok_and(|cmdline| is_claude_cmdline(&cmdline))
+        })
+        // Processes of other users can't be read; they aren't our sessions
+        .filter_map(|path| std::fs::read_link(path.join("cwd")).ok())
+        .collect();
+
+    Some(cwds)
+}
+
+/// Working directories of the running Claude Code processes.
+#[cfg(not(target_os = "linux"))]
+fn claude_process_cwds() -> Option<Vec<PathBuf>> {
+    None
+}
+
+/// Whether a NUL-separated command line runs Claude Code.
+///
+/// Matches the `claude` executable itself and `node` running the `claude`
+/// script or the npm package's `cli.js`.
+fn is_claude_cmdline(cmdline: &[u8]) -> bool {
+    let mut args = cmdline.split(|b| *b == 0).map(String::from_utf8_lossy);
+    let Some(program) = args.next() else {
+        return false;
+    };
+    if is_claude_program(&program) {
+        return true;
+    }
+
+    let runs_node = Path::new(program.as_ref())
+        .file_name()
+        .is_some_and(|n| n.to_string_lossy().starts_with("node"));
+    runs_node && args.next().is_some_and(|script| is_claude_program(&script))
+}
+
+/// Whether a program or script path is Claude Code.
+fn is_claude_program(arg: &str) -> bool {
+    let path = Path::new(arg);
+    path.file_name().is_some_and(|n| n == "claude")
+        || (path.ends_with("cli.js") && arg.contains("claude-code"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_claude_cmdline() {
+        assert!(is_claude_cmdline(b"claude\0--resume\0abc\0"));
+        assert!(is_claude_cmdline(b"node\0/usr/local/bin/claude\0"));
+        assert!(is_claude_cmdline(
+            b"node\0/usr/lib/node_modules/@anthropic-ai/claude-code/cli.js\0"
+        ));
+        assert!(!is_claude_cmdline(b"vim\0claude\0"));
+        assert!(!is_claude_cmdline(b"bash\0-c\0echo claude\0"));
+        assert!(!is_claude_cmdline(b""));
+    }
+}
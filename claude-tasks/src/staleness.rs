@@ -1,45 +1,181 @@
 //! Staleness tracking for tasklist updates.
 //!
-//! Tracks time since last update and provides human-readable staleness display.
-//! Default threshold: 15 minutes without updates triggers stale state.
+//! Tracks time since the tasklist last changed and grades it into levels: active, idle,
+//! stale and abandoned. Default thresholds: idle after 5 minutes, stale
+//! after 15 minutes and abandoned after a day without updates.
+//!
+//! The time of the last change is taken from the task files' modification
+//! times when known, so a tasklist untouched for days is reported as such
+//! right after a restart. Otherwise the time updates were received is used.
+//!
+//! Inactivity only suggests a session is over; when the Claude Code process
+//! is known to have exited (see [`crate::session`]), the tracker reports the
+//! session as ended instead.
+
+use std::time::{Duration, Instant, SystemTime};
+use totui_plugin_interface::FfiTodoState;
 
-use std::time::{Duration, Instant};
+/// Minutes without updates after which an incomplete tasklist counts as abandoned.
+pub const ABANDONED_THRESHOLD_MINUTES: u64 = 24 * 60;
+
+/// How quiet a tasklist is, from most to least active.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum StalenessLevel {
+    /// Updated recently
+    Active,
+    /// Quiet for a short while
+    Idle,
+    /// Quiet long enough that the session may be stuck or over
+    Stale,
+    /// Quiet so long the session was most likely abandoned
+    Abandoned,
+}
+
+/// Minutes without updates at which each level starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StalenessThresholds {
+    /// Minutes until idle (default: 5)
+    pub idle_minutes: u64,
+    /// Minutes until stale (default: 15)
+    pub stale_minutes: u64,
+    /// Minutes until abandoned (default: 1 day)
+    pub abandoned_minutes: u64,
+}
+
+impl Default for StalenessThresholds {
+    fn default() -> Self {
+        Self {
+            idle_minutes: 5,
+            stale_minutes: 15,
+            abandoned_minutes: ABANDONED_THRESHOLD_MINUTES,
+        }
+    }
+}
+
+impl StalenessThresholds {
+    /// Grade a quiet period; the highest level whose threshold is exceeded wins.
+    pub fn level(&self, quiet: Duration) -> StalenessLevel {
+        let exceeds = |minutes: u64| quiet > Duration::from_secs(minutes * 60);
+        if exceeds(self.abandoned_minutes) {
+            StalenessLevel::Abandoned
+        } else if exceeds(self.stale_minutes) {
+            StalenessLevel::Stale
+        } else if exceeds(self.idle_minutes) {
+            StalenessLevel::Idle
+        } else {
+            StalenessLevel::Active
+        }
+    }
+}
+
+/// What the tasklist header shows about its activity.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeaderStatus {
+    /// Quiet for `duration` (formatted) at an idle, stale or abandoned level
+    Quiet {
+        level: StalenessLevel,
+        duration: String,
+    },
+    /// The Claude Code session owning the tasklist has exited
+    SessionEnded,
+}
+
+impl HeaderStatus {
+    /// Suffix appended to the header content.
+    pub fn label(&self) -> String {
+        match self {
+            HeaderStatus::Quiet { level, duration } => match level {
+                StalenessLevel::Active | StalenessLevel::Idle => format!("idle ({})", duration),
+                StalenessLevel::Stale => format!("\u{23F0} STALE ({})", duration),
+                StalenessLevel::Abandoned => format!("\u{1F4A4} ABANDONED ({})", duration),
+            },
+            HeaderStatus::SessionEnded => "\u{23F9} SESSION ENDED".to_string(),
+        }
+    }
+
+    /// Header todo state: Exclamation once stale or abandoned.
+    pub fn todo_state(&self) -> FfiTodoState {
+        match self {
+            HeaderStatus::Quiet { level, .. } if *level >= StalenessLevel::Stale => {
+                FfiTodoState::Exclamation
+            }
+            _ => FfiTodoState::Empty,
+        }
+    }
+}
 
 /// Tracks staleness of a tasklist.
 #[derive(Debug)]
 pub struct StalenessTracker {
     /// Time of last recorded update
     last_update: Option<Instant>,
-    /// Threshold for staleness (default: 15 minutes)
-    threshold: Duration,
+    /// Time the tasklist last changed on disk, if known
+    last_activity: Option<SystemTime>,
+    /// Thresholds for the staleness levels
+    thresholds: StalenessThresholds,
+    /// Whether the session's Claude Code process is known to have exited
+    session_ended: bool,
 }
 
 impl StalenessTracker {
-    /// Create a new tracker with the given threshold in minutes.
+    /// Create a new tracker with the given stale threshold in minutes and
+    /// default idle and abandoned thresholds.
     pub fn new(threshold_minutes: u64) -> Self {
+        Self::with_thresholds(StalenessThresholds {
+            stale_minutes: threshold_minutes,
+            ..Default::default()
+        })
+    }
+
+    /// Create a new tracker with the given thresholds.
+    pub fn with_thresholds(thresholds: StalenessThresholds) -> Self {
         Self {
             last_update: None,
-            threshold: Duration::from_secs(threshold_minutes * 60),
+            last_activity: None,
+            thresholds,
+            session_ended: false,
         }
     }
 
+    /// Change the thresholds, keeping the time of the last update.
+    pub fn set_thresholds(&mut self, thresholds: StalenessThresholds) {
+        self.thresholds = thresholds;
+    }
+
     /// Record that an update was received.
+    ///
+    /// An update means the session is alive, so it is no longer ended.
     pub fn record_update(&mut self) {
         self.last_update = Some(Instant::now());
+        self.session_ended = false;
+    }
+
+    /// Record when the tasklist last changed on disk.
+    ///
+    /// Staleness is measured from this time from now on. It never moves
+    /// back, so removing the newest task file doesn't age the tasklist.
+    pub fn set_last_activity(&mut self, time: SystemTime) {
+        self.last_activity = Some(self.last_activity.map_or(time, |last| last.max(time)));
+    }
+
+    /// Record whether the session's Claude Code process has exited.
+    pub fn set_session_ended(&mut self, ended: bool) {
+        self.session_ended = ended;
+    }
+
+    /// Get the current staleness level (active until the first update).
+    pub fn level(&self) -> StalenessLevel {
+        self.time_since_update()
+            .map_or(StalenessLevel::Active, |quiet| self.thresholds.level(quiet))
     }
 
     /// Check if the tasklist is stale.
     ///
-    /// Returns None if not stale (or no updates yet), Some(duration) if stale.
+    /// Returns None if not stale (or no updates yet), Some(duration) if
+    /// stale or abandoned.
     pub fn check_staleness(&self) -> Option<Duration> {
-        self.last_update.and_then(|instant| {
-            let elapsed = instant.elapsed();
-            if elapsed > self.threshold {
-                Some(elapsed)
-            } else {
-                None
-            }
-        })
+        self.time_since_update()
+            .filter(|_| self.level() >= StalenessLevel::Stale)
     }
 
     /// Check if we're currently tracking (have received at least one update).
@@ -47,22 +183,47 @@ impl StalenessTracker {
         self.last_update.is_some()
     }
 
-    /// Format staleness duration for display.
+    /// Get what the header should show, if anything.
     ///
-    /// Returns None if not stale, Some("Xm") or Some("XhYm") if stale.
+    /// None while active (or before the first update).
+    pub fn header_status(&self) -> Option<HeaderStatus> {
+        let quiet = self.time_since_update()?;
+        if self.session_ended {
+            return Some(HeaderStatus::SessionEnded);
+        }
+        match self.thresholds.level(quiet) {
+            StalenessLevel::Active => None,
+            level => Some(HeaderStatus::Quiet {
+                level,
+                duration: format_duration(quiet),
+            }),
+        }
+    }
+
+    /// Format the header status for display.
+    ///
+    /// Returns None while active, otherwise the label, e.g. "⏰ STALE (23m)".
     pub fn format_staleness(&self) -> Option<String> {
-        self.check_staleness().map(format_duration)
+        self.header_status().map(|status| status.label())
     }
 
     /// Get time since last update.
+    ///
+    /// Measured from the last change on disk if known, otherwise from the
+    /// last recorded update. None until the first update.
     pub fn time_since_update(&self) -> Option<Duration> {
-        self.last_update.map(|instant| instant.elapsed())
+        self.last_update?;
+        match self.last_activity {
+            // A modification time in the future (clock skew) counts as now
+            Some(time) => Some(time.elapsed().unwrap_or_default()),
+            None => self.last_update.map(|instant| instant.elapsed()),
+        }
     }
 }
 
 impl Default for StalenessTracker {
     fn default() -> Self {
-        Self::new(15) // 15 minutes default
+        Self::with_thresholds(StalenessThresholds::default())
     }
 }
 
@@ -140,7 +301,67 @@ mod tests {
     #[test]
     fn test_default_threshold() {
         let tracker = StalenessTracker::default();
-        assert_eq!(tracker.threshold, Duration::from_secs(15 * 60));
+        assert_eq!(tracker.thresholds.stale_minutes, 15);
+    }
+
+    #[test]
+    fn test_set_thresholds_keeps_last_update() {
+        let mut tracker = StalenessTracker::new(15);
+        tracker.record_update();
+        thread::sleep(Duration::from_millis(10));
+        assert!(tracker.check_staleness().is_none());
+
+        tracker.set_thresholds(StalenessThresholds {
+            stale_minutes: 0,
+            ..Default::default()
+        });
+        assert!(tracker.check_staleness().is_some());
+        assert_eq!(tracker.thresholds.stale_minutes, 0);
+    }
+
+    #[test]
+    fn test_levels() {
+        let thresholds = StalenessThresholds::default();
+        let minutes = |m: u64| Duration::from_secs(m * 60);
+
+        assert_eq!(thresholds.level(minutes(1)), StalenessLevel::Active);
+        assert_eq!(thresholds.level(minutes(6)), StalenessLevel::Idle);
+        assert_eq!(thresholds.level(minutes(20)), StalenessLevel::Stale);
+        assert_eq!(
+            thresholds.level(minutes(25 * 60)),
+            StalenessLevel::Abandoned
+        );
+    }
+
+    #[test]
+    fn test_header_status_labels_and_state() {
+        let quiet = |level| HeaderStatus::Quiet {
+            level,
+            duration: "23m".to_string(),
+        };
+
+        assert_eq!(quiet(StalenessLevel::Idle).label(), "idle (23m)");
+        assert_eq!(quiet(StalenessLevel::Stale).label(), "\u{23F0} STALE (23m)");
+        assert!(matches!(
+            quiet(StalenessLevel::Abandoned).todo_state(),
+            FfiTodoState::Exclamation
+        ));
+        assert!(matches!(
+            quiet(StalenessLevel::Idle).todo_state(),
+            FfiTodoState::Empty
+        ));
+        assert!(HeaderStatus::SessionEnded.label().contains("SESSION ENDED"));
+    }
+
+    #[test]
+    fn test_session_ended_until_next_update() {
+        let mut tracker = StalenessTracker::new(15);
+        tracker.record_update();
+        tracker.set_session_ended(true);
+        assert_eq!(tracker.header_status(), Some(HeaderStatus::SessionEnded));
+
+        tracker.record_update();
+        assert_eq!(tracker.header_status(), None);
     }
 
     #[test]
@@ -158,6 +379,24 @@ mod tests {
         assert!(tracker.format_staleness().is_some());
     }
 
+    #[test]
+    fn test_last_activity_sets_staleness() {
+        let mut tracker = StalenessTracker::new(15);
+        let two_hours_ago = SystemTime::now() - Duration::from_secs(2 * 60 * 60);
+        tracker.set_last_activity(two_hours_ago);
+        tracker.record_update();
+
+        assert_eq!(tracker.level(), StalenessLevel::Stale);
+        assert_eq!(tracker.format_staleness().unwrap(), "\u{23F0} STALE (2h)");
+
+        // Older modification times are ignored
+        tracker.set_last_activity(two_hours_ago - Duration::from_secs(60 * 60));
+        assert_eq!(tracker.format_staleness().unwrap(), "\u{23F0} STALE (2h)");
+
+        tracker.set_last_activity(SystemTime::now());
+        assert_eq!(tracker.level(), StalenessLevel::Active);
+    }
+
     #[test]
     fn test_time_since_update() {
         let mut tracker = StalenessTracker::new(15);
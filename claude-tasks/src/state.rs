@@ -4,11 +4,15 @@
 //! SyncEvent represents events from the file watcher thread.
 //! GuidanceState tracks what guidance UI is currently displayed.
 
+use crate::agents::AgentLayout;
+use crate::commands::{header_id, task_todo_id};
 use crate::config::PluginConfig;
+use crate::discovery::{self, tasklist_id_from_path};
 use crate::staleness::StalenessTracker;
 use std::collections::HashSet;
 use std::path::PathBuf;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime};
 use totui_plugin_interface::FfiCommand;
 
 /// Events from the file watcher thread.
@@ -22,6 +26,8 @@ pub enum SyncEvent {
     FileRemoved(PathBuf),
     /// Initial scan complete (sent after watching starts)
     InitialScan,
+    /// A config file was created, changed or removed
+    ConfigChanged,
 }
 
 /// Current guidance state for UX flow.
@@ -38,6 +44,36 @@ pub enum GuidanceState {
     EmptyTasklist,
     /// Error occurred with recovery guidance shown
     Error,
+    /// A config file failed to parse
+    InvalidConfig,
+    /// The selected tasklist is archived
+    Archived,
+}
+
+/// Sync counters for the status report, since the plugin was loaded.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncStats {
+    /// Events received from the watchers
+    pub events: u64,
+    /// Commands returned to the host
+    pub commands: u64,
+    /// When the last event was received
+    pub last_event: Option<SystemTime>,
+}
+
+impl SyncStats {
+    /// Count a batch of received events.
+    pub fn record_events(&mut self, count: usize) {
+        if count > 0 {
+            self.events += count as u64;
+            self.last_event = Some(SystemTime::now());
+        }
+    }
+
+    /// Count commands returned to the host.
+    pub fn record_commands(&mut self, count: usize) {
+        self.commands += count as u64;
+    }
 }
 
 /// Shared state for the sync engine.
@@ -47,15 +83,26 @@ pub enum GuidanceState {
 pub struct SyncState {
     /// Path to the currently selected tasklist folder (e.g., ~/.claude/tasks/{uuid}/)
     pub selected_tasklist: Option<PathBuf>,
+    /// Tasklist chosen in totui's plugin settings; differs from the selected
+    /// tasklist after a `switch` prompt command
+    pub configured_tasklist_id: Option<String>,
     /// ID of the header todo for this tasklist (for updates)
     pub header_todo_id: Option<String>,
     /// Set of task IDs that have been synced to totui.
     /// Used to determine if we should create vs update without querying HostApi.
     pub known_tasks: HashSet<String>,
+    /// Agent sub-headers and task placements (with group_by_agent)
+    pub agent_layout: AgentLayout,
     /// Plugin configuration (aliases, staleness threshold)
     pub config: PluginConfig,
+    /// Name derived from the Claude project of the selected tasklist
+    pub project_name: Option<String>,
+    /// Working directory of the selected tasklist's Claude session, if known
+    pub session_cwd: Option<PathBuf>,
     /// Staleness tracker for detecting stale tasklists
     pub staleness_tracker: StalenessTracker,
+    /// Staleness text the header currently shows (None when fresh)
+    pub shown_staleness: Option<String>,
     /// Current guidance state for UX flow
     pub guidance_state: GuidanceState,
     /// Whether guidance todos are currently displayed
@@ -63,9 +110,67 @@ pub struct SyncState {
     /// Commands to return on next on_event call.
     /// Used for guidance commands created during on_config_loaded.
     pub pending_commands: Vec<FfiCommand>,
+    /// IDs of the prune preview todos currently displayed (cleared on next prune)
+    pub prune_preview_ids: Vec<String>,
+    /// Whether the first-invocation prune (prune_on_first_invocation) has run
+    pub first_invocation_prune_done: bool,
+    /// Whether todos with older metadata versions have been migrated
+    pub metadata_migration_done: bool,
+    /// Whether todos left over from an earlier session's `switch` were removed
+    pub leftover_cleanup_done: bool,
+    /// IDs of the status report todos currently displayed (cleared on next status)
+    pub status_ids: Vec<String>,
+    /// Event and command counters for the status report
+    pub stats: SyncStats,
+    /// When the last consistency check ran and what it found
+    pub drift_check: Option<(Instant, String)>,
 }
 
 impl SyncState {
+    /// Get the ID of the selected tasklist (folder name or legacy file stem).
+    pub fn selected_tasklist_id(&self) -> Option<String> {
+        self.selected_tasklist
+            .as_deref()
+            .and_then(tasklist_id_from_path)
+            .map(String::from)
+    }
+
+    /// Get the header display name for a tasklist.
+    ///
+    /// Manual aliases take precedence over the derived project name. The
+    /// project of a tasklist other than the selected one is looked up in
+    /// the configured task roots.
+    pub fn display_name(&self, tasklist_id: &str) -> Option<String> {
+        if let Some(alias) = self.config.get_alias(tasklist_id) {
+            return Some(alias.to_string());
+        }
+        if self.selected_tasklist_id().as_deref() == Some(tasklist_id) {
+            return self.project_name.clone();
+        }
+        let roots = self.config.task_roots();
+        discovery::find_tasklist_project(&roots, tasklist_id).map(|project| project.name())
+    }
+
+    /// IDs of the selected tasklist's todos as tracked locally: the header,
+    /// then agent sub-headers, then tasks (see
+    /// [`crate::commands::clear_todos_commands`]).
+    pub fn synced_todo_ids(&self) -> Vec<String> {
+        let Some(tasklist_id) = self.selected_tasklist_id() else {
+            return Vec::new();
+        };
+        let mut task_ids: Vec<&String> = self.known_tasks.iter().collect();
+        task_ids.sort();
+
+        let mut ids = vec![header_id(&tasklist_id)];
+        ids.extend(self.agent_layout.header_ids(&tasklist_id));
+        ids.extend(
+            task_ids
+                .into_iter()
+                .map(|id| task_todo_id(&tasklist_id, id)),
+        );
+        ids
+    }
+
     /// Mark a task as known (synced to totui)
     pub fn mark_task_known(&mut self, task_id: &str) {
         self.known_tasks.insert(task_id.to_string());
@@ -109,18 +214,26 @@ impl SyncState {
         std::mem::take(&mut self.pending_commands)
     }
 
+    /// Whether the header's staleness text is out of date.
+    ///
+    /// Only applies once updates are being tracked.
+    pub fn staleness_display_changed(&self) -> bool {
+        self.staleness_tracker.is_tracking()
+            && self.staleness_tracker.format_staleness() != self.shown_staleness
+    }
+
     /// Check if there are pending commands to return.
     pub fn has_pending_commands(&self) -> bool {
         !self.pending_commands.is_empty()
     }
 }
 
-/// Thread-safe wrapper for SyncState.
-pub type SharedSyncState = Mutex<SyncState>;
+/// Thread-safe wrapper for SyncState, shared with the staleness ticker.
+pub type SharedSyncState = Arc<Mutex<SyncState>>;
 
 /// Create a new shared sync state with default values.
 pub fn new_shared_state() -> SharedSyncState {
-    Mutex::new(SyncState::default())
+    Arc::new(Mutex::new(SyncState::default()))
 }
 
 #[cfg(test)]
@@ -158,6 +271,37 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_staleness_display_changed() {
+        let mut state = SyncState {
+            staleness_tracker: StalenessTracker::new(0),
+            ..Default::default()
+        };
+        // Nothing to show before the first update
+        assert!(!state.staleness_display_changed());
+
+        state.staleness_tracker.record_update();
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        assert!(state.staleness_display_changed());
+
+        state.shown_staleness = state.staleness_tracker.format_staleness();
+        assert!(!state.staleness_display_changed());
+    }
+
+    #[test]
+    fn test_sync_stats() {
+        let mut stats = SyncStats::default();
+        stats.record_events(0);
+        assert_eq!(stats.last_event, None);
+
+        stats.record_events(3);
+        stats.record_commands(5);
+        stats.record_commands(2);
+        assert_eq!(stats.events, 3);
+        assert_eq!(stats.commands, 7);
+        assert!(stats.last_event.is_some());
+    }
+
     #[test]
     fn test_known_tasks_tracking() {
         let mut state = SyncState::default();
@@ -275,4 +419,67 @@ mod tests {
         let cmds = state.take_pending_commands();
         assert!(cmds.is_empty());
     }
+
+    #[test]
+    fn test_selected_tasklist_id() {
+        let mut state = SyncState::default();
+        assert_eq!(state.selected_tasklist_id(), None);
+
+        state.selected_tasklist = Some(PathBuf::from("/home/user/.claude/tasks/abc-123"));
+        assert_eq!(state.selected_tasklist_id().as_deref(), Some("abc-123"));
+
+        state.selected_tasklist =
+            Some(PathBuf::from("/home/user/.claude/todos/abc-agent-def.json"));
+        assert_eq!(
+            state.selected_tasklist_id().as_deref(),
+            Some("abc-agent-def")
+        );
+    }
+
+    #[test]
+    fn test_display_name_precedence() {
+        let mut state = SyncState::default();
+        state.config.task_roots = Some(vec!["/nonexistent/tasks".to_string()]);
+        assert_eq!(state.display_name("abc-123"), None);
+
+        // The project name only belongs to the selected tasklist
+        state.project_name = Some("my-repo [main]".to_string());
+        assert_eq!(state.display_name("abc-123"), None);
+
+        state.selected_tasklist = Some(PathBuf::from("/home/user/.claude/tasks/abc-123"));
+        assert_eq!(
+            state.display_name("abc-123").as_deref(),
+            Some("my-repo [main]")
+        );
+
+        // Manual alias wins over the derived project name
+        state
+            .config
+            .aliases
+            .insert("abc-123".to_string(), "Backend".to_string());
+        assert_eq!(state.display_name("abc-123").as_deref(), Some("Backend"));
+    }
+
+    #[test]
+    fn test_display_name_of_other_tasklist() {
+        let dir = tempfile::tempdir().unwrap();
+        let transcripts = dir.path().join("projects").join("-work-other-repo");
+        std::fs::create_dir_all(&transcripts).unwrap();
+        std::fs::write(
+            transcripts.join("def-456.jsonl"),
+            r#"{"cwd":"/work/other-repo","gitBranch":"dev"}"#,
+        )
+        .unwrap();
+
+        let mut state = SyncState::default();
+        let root = dir.path().join("tasks");
+        state.config.task_roots = Some(vec![root.to_string_lossy().to_string()]);
+        state.selected_tasklist = Some(root.join("abc-123"));
+        state.project_name = Some("my-repo [main]".to_string());
+
+        assert_eq!(
+            state.display_name("def-456").as_deref(),
+            Some("other-repo [dev]")
+        );
+    }
 }
@@ -0,0 +1,207 @@
+//! Status report rendered as temporary todos.
+//!
+//! The `status` prompt command shows what the plugin is doing as a small
+//! block of guidance-style todos. The IDs are tracked in state so the
+//! previous report is cleared when a new one is shown.
+//!
+//! Besides the selected tasklist and config files, the report carries sync
+//! diagnostics (see [`Diagnostics`]) so a sync that looks wrong can be
+//! checked without reading the log.
+
+use crate::metadata::TodoMetadata;
+use crate::staleness::format_duration;
+use crate::state::{GuidanceState, SyncStats};
+use crate::watcher::WatcherHealth;
+use abi_stable::std_types::{ROption, RString};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+use totui_plugin_interface::{FfiCommand, FfiTodoState};
+
+/// ID for the status header todo
+pub const STATUS_HEADER_ID: &str = "claude-status";
+
+/// Header text for the status report
+pub const MSG_STATUS_HEADER: &str = "CLAUDE TASKS - Status";
+
+/// Sync diagnostics of the selected tasklist.
+#[derive(Debug, Clone, Default)]
+pub struct Diagnostics {
+    /// Health of the tasklist watcher, None if it isn't running
+    pub watcher: Option<WatcherHealth>,
+    /// Tasks synced to totui
+    pub synced_tasks: usize,
+    /// Task files of the tasklist on disk
+    pub task_files: usize,
+    /// Task files that fail to parse
+    pub unreadable_files: Vec<PathBuf>,
+    /// Guidance currently shown
+    pub guidance: GuidanceState,
+    /// Commands waiting for the next host invocation
+    pub pending_commands: usize,
+    /// Event and command counters
+    pub stats: SyncStats,
+    /// Time since the last consistency check and what it found
+    pub drift_check: Option<(Duration, String)>,
+}
+
+/// Render diagnostics as status report lines.
+pub fn diagnostic_lines(diagnostics: &Diagnostics) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    lines.push(match &diagnostics.watcher {
+        Some(health) if health.errors > 0 => format!(
+            "Watcher: {}, {} errors (last: {})",
+            health.backend,
+            health.errors,
+            health.last_error.as_deref().unwrap_or("unknown")
+        ),
+        Some(health) => format!("Watcher: {}", health.backend),
+        None => "Watcher: not running".to_string(),
+    });
+
+    lines.push(format!(
+        "Tasks synced: {} of {} task files",
+        diagnostics.synced_tasks, diagnostics.task_files
+    ));
+    if !diagnostics.unreadable_files.is_empty() {
+        let names: Vec<String> = diagnostics
+            .unreadable_files
+            .iter()
+            .map(|p| {
+                p.file_name()
+                    .map_or_else(|| p.display().to_string(), |n| n.to_string_lossy().into())
+            })
+            .collect();
+        lines.push(format!("Unreadable task files: {}", names.join(", ")));
+    }
+
+    let last_event = diagnostics
+        .stats
+        .last_event
+        .and_then(|time| SystemTime::now().duration_since(time).ok())
+        .map_or("none yet".to_string(), |ago| {
+            format!("{} ago", format_duration(ago))
+        });
+    lines.push(format!("Last event: {}", last_event));
+    lines.push(format!("Guidance: {:?}", diagnostics.guidance));
+    lines.push(format!(
+        "Pending commands: {}",
+        diagnostics.pending_commands
+    ));
+    lines.push(format!(
+        "Since start: {} events, {} commands",
+        diagnostics.stats.events, diagnostics.stats.commands
+    ));
+    lines.push(match &diagnostics.drift_check {
+        Some((ago, summary)) => format!(
+            "Consistency check: {} ({} ago)",
+            summary,
+            format_duration(*ago)
+        ),
+        None => "Consistency check: none yet".to_string(),
+    });
+
+    lines
+}
+
+/// Create status report todos.
+///
+/// Displays:
+/// - Header: "CLAUDE TASKS - Status" (Question state)
+/// - One child per line
+///
+/// Returns the commands and the IDs of the created todos.
+pub fn create_status_commands(lines: &[String]) -> (Vec<FfiCommand>, Vec<String>) {
+    let mut commands = Vec::new();
+    let mut ids = vec![STATUS_HEADER_ID.to_string()];
+
+    commands.push(FfiCommand::CreateTodo {
+        content: RString::from(MSG_STATUS_HEADER),
+        parent_id: ROption::RNone,
+        temp_id: ROption::RSome(RString::from(STATUS_HEADER_ID)),
+        state: FfiTodoState::Question,
+        priority: ROption::RNone,
+        indent_level: 0,
+    });
+
+    for (i, line) in lines.iter().enumerate() {
+        let id = format!("{}-{}", STATUS_HEADER_ID, i);
+        commands.push(FfiCommand::CreateTodo {
+            content: RString::from(line.as_str()),
+            parent_id: ROption::RSome(RString::from(STATUS_HEADER_ID)),
+            temp_id: ROption::RSome(RString::from(id.clone())),
+            state: FfiTodoState::Empty,
+            priority: ROption::RNone,
+            indent_level: 1,
+        });
+        ids.push(id);
+    }
+
+    commands.push(TodoMetadata::guidance().set_command(STATUS_HEADER_ID));
+
+    (commands, ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_status_commands() {
+        let lines = vec!["Syncing: abc".to_string(), "Tasks: 3".to_string()];
+        let (cmds, ids) = create_status_commands(&lines);
+
+        // header + 2 lines + metadata
+        assert_eq!(cmds.len(), 4);
+        assert_eq!(
+            ids,
+            vec!["claude-status", "claude-status-0", "claude-status-1"]
+        );
+        match &cmds[1] {
+            FfiCommand::CreateTodo {
+                content, parent_id, ..
+            } => {
+                assert_eq!(content.as_str(), "Syncing: abc");
+                assert!(
+                    matches!(parent_id, ROption::RSome(ref p) if p.as_str() == STATUS_HEADER_ID)
+                );
+            }
+            _ => panic!("Expected CreateTodo command"),
+        }
+    }
+
+    #[test]
+    fn test_diagnostic_lines() {
+        let diagnostics = Diagnostics {
+            watcher: Some(WatcherHealth {
+                backend: crate::watcher::WatcherBackend::Polling,
+                errors: 2,
+                last_error: Some("inotify limit".to_string()),
+            }),
+            synced_tasks: 3,
+            task_files: 4,
+            unreadable_files: vec![PathBuf::from("/tasks/abc/4.json")],
+            pending_commands: 1,
+            stats: SyncStats {
+                events: 7,
+                commands: 12,
+                last_event: None,
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(
+            diagnostic_lines(&diagnostics),
+            vec![
+                "Watcher: polling, 2 errors (last: inotify limit)",
+                "Tasks synced: 3 of 4 task files",
+                "Unreadable task files: 4.json",
+                "Last event: none yet",
+                "Guidance: None",
+                "Pending commands: 1",
+                "Since start: 7 events, 12 commands",
+                "Consistency check: none yet",
+            ]
+        );
+    }
+}
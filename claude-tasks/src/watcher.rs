@@ -3,16 +3,60 @@
 //! Provides debounced file system watching for Claude tasklist directories.
 //! Events are sent via mpsc channel to the main plugin thread.
 
-use crate::state::SyncEvent;
-use crate::{plugin_debug, plugin_info, SharedNotifier};
-use notify::RecursiveMode;
-use notify_debouncer_full::{new_debouncer, DebounceEventResult, DebouncedEvent};
-use std::path::PathBuf;
+use crate::config::WatchMode;
+use crate::discovery::is_legacy_todo_file;
+use crate::session;
+use crate::staleness::StalenessLevel;
+use crate::state::{SharedSyncState, SyncEvent};
+use crate::{plugin_debug, plugin_error, plugin_trace, plugin_warn, SharedNotifier};
+use notify::{PollWatcher, RecommendedWatcher, RecursiveMode, Watcher};
+use notify_debouncer_full::{
+    new_debouncer_opt, DebounceEventHandler, DebounceEventResult, DebouncedEvent, RecommendedCache,
+};
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::thread::{self, JoinHandle};
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+/// What a watcher thread is watching with, for the status report.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum WatcherBackend {
+    /// The thread hasn't set up its watcher yet
+    #[default]
+    Starting,
+    /// Native file system events
+    Native,
+    /// Polling, as configured or because native watching failed
+    Polling,
+    /// No watcher could be set up
+    Failed(String),
+}
+
+impl std::fmt::Display for WatcherBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WatcherBackend::Starting => write!(f, "starting"),
+            WatcherBackend::Native => write!(f, "native events"),
+            WatcherBackend::Polling => write!(f, "polling"),
+            WatcherBackend::Failed(e) => write!(f, "failed ({})", e),
+        }
+    }
+}
+
+/// Health of a watcher thread, shared with its handle.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WatcherHealth {
+    /// Backend in use
+    pub backend: WatcherBackend,
+    /// Number of errors reported while watching
+    pub errors: usize,
+    /// Most recent error reported while watching
+    pub last_error: Option<String>,
+}
+
+type SharedHealth = Arc<Mutex<WatcherHealth>>;
 
 /// Handle to the watcher thread.
 ///
@@ -23,17 +67,25 @@ pub struct WatcherHandle {
     thread_handle: Option<JoinHandle<()>>,
     /// Flag to signal the thread to shut down
     shutdown_flag: Arc<AtomicBool>,
+    /// Health reported by the thread
+    health: SharedHealth,
 }
 
 impl WatcherHandle {
-    /// Create a new WatcherHandle with the given thread handle and shutdown flag.
-    fn new(handle: JoinHandle<()>, shutdown_flag: Arc<AtomicBool>) -> Self {
+    /// Create a new WatcherHandle with the given thread handle, shutdown flag and health.
+    fn new(handle: JoinHandle<()>, shutdown_flag: Arc<AtomicBool>, health: SharedHealth) -> Self {
         Self {
             thread_handle: Some(handle),
             shutdown_flag,
+            health,
         }
     }
 
+    /// Get the watcher's current health.
+    pub fn health(&self) -> WatcherHealth {
+        self.health.lock().unwrap().clone()
+    }
+
     /// Gracefully shut down the watcher thread.
     ///
     /// Signals the thread to stop and waits for it to finish.
@@ -63,16 +115,37 @@ impl std::fmt::Debug for WatcherHandle {
     }
 }
 
+/// How often the staleness ticker re-evaluates the header.
+const STALENESS_TICK: Duration = Duration::from_secs(15);
+
+/// Interval between checks for the session's process while the staleness
+/// level stays the same. Scanning all processes is too costly for every tick.
+const SESSION_CHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Poll interval used by [`WatchMode::Poll`] and the native fallback.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Translates debounced file system events into SyncEvents for one watcher.
+type Translate = Arc<dyn Fn(&DebouncedEvent) -> Option<SyncEvent> + Send + Sync>;
+
 /// Start a file watcher for the given tasklist directory.
 ///
 /// The watcher uses notify-debouncer-full with a 200ms timeout to batch
 /// rapid file system events. Events are translated to SyncEvent and sent
 /// through the provided mpsc channel.
 ///
+/// In [`WatchMode::Native`] the platform watcher is used, falling back to
+/// polling if the directory can't be watched natively (e.g., inotify limits
+/// or task roots on network file systems). [`WatchMode::Poll`] always polls.
+///
+/// Legacy TodoWrite files are watched through their folder, since the list
+/// is replaced on every write; only events for that file are forwarded.
+///
 /// # Arguments
 /// * `tasklist_path` - The directory to watch (e.g., ~/.claude/tasks/{uuid}/)
 /// * `tx` - Channel sender for SyncEvent notifications
 /// * `notifier` - Shared notifier to wake up the host immediately when events occur
+/// * `mode` - Native events or polling
 ///
 /// # Returns
 /// * `Ok(WatcherHandle)` - Handle to the watcher thread
@@ -81,81 +154,270 @@ pub fn start_watcher(
     tasklist_path: PathBuf,
     tx: mpsc::Sender<SyncEvent>,
     notifier: SharedNotifier,
+    mode: WatchMode,
 ) -> Result<WatcherHandle, String> {
-    let tx_for_debouncer = tx.clone();
-    let notifier_for_debouncer = notifier.clone();
-    let path_for_thread = tasklist_path.clone();
+    if !is_legacy_todo_file(&tasklist_path) {
+        let targets = vec![(tasklist_path, RecursiveMode::Recursive)];
+        return Ok(spawn_watcher(
+            targets,
+            mode,
+            tx,
+            notifier,
+            Arc::new(translate_event),
+        ));
+    }
 
+    // Events for the file are always reported as FileChanged; the sync
+    // engine checks whether it still exists
+    let folder = tasklist_path
+        .parent()
+        .ok_or_else(|| format!("No folder for {}", tasklist_path.display()))?
+        .to_path_buf();
+    let translate: Translate = Arc::new(move |event| {
+        event
+            .paths
+            .contains(&tasklist_path)
+            .then(|| SyncEvent::FileChanged(tasklist_path.clone()))
+    });
+
+    let targets = vec![(folder, RecursiveMode::NonRecursive)];
+    Ok(spawn_watcher(targets, mode, tx, notifier, translate))
+}
+
+/// Start a watcher for the plugin's config files.
+///
+/// Sends [`SyncEvent::ConfigChanged`] when any of `config_files` is
+/// created, changed or removed. The folders are watched rather than the
+/// files, since editors replace files on save and a file may not exist yet;
+/// folders that don't exist are skipped.
+pub fn start_config_watcher(
+    config_files: Vec<PathBuf>,
+    tx: mpsc::Sender<SyncEvent>,
+    notifier: SharedNotifier,
+    mode: WatchMode,
+) -> Result<WatcherHandle, String> {
+    let mut folders: Vec<PathBuf> = config_files
+        .iter()
+        .filter_map(|f| f.parent())
+        .filter(|d| d.is_dir())
+        .map(Path::to_path_buf)
+        .collect();
+    folders.dedup();
+    if folders.is_empty() {
+        return Err("No config folders to watch".to_string());
+    }
+
+    let translate: Translate = Arc::new(move |event| {
+        event
+            .paths
+            .iter()
+            .any(|p| config_files.contains(p))
+            .then_some(SyncEvent::ConfigChanged)
+    });
+
+    let targets = folders
+        .into_iter()
+        .map(|f| (f, RecursiveMode::NonRecursive))
+        .collect();
+    Ok(spawn_watcher(targets, mode, tx, notifier, translate))
+}
+
+/// Spawn a watcher thread for `targets`, with the native-to-polling fallback.
+fn spawn_watcher(
+    targets: Vec<(PathBuf, RecursiveMode)>,
+    mode: WatchMode,
+    tx: mpsc::Sender<SyncEvent>,
+    notifier: SharedNotifier,
+    translate: Translate,
+) -> WatcherHandle {
     // Create shutdown flag for graceful termination
     let shutdown_flag = Arc::new(AtomicBool::new(false));
     let shutdown_for_thread = shutdown_flag.clone();
+    let health = SharedHealth::default();
+    let thread_health = health.clone();
+    let set_backend = move |backend| thread_health.lock().unwrap().backend = backend;
+    let handler_health = health.clone();
 
     // Spawn the watcher thread
     let handle = thread::spawn(move || {
-        // Create debouncer with 200ms timeout
-        let debouncer_result = new_debouncer(
-            Duration::from_millis(200),
-            None,
-            move |result: DebounceEventResult| {
-                if let Ok(events) = result {
-                    let mut sent_any = false;
-                    for event in events {
-                        if let Some(sync_event) = translate_event(&event) {
-                            plugin_info!(
-                                "Watcher: File event detected: {}",
-                                match &sync_event {
-                                    SyncEvent::FileChanged(p) => format!("FileChanged({})", p.display()),
-                                    SyncEvent::FileRemoved(p) => format!("FileRemoved({})", p.display()),
-                                    SyncEvent::InitialScan => "InitialScan".to_string(),
-                                }
-                            );
-                            // Ignore send errors - receiver might be dropped
-                            let _ = tx_for_debouncer.send(sync_event);
-                            sent_any = true;
-                        }
-                    }
-                    // Wake up the host immediately after sending events
-                    if sent_any {
-                        plugin_debug!("Watcher: Calling notifier to wake host");
-                        if let Ok(guard) = notifier_for_debouncer.lock() {
-                            if let Some(n) = *guard {
-                                plugin_info!("Watcher: Notifier callback invoked");
-                                (n.func)();
-                            } else {
-                                plugin_debug!("Watcher: No notifier set yet");
-                            }
-                        }
-                    }
-                }
-            },
-        );
+        if mode == WatchMode::Native {
+            let result = watch_until_shutdown::<RecommendedWatcher>(
+                notify::Config::default(),
+                &targets,
+                make_handler(
+                    tx.clone(),
+                    notifier.clone(),
+                    translate.clone(),
+                    handler_health.clone(),
+                ),
+                &shutdown_for_thread,
+                || set_backend(WatcherBackend::Native),
+            );
+            match result {
+                Ok(()) => return,
+                Err(e) => plugin_warn!("Watcher: Native watch failed ({}), polling instead", e),
+            }
+        }
+
+        if let Err(e) = watch_until_shutdown::<PollWatcher>(
+            notify::Config::default().with_poll_interval(POLL_INTERVAL),
+            &targets,
+            make_handler(tx, notifier, translate, handler_health),
+            &shutdown_for_thread,
+            || set_backend(WatcherBackend::Polling),
+        ) {
+            plugin_error!("Watcher: Polling failed: {}", e);
+            set_backend(WatcherBackend::Failed(e.to_string()));
+        }
+    });
+
+    WatcherHandle::new(handle, shutdown_flag, health)
+}
+
+/// Watch `targets` with watcher type `T` until the shutdown flag is set.
+///
+/// Returns an error if the watcher couldn't be created or a path couldn't
+/// be watched; otherwise calls `on_watching` and blocks until shutdown.
+fn watch_until_shutdown<T: Watcher>(
+    config: notify::Config,
+    targets: &[(PathBuf, RecursiveMode)],
+    handler: impl DebounceEventHandler,
+    shutdown: &AtomicBool,
+    on_watching: impl FnOnce(),
+) -> Result<(), notify::Error> {
+    // Create debouncer with 200ms timeout
+    let mut debouncer = new_debouncer_opt::<_, T, _>(
+        Duration::from_millis(200),
+        None,
+        handler,
+        RecommendedCache::new(),
+        config,
+    )?;
+
+    for (path, recursive) in targets {
+        debouncer.watch(path, *recursive)?;
+    }
+    on_watching();
 
-        let mut debouncer = match debouncer_result {
-            Ok(d) => d,
-            Err(_) => {
-                return;
+    // Keep thread alive - debouncer needs to stay in scope
+    // Check shutdown flag every 100ms for graceful termination
+    while !shutdown.load(Ordering::SeqCst) {
+        thread::park_timeout(Duration::from_millis(100));
+    }
+
+    Ok(())
+}
+
+/// Build the debounced event handler: translate events, send them and wake the host.
+///
+/// Watch errors are logged and recorded in `health`.
+fn make_handler(
+    tx: mpsc::Sender<SyncEvent>,
+    notifier: SharedNotifier,
+    translate: Translate,
+    health: SharedHealth,
+) -> impl FnMut(DebounceEventResult) + Send + 'static {
+    move |result: DebounceEventResult| match result {
+        Err(errors) => {
+            let mut health = health.lock().unwrap();
+            for error in errors {
+                plugin_warn!("Watcher: {}", error);
+                health.errors += 1;
+                health.last_error = Some(error.to_string());
             }
-        };
+        }
+        Ok(events) => {
+            let mut sent_any = false;
+            for event in events {
+                if let Some(sync_event) = translate(&event) {
+                    plugin_debug!("Watcher: File event detected: {:?}", sync_event);
+                    // Ignore send errors - receiver might be dropped
+                    let _ = tx.send(sync_event);
+                    sent_any = true;
+                }
+            }
+            // Wake up the host immediately after sending events
+            if sent_any {
+                wake_host(&notifier);
+            }
+        }
+    }
+}
 
-        // Watch the tasklist directory
-        if debouncer
-            .watch(&path_for_thread, RecursiveMode::Recursive)
-            .is_err()
-        {
-            return;
+/// Call the host's notifier so it invokes the plugin for updates.
+fn wake_host(notifier: &SharedNotifier) {
+    plugin_debug!("Watcher: Calling notifier to wake host");
+    if let Ok(guard) = notifier.lock() {
+        if let Some(n) = *guard {
+            plugin_trace!("Watcher: Notifier callback invoked");
+            (n.func)();
+        } else {
+            plugin_debug!("Watcher: No notifier set yet");
         }
+    }
+}
+
+/// Start the staleness ticker.
+///
+/// Staleness is only evaluated when the host invokes the plugin, so without
+/// file events a quiet tasklist would never show as stale. Every
+/// [`STALENESS_TICK`] the ticker checks whether the header's staleness text
+/// is out of date and wakes the host if so; the header update itself is
+/// built when the host calls back.
+///
+/// Once the tasklist is no longer active, the ticker also checks whether
+/// the session's Claude Code process is still running (see [`crate::session`]).
+/// That check runs when the staleness level changes and otherwise every
+/// [`SESSION_CHECK_INTERVAL`].
+pub fn start_staleness_ticker(state: SharedSyncState, notifier: SharedNotifier) -> WatcherHandle {
+    let shutdown_flag = Arc::new(AtomicBool::new(false));
+    let shutdown_for_thread = shutdown_flag.clone();
+
+    let handle = thread::spawn(move || {
+        // Level and time of the last process check
+        let mut last_session_check: Option<(StalenessLevel, Instant)> = None;
 
-        // Keep thread alive - debouncer needs to stay in scope
-        // Check shutdown flag every 100ms for graceful termination
         loop {
+            // Shutdown unparks the thread; spurious wakeups only cause an extra check
+            thread::park_timeout(STALENESS_TICK);
             if shutdown_for_thread.load(Ordering::SeqCst) {
                 break;
             }
-            thread::park_timeout(Duration::from_millis(100));
+
+            // Scan processes without holding the state lock
+            let session_check = {
+                let state = state.lock().unwrap();
+                let level = state.staleness_tracker.level();
+                let due = last_session_check.is_none_or(|(checked, at)| {
+                    checked != level || at.elapsed() >= SESSION_CHECK_INTERVAL
+                });
+                let quiet = level > StalenessLevel::Active;
+                state
+                    .session_cwd
+                    .clone()
+                    .filter(|_| quiet && due)
+                    .map(|cwd| (cwd, level))
+            };
+            if let Some((cwd, level)) = session_check {
+                last_session_check = Some((level, Instant::now()));
+                if let Some(running) = session::session_running(&cwd) {
+                    state
+                        .lock()
+                        .unwrap()
+                        .staleness_tracker
+                        .set_session_ended(!running);
+                }
+            }
+
+            let changed = state.lock().unwrap().staleness_display_changed();
+            if changed {
+                plugin_debug!("Ticker: Staleness changed");
+                wake_host(&notifier);
+            }
         }
     });
 
-    Ok(WatcherHandle::new(handle, shutdown_flag))
+    WatcherHandle::new(handle, shutdown_flag, SharedHealth::default())
 }
 
 /// Translate a debounced file system event to a SyncEvent.
@@ -255,6 +517,7 @@ mod tests {
         let mut watcher = WatcherHandle {
             thread_handle: Some(handle),
             shutdown_flag: flag,
+            health: SharedHealth::default(),
         };
 
         // Shutdown should complete without hanging
@@ -4,6 +4,10 @@
 
 #![allow(non_local_definitions)]
 
+pub mod log;
+
+pub use plugin_log::{plugin_debug, plugin_error, plugin_info, plugin_trace, plugin_warn};
+
 use abi_stable::{
     export_root_module,
     prefix_type::PrefixTypeTrait,
@@ -63,6 +67,7 @@ impl JiraClaudePlugin {
 
     /// Run a command and return stdout.
     fn run_command(command: &str, args: &[&str]) -> Result<String, String> {
+        plugin_debug!("run_command: {} {}", command, args.first().unwrap_or(&""));
         let output = Command::new(command)
             .args(args)
             .output()
@@ -216,6 +221,7 @@ IMPORTANT: Respond ONLY with a JSON array, no other text. Format:
         Self::check_command_exists("claude")?;
 
         let ticket_id = input.trim().to_uppercase();
+        plugin_info!("generate: Fetching ticket {}", ticket_id);
 
         let ticket = self
             .fetch_jira_ticket(&ticket_id)
@@ -232,6 +238,12 @@ IMPORTANT: Respond ONLY with a JSON array, no other text. Format:
             ));
         }
 
+        plugin_info!(
+            "generate: Claude generated {} todos for {}",
+            generated.len(),
+            ticket_id
+        );
+
         let ticket_url = self
             .fetch_jira_base_url()
             .map(|base| format!("{}/browse/{}", base, ticket.key))
@@ -312,7 +324,10 @@ impl Plugin for JiraClaudePlugin {
     fn generate(&self, input: RString) -> RResult<RVec<FfiTodoItem>, RString> {
         match self.generate_from_ticket(input.as_str()) {
             Ok(items) => RResult::ROk(items.into_iter().collect()),
-            Err(e) => RResult::RErr(e.into()),
+            Err(e) => {
+                plugin_error!("generate: {}", e);
+                RResult::RErr(e.into())
+            }
         }
     }
 
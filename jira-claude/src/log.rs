@@ -0,0 +1,37 @@
+//! Leveled, rotating file log for plugin debugging.
+//!
+//! Messages go to `{cache_dir}/jira-claude-plugin.log` by default; see the shared
+//! `plugin-log` crate for levels, formats and rotation.
+//!
+//! Level, format and file are set with the `JIRA_CLAUDE_LOG`,
+//! `JIRA_CLAUDE_LOG_FORMAT` and `JIRA_CLAUDE_LOG_FILE` environment variables.
+
+use std::fmt;
+use std::path::PathBuf;
+
+pub use plugin_log::{Level, LogFormat, LogSettings, KEPT_LOGS, MAX_LOG_BYTES};
+
+static LOG: plugin_log::PluginLog =
+    plugin_log::PluginLog::new("jira-claude", env!("CARGO_PKG_VERSION"), "JIRA_CLAUDE_LOG");
+
+/// Apply logging settings, e.g. after the config was (re)loaded.
+pub fn configure(settings: LogSettings) {
+    LOG.configure(settings)
+}
+
+/// Path of the log file currently written to.
+pub fn log_path() -> PathBuf {
+    LOG.log_path()
+}
+
+/// Whether messages at `level` are written.
+pub fn enabled(level: Level) -> bool {
+    LOG.enabled(level)
+}
+
+/// Log a message to the plugin log file if `level` is enabled.
+///
+/// Called by the `plugin_*!` macros.
+pub fn log(level: Level, args: fmt::Arguments) {
+    LOG.log(level, args)
+}
@@ -0,0 +1,492 @@
+//! Leveled, rotating file log for totui plugins.
+//!
+//! Plugins don't share the host's tracing subscriber, so messages go to a
+//! log file of their own, `{cache_dir}/{plugin}-plugin.log` by default.
+//! Messages up to the configured level are written (default: info), either
+//! as text lines or as JSON lines for machine analysis. Once the file grows
+//! past [`MAX_LOG_BYTES`] it is rotated to `.1`, `.2`, ..., keeping
+//! [`KEPT_LOGS`] old files.
+//!
+//! Each plugin declares one [`PluginLog`] with its name and environment
+//! variable prefix, and wraps it in a `log` module with a `log` function,
+//! which the `plugin_*!` macros call:
+//!
+//! ```ignore
+//! static LOG: PluginLog = PluginLog::new("my-plugin", env!("CARGO_PKG_VERSION"), "MY_PLUGIN_LOG");
+//!
+//! pub fn log(level: Level, args: std::fmt::Arguments) {
+//!     LOG.log(level, args)
+//! }
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Size at which the log file is rotated.
+pub const MAX_LOG_BYTES: u64 = 1024 * 1024;
+/// Number of rotated log files kept.
+pub const KEPT_LOGS: usize = 3;
+
+/// Log level, from least to most verbose.
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    /// Nothing is logged
+    Off,
+    /// Failures the plugin can't recover from
+    Error,
+    /// Problems the plugin works around
+    Warn,
+    /// Notable events
+    #[default]
+    Info,
+    /// Detail for debugging
+    Debug,
+    /// Everything, including per-event detail
+    Trace,
+}
+
+impl Level {
+    /// Parse a level name, ignoring case.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "off" => Some(Level::Off),
+            "error" => Some(Level::Error),
+            "warn" | "warning" => Some(Level::Warn),
+            "info" => Some(Level::Info),
+            "debug" => Some(Level::Debug),
+            "trace" => Some(Level::Trace),
+            _ => None,
+        }
+    }
+
+    /// Lowercase level name.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Level::Off => "off",
+            Level::Error => "error",
+            Level::Warn => "warn",
+            Level::Info => "info",
+            Level::Debug => "debug",
+            Level::Trace => "trace",
+        }
+    }
+
+    fn from_u8(value: u8) -> Self {
+        match value {
+            0 => Level::Off,
+            1 => Level::Error,
+            2 => Level::Warn,
+            3 => Level::Info,
+            4 => Level::Debug,
+            _ => Level::Trace,
+        }
+    }
+}
+
+/// Format of log lines.
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// `2026-01-31T12:00:00.000Z INFO  message`
+    #[default]
+    Text,
+    /// One JSON object per line with `ts`, `level`, `plugin` and `msg`
+    Json,
+}
+
+impl LogFormat {
+    /// Parse a format name, ignoring case.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "text" => Some(LogFormat::Text),
+            "json" => Some(LogFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Logging settings from the config; unset values use the defaults.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LogSettings {
+    /// Most verbose level written (default: info)
+    pub level: Option<Level>,
+    /// Line format (default: text)
+    pub format: Option<LogFormat>,
+    /// Log file (default: `{cache_dir}/{plugin}-plugin.log`)
+    pub file: Option<PathBuf>,
+}
+
+/// Log file of one plugin.
+pub struct PluginLog {
+    /// Plugin name, used for the default log file and JSON entries
+    name: &'static str,
+    /// Plugin version, written to the banner at startup
+    version: &'static str,
+    /// Environment variable setting the level; `_FORMAT` and `_FILE`
+    /// variants set the format and file
+    env_prefix: &'static str,
+    /// Current level, kept outside the lock so disabled messages cost nothing
+    level: AtomicU8,
+    logger: OnceLock<Mutex<Logger>>,
+}
+
+struct Logger {
+    format: LogFormat,
+    path: PathBuf,
+    /// Whether this process has written to the log yet
+    started: bool,
+}
+
+impl PluginLog {
+    /// Declare the log of plugin `name`, configured by the `{env_prefix}`,
+    /// `{env_prefix}_FORMAT` and `{env_prefix}_FILE` environment variables.
+    pub const fn new(name: &'static str, version: &'static str, env_prefix: &'static str) -> Self {
+        Self {
+            name,
+            version,
+            env_prefix,
+            level: AtomicU8::new(Level::Info as u8),
+            logger: OnceLock::new(),
+        }
+    }
+
+    fn logger(&self) -> std::sync::MutexGuard<'_, Logger> {
+        self.logger
+            .get_or_init(|| {
+                let mut logger = Logger {
+                    format: LogFormat::Text,
+                    path: PathBuf::new(),
+                    started: false,
+                };
+                self.apply(&mut logger, LogSettings::default());
+                Mutex::new(logger)
+            })
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Apply settings, letting the environment variables override them.
+    fn apply(&self, logger: &mut Logger, settings: LogSettings) {
+        let env = |suffix: &str| {
+            std::env::var(format!("{}{}", self.env_prefix, suffix))
+                .ok()
+                .filter(|value| !value.trim().is_empty())
+        };
+
+        let level = env("")
+            .and_then(|value| Level::parse(&value))
+            .or(settings.level)
+            .unwrap_or_default();
+        self.level.store(level as u8, Ordering::Relaxed);
+
+        logger.format = env("_FORMAT")
+            .and_then(|value| LogFormat::parse(&value))
+            .or(settings.format)
+            .unwrap_or_default();
+
+        let path = env("_FILE")
+            .map(PathBuf::from)
+            .or(settings.file)
+            .unwrap_or_else(|| default_log_path(self.name));
+        if path != logger.path {
+            logger.path = path;
+            logger.started = false;
+        }
+    }
+
+    /// Apply logging settings, e.g. after the config was (re)loaded.
+    pub fn configure(&self, settings: LogSettings) {
+        let mut logger = self.logger();
+        self.apply(&mut logger, settings);
+    }
+
+    /// Path of the log file currently written to.
+    pub fn log_path(&self) -> PathBuf {
+        self.logger().path.clone()
+    }
+
+    /// Whether messages at `level` are written.
+    pub fn enabled(&self, level: Level) -> bool {
+        // The environment is applied when the logger is set up; after that
+        // the level is read without locking
+        if self.logger.get().is_none() {
+            drop(self.logger());
+        }
+        level != Level::Off && level <= Level::from_u8(self.level.load(Ordering::Relaxed))
+    }
+
+    /// Log a message to the plugin log file if `level` is enabled.
+    pub fn log(&self, level: Level, args: fmt::Arguments) {
+        if !self.enabled(level) {
+            return;
+        }
+        let mut logger = self.logger();
+        if !logger.started {
+            logger.started = true;
+            let banner = format!(
+                "=== {} {} started (pid {}) ===",
+                self.name,
+                self.version,
+                std::process::id()
+            );
+            logger.write_line(self.name, Level::Info, &banner);
+        }
+        logger.write_line(self.name, level, &args.to_string());
+    }
+}
+
+impl Logger {
+    fn write_line(&self, plugin: &str, level: Level, message: &str) {
+        let line = format_line(self.format, SystemTime::now(), plugin, level, message);
+        rotate_if_full(&self.path, MAX_LOG_BYTES, KEPT_LOGS);
+        if let Ok(mut file) = OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(&self.path)
+        {
+            let _ = writeln!(file, "{}", line);
+        }
+    }
+}
+
+fn default_log_path(plugin: &str) -> PathBuf {
+    dirs::cache_dir()
+        .unwrap_or_else(|| PathBuf::from("/tmp"))
+        .join(format!("{}-plugin.log", plugin))
+}
+
+/// Format one log line (without the newline).
+fn format_line(
+    format: LogFormat,
+    time: SystemTime,
+    plugin: &str,
+    level: Level,
+    message: &str,
+) -> String {
+    let timestamp = format_timestamp(time);
+    match format {
+        LogFormat::Text => format!(
+            "{} {:<5} {}",
+            timestamp,
+            level.as_str().to_ascii_uppercase(),
+            message
+        ),
+        LogFormat::Json => serde_json::json!({
+            "ts": timestamp,
+            "level": level.as_str(),
+            "plugin": plugin,
+            "msg": message,
+        })
+        .to_string(),
+    }
+}
+
+/// Rotate `path` to `path.1` (shifting older files up) once it reaches
+/// `max_bytes`; the oldest beyond `keep` files is dropped.
+fn rotate_if_full(path: &Path, max_bytes: u64, keep: usize) {
+    let full = std::fs::metadata(path).is_ok_and(|m| m.len() >= max_bytes);
+    if !full {
+        return;
+    }
+    if keep == 0 {
+        let _ = std::fs::remove_file(path);
+        return;
+    }
+    for n in (1..keep).rev() {
+        let _ = std::fs::rename(rotated_path(path, n), rotated_path(path, n + 1));
+    }
+    let _ = std::fs::rename(path, rotated_path(path, 1));
+}
+
+fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+/// Format a time as an RFC 3339 UTC timestamp with milliseconds.
+fn format_timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let secs_of_day = secs % 86_400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+/// Convert days since 1970-01-01 into a (year, month, day) date.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    // Howard Hinnant's algorithm, with eras of 400 years starting in March
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+// The macros call the `log` function of the invoking plugin's `log`
+// module, which passes the message on to that plugin's `PluginLog`.
+
+/// Log an error message.
+#[macro_export]
+#[allow(clippy::crate_in_macro_def)]
+macro_rules! plugin_error {
+    ($($arg:tt)*) => {
+        crate::log::log($crate::Level::Error, format_args!($($arg)*))
+    };
+}
+
+/// Log a warning message.
+#[macro_export]
+#[allow(clippy::crate_in_macro_def)]
+macro_rules! plugin_warn {
+    ($($arg:tt)*) => {
+        crate::log::log($crate::Level::Warn, format_args!($($arg)*))
+    };
+}
+
+/// Log an info message.
+#[macro_export]
+#[allow(clippy::crate_in_macro_def)]
+macro_rules! plugin_info {
+    ($($arg:tt)*) => {
+        crate::log::log($crate::Level::Info, format_args!($($arg)*))
+    };
+}
+
+/// Log a debug message.
+#[macro_export]
+#[allow(clippy::crate_in_macro_def)]
+macro_rules! plugin_debug {
+    ($($arg:tt)*) => {
+        crate::log::log($crate::Level::Debug, format_args!($($arg)*))
+    };
+}
+
+/// Log a trace message.
+#[macro_export]
+#[allow(clippy::crate_in_macro_def)]
+macro_rules! plugin_trace {
+    ($($arg:tt)*) => {
+        crate::log::log($crate::Level::Trace, format_args!($($arg)*))
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_level_parse_and_order() {
+        assert_eq!(Level::parse("DEBUG"), Some(Level::Debug));
+        assert_eq!(Level::parse(" warning "), Some(Level::Warn));
+        assert_eq!(Level::parse("loud"), None);
+        assert!(Level::Error < Level::Info && Level::Info < Level::Trace);
+        assert_eq!(Level::from_u8(Level::Debug as u8), Level::Debug);
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+        // 2024-02-29 12:34:56.789 UTC
+        let leap_day = UNIX_EPOCH + Duration::from_millis(1_709_210_096_789);
+        assert_eq!(format_timestamp(leap_day), "2024-02-29T12:34:56.789Z");
+    }
+
+    #[test]
+    fn test_format_line() {
+        let text = format_line(
+            LogFormat::Text,
+            UNIX_EPOCH,
+            "demo",
+            Level::Warn,
+            "disk full",
+        );
+        assert_eq!(text, "1970-01-01T00:00:00.000Z WARN  disk full");
+
+        let json = format_line(
+            LogFormat::Json,
+            UNIX_EPOCH,
+            "demo",
+            Level::Info,
+            "say \"hi\"",
+        );
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["level"], "info");
+        assert_eq!(value["plugin"], "demo");
+        assert_eq!(value["msg"], "say \"hi\"");
+    }
+
+    #[test]
+    fn test_plugin_log_writes_banner_and_messages() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("demo.log");
+        let log = PluginLog::new("demo", "1.2.3", "PLUGIN_LOG_TEST");
+        log.configure(LogSettings {
+            level: Some(Level::Warn),
+            format: None,
+            file: Some(path.clone()),
+        });
+        assert_eq!(log.log_path(), path);
+
+        log.log(Level::Info, format_args!("hidden"));
+        log.log(Level::Warn, format_args!("shown {}", 1));
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 2, "{:?}", lines);
+        assert!(lines[0].contains("=== demo 1.2.3 started"));
+        assert!(lines[1].ends_with("WARN  shown 1"));
+    }
+
+    #[test]
+    fn test_rotate_if_full() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("plugin.log");
+        let write = |path: &Path, content: &str| std::fs::write(path, content).unwrap();
+        let read = |path: PathBuf| std::fs::read_to_string(path).ok();
+
+        write(&path, "small");
+        rotate_if_full(&path, 10, 2);
+        assert_eq!(read(path.clone()).as_deref(), Some("small"));
+
+        write(&path, "first file");
+        rotate_if_full(&path, 10, 2);
+        write(&path, "second file");
+        rotate_if_full(&path, 10, 2);
+        write(&path, "third file");
+        rotate_if_full(&path, 10, 2);
+
+        assert!(!path.exists());
+        assert_eq!(read(rotated_path(&path, 1)).as_deref(), Some("third file"));
+        assert_eq!(read(rotated_path(&path, 2)).as_deref(), Some("second file"));
+        assert!(!rotated_path(&path, 3).exists());
+    }
+}